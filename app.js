@@ -1,14 +1,17 @@
 "use strict";
 
-const chalk = require('chalk');
+const logger = require('./lib/logger');
 let config;
 try {
-	config = require('./config');
+	config = require('./lib/config');
 } catch (err) {
-	console.error(chalk.bgRed.bold('Error:') + ' config not found. Please create `./config.js` based on the `./config.js.template`.');
+	if (err.code === 'MODULE_NOT_FOUND') logger.error('config not found. Please create `./config.js` based on the `./config.js.template`.');
+	else logger.error('Invalid config: ' + err.message);
 	return;
 }
 
+const fs = require('fs');
+const path = require('path');
 const express = require('express');
 const app = express();
 const bodyParser = require('body-parser');
@@ -19,19 +22,100 @@ const escape = require('escape-html');
 const CustomSocketServer = require('./lib/custom-socket-server');
 const IcmpEchoLogger = require('./lib/icmp-echo-logger');
 const helper = require('./lib/helper');
+const api = require('./lib/api');
 const tcp_ports = require('./lib/tcp-ports');
+const RateLimiter = require('./lib/rate-limiter');
+const webAuth = require('./lib/web-auth');
+const cors = require('./lib/cors');
+const enrichers = require('./lib/enrichers');
+const cidr = require('./lib/cidr');
+const httpAnomalies = require('./lib/http-anomalies');
+const protocolDetector = require('./lib/protocol-detector');
+const exploitClassifier = require('./lib/exploit-classifier');
+const adminPanels = require('./lib/admin-panels');
+const Deduplicator = require('./lib/deduplicator');
+const AbuseIpDbReporter = require('./lib/abuseipdb');
+const SampleTracker = require('./lib/samples');
+const redact = require('./lib/redact');
+const SyslogSender = require('./lib/syslog');
+const DeliveryQueue = require('./lib/delivery-queue');
+const requestPort = require('./lib/request-port');
 
 let data = [];
 let monthly_stats;
+let monthly_stats_without_scanners;
 let total_requests_number = 0;
 let recent_credentials = null;
+let socket_servers = [];
+// Requests since the last `stats` message and since the midnight (or the start of the app)
+let live_stats = {'requests': 0, 'today': 0, 'day': new Date().toDateString()};
+// Requests per service since the start, @see /metrics
+let service_requests = {};
+// `event_id` of the last item, microseconds since the epoch at most, so that the ids keep growing across the restarts
+let last_event_id = Date.now() * 1000;
+// Last items sent to the live feed, replayed to the clients reconnecting with the `last_event_id` they saw
+const REPLAY_EVENTS = 1000;
+let replay = [];
+let database_size = null;
 
+/* Socket.io WebSocket Server: the browser sends the same Authorization header as for the dashboard */
+io.use((socket, next) => {
+	if (webAuth.isAuthorized(socket.request.headers['authorization'])) return next();
+	next(new Error('Unauthorized'));
+});
+/**
+ * Filter of the WebSocket client, either from the query, e.g. io('/?exclude_scanners=true') skips the known scanners,
+ * or changed at any time with socket.emit('filter', {'service': 'ssh', 'ip': '192.0.2.0/24'})
+ * The `ip` addresses/subnets (comma-separated) are parsed here, once, rather than for every item
+ * @param filter
+ */
+const parseFilter = (filter) => {
+	let networks = null;
+	if (typeof filter.ip === 'string' && filter.ip.trim().length !== 0) {
+		try {
+			networks = cidr.ranges(filter.ip.split(',').map(cidr.parse));
+		} catch (err) {
+			// Nothing matches an invalid address, rather than the whole feed
+			networks = cidr.ranges([]);
+		}
+	}
+
+	return {
+		'service': typeof filter.service === 'string' ? filter.service : '',
+		'exclude_scanners': filter.exclude_scanners === true || filter.exclude_scanners === 'true',
+		'priority': filter.priority === true || filter.priority === 'true',
+		'networks': networks
+	};
+};
+/**
+ * Whether the item should be sent to the WebSocket client
+ * @param socket
+ * @param item
+ */
+const matchesFilter = (socket, item) => {
+	let filter = socket.filter;
+	if (filter.exclude_scanners && item.scanner_name) return false;
+	if (filter.service && item.service !== filter.service) return false;
+	if (filter.priority && !item.priority) return false;
+	if (filter.networks !== null && !cidr.contains(filter.networks, item.ip)) return false;
+
+	return true;
+};
 /* Socket.io WebSocket Server: on connection */
 io.on('connection', (socket) => {
-	socket.emit('init', {
-		'data': data,
+	socket.filter = parseFilter(socket.handshake.query);
+	// Reconnecting after a network blip: only the items missed in the meantime, `resumed` is false when some are gone
+	let since = Number(socket.handshake.query.last_event_id);
+	let resumed = since > 0 && replay.length !== 0 && replay[0].event_id <= since;
+	socket.emit('init', redact({
+		'data': (resumed ? replay.filter((item) => item.event_id > since) : data).filter((item) => matchesFilter(socket, item)),
+		'resumed': resumed,
 		'total_requests_number': total_requests_number,
 		'recent_credentials': recent_credentials
+	}));
+	socket.on('filter', (filter) => {
+		if (!filter || typeof filter !== 'object') return;
+		socket.filter = parseFilter(filter);
 	});
 });
 
@@ -40,15 +124,48 @@ io.on('connection', (socket) => {
  * @see: ./lib/tcp-ports
  */
 for (let port in tcp_ports) {
-	(CustomSocketServer(port, tcp_ports[port])).on('data', (data) => {
+	// `enabled_ports` is an allowlist when not empty, e.g. when co-hosting with other services
+	if (config.enabled_ports.length !== 0 && !config.enabled_ports.includes(Number(port))) continue;
+	if (config.disabled_ports.includes(Number(port))) continue;
+	socket_servers.push((CustomSocketServer(port, tcp_ports[port])).on('data', (data) => {
 		emitData(data);
-	});
+	}));
 }
+/* A port taken by another service (e.g. the real MySQL) is easy to miss among the warnings, hence the summary */
+Promise.all(socket_servers.map((socket_server) => new Promise((resolve) => socket_server.once('bound', resolve)))).then(() => {
+	let failed = socket_servers.filter((socket_server) => socket_server.listening === 0).map((socket_server) => socket_server.port);
+	let summary = 'Listening on ' + (socket_servers.length - failed.length) + '/' + socket_servers.length + ' ports';
+	if (failed.length !== 0) logger.warning(summary + ', ' + failed.length + ' failed: [' + failed.join(', ') + ']');
+	else logger.info(summary);
+});
+
+/* Connections over the per-IP limit are dropped, only a periodic summary is logged */
+CustomSocketServer.connectionLimiter.on('summary', (ip, service, dropped) => {
+	emitData({
+		'ip': ip,
+		'service': service,
+		'request': dropped + ' connections dropped from ' + ip + ' (more than ' + config.max_connections_per_ip + ' concurrent connections)'
+	});
+});
+
+/* Many distinct ports connected to by the same IP address are logged once more as a port scan */
+CustomSocketServer.portScanDetector.on('scan', (ip, ports, duration_ms) => {
+	emitData({
+		'ip': ip,
+		'service': 'portscan',
+		'request': 'Port scan from ' + ip + ': ' + ports.length + ' ports in ' + Math.round(duration_ms / 1000) + ' seconds',
+		'request_headers': 'ports: ' + ports.join(', ') + '\r\n',
+		'duration_ms': duration_ms
+	});
+});
 
 /* Catching ICMP echo requests (ping) using tcpdump */
-const ping = new IcmpEchoLogger().on('data', (data) => {
+const ping = new IcmpEchoLogger('ipv4').on('data', (data) => {
 	emitData(data);
 });
+const ping6 = config.icmpv6_capture ? new IcmpEchoLogger('ipv6').on('data', (data) => {
+	emitData(data);
+}) : null;
 
 /* MySQL Helper */
 (new helper.Mysql()).on('total_requests_number', (count) => {
@@ -56,43 +173,137 @@ const ping = new IcmpEchoLogger().on('data', (data) => {
 }).on('recent_credentials', (rows) => {
 	// Returns recent SSH/FTP usernames/passwords
 	recent_credentials = rows;
-}).on('monthly_stats', (stats) => {
+}).on('monthly_stats', (stats, stats_without_scanners) => {
 	monthly_stats = stats;
+	monthly_stats_without_scanners = stats_without_scanners;
+}).on('database_size', (size) => {
+	database_size = size;
+});
+
+/* Per-IP rate limit for the HTTP requests: once exceeded, only a periodic summary is logged */
+const rateLimiter = new RateLimiter(config.http_rate_limit.requests_per_second, config.http_rate_limit.burst, config.http_rate_limit.summary_interval).on('summary', (ip, suppressed) => {
+	emitData({
+		'ip': ip,
+		'service': 'http',
+		'request': suppressed + ' requests suppressed from ' + helper.formatIpAddress(ip)
+	});
+});
+
+/* Repeated HTTP requests (same IP, method and path) are collapsed into one with a count */
+const deduplicator = new Deduplicator(config.http_dedup_window).on('data', (item) => {
+	emitData(item);
 });
 
+/* Retrying the deliveries of the integrations below that failed */
+const deliveryQueue = new DeliveryQueue(config.delivery_queue);
+
+/* Reporting the attackers to AbuseIPDB */
+const abuseIpDb = config.abuseipdb.api_key ? new AbuseIpDbReporter(config.abuseipdb, deliveryQueue) : null;
+const samples = new SampleTracker(config.samples);
+
+/* Forwarding the requests to the SIEM */
+const syslog = config.syslog.host ? new SyslogSender(config.syslog, deliveryQueue) : null;
+
 /* Express App */
-if (config.nginx_reverse_proxy) app.enable('trust proxy', 1);
-app.use(helmet());
+// X-Forwarded-* headers are honored only when the connection comes from one of the trusted proxies,
+// otherwise anyone could spoof their IP address by sending the header directly
+if (config.nginx_reverse_proxy) app.set('trust proxy', config.trusted_proxies);
+// Framing is left to the `frame-ancestors` of the CORS origins when there are any
+app.use(helmet(config.cors_allowed_origins.length !== 0 ? {'frameguard': false} : {}));
 app.set('view engine', 'ejs');
-app.set('views', './view');
+// Relative to the app rather than the working directory, so that it can be started from anywhere
+app.set('views', path.join(__dirname, 'view'));
+['static', 'view'].forEach((directory) => {
+	if (!fs.existsSync(path.join(__dirname, directory))) logger.warning('`' + directory + '` directory is missing, the dashboard will not be available.');
+});
 app.use(bodyParser.urlencoded({ extended: true }));
+// Probes of Docker / Kubernetes, registered before the logging middleware so that they don't show up as attacks
+app.get('/healthz', (req, res) => {
+	res.json({'status': 'ok', 'uptime': Math.round(process.uptime())});
+});
+app.get('/readyz', (req, res) => {
+	helper.mysqlPool.query('SELECT 1', (error) => {
+		if (error) return res.status(503).json({'status': 'unavailable', 'error': error.code || error.message});
+		res.json({'status': 'ok'});
+	});
+});
+// Prometheus metrics: which ports are listened on, their open connections and the requests since the start
+app.get('/metrics', webAuth.middleware, (req, res) => {
+	let lines = [
+		'# HELP honeypot_port_up Whether the port is listened on (0 when taken by another service or not permitted)',
+		'# TYPE honeypot_port_up gauge'
+	];
+	socket_servers.forEach((socket_server) => {
+		lines.push('honeypot_port_up{port="' + socket_server.port + '",service="' + socket_server.name + '"} ' + (socket_server.listening !== 0 ? 1 : 0));
+	});
+	lines.push('# HELP honeypot_active_connections Open connections per port', '# TYPE honeypot_active_connections gauge');
+	socket_servers.forEach((socket_server) => {
+		lines.push('honeypot_active_connections{port="' + socket_server.port + '",service="' + socket_server.name + '"} ' + socket_server.connections);
+	});
+	lines.push('# HELP honeypot_requests_total Requests per service since the start', '# TYPE honeypot_requests_total counter');
+	Object.keys(service_requests).forEach((service) => {
+		lines.push('honeypot_requests_total{service="' + service + '"} ' + service_requests[service]);
+	});
+	if (database_size !== null) {
+		lines.push('# HELP honeypot_database_size_bytes Size of the MySQL tables and indexes', '# TYPE honeypot_database_size_bytes gauge');
+		lines.push('honeypot_database_size_bytes ' + database_size);
+	}
+	res.type('text/plain; version=0.0.4').send(lines.join('\n') + '\n');
+});
 app.use((req, res, next) => {
+	let headers = req.headers;
+	// Not leaking the dashboard token to the feed
+	if (config.dashboard_auth_token && headers['authorization'] && webAuth.isAuthorized(headers['authorization'])) headers = Object.assign({}, headers, {'authorization': '[redacted]'});
+	CustomSocketServer.portScanDetector.record(helper.formatIpAddress(req.ip), requestPort(req));
 	let item = {
 		'ip': req.ip,
 		'service': req.protocol,
 		'request': req.method + ' ' + req.originalUrl,
 		'http_request_path': req.originalUrl,
-		'request_headers': helper.formatHeaders(req.headers)
+		'request_headers': helper.formatHeaders(headers),
+		'user_agent': req.headers['user-agent'],
+		'port': requestPort(req)
 	};
-	if (req.hostname !== config.hostname || (req.protocol === 'http' && config.https_only)) {
+	let exploit = exploitClassifier.classify(req.originalUrl, item.request_headers + (Object.keys(req.body || {}).length !== 0 ? '\r\n' + helper.formatHeaders(req.body) : ''));
+	if (exploit !== null) item.exploit = exploit;
+	let malformed = httpAnomalies.smuggling(req.rawHeaders);
+	if (malformed !== null) item.malformed = malformed;
+	// Logins to the fake admin panels are saved as credentials
+	let credentials = req.method === 'POST' && adminPanels.match(req.path) !== null ? adminPanels.credentials(req.body) : null;
+	if (credentials !== null) Object.assign(item, credentials);
+	if (config.emulation.web_server_header) res.set('Server', config.emulation.web_server_header);
+	if ((req.hostname !== config.hostname && config.emulation.redirect_unknown_hosts) || (req.protocol === 'http' && config.https_only)) {
 		if (req.hostname) item.request = req.method + ' ' + req.protocol + '://' + req.hostname + req.originalUrl;
-		emitData(item);
+		if (rateLimiter.take(req.ip)) deduplicator.add(item, req.method);
 		res.redirect((config.https_only ? 'https' : 'http') + '://' + config.hostname + req.originalUrl);
 	}
 	else {
-		emitData(item);
+		if (rateLimiter.take(req.ip)) deduplicator.add(item, req.method);
 		next()
 	}
 });
-app.use(express.static('static'));
-app.get('/', (req, res) => {
+app.use(express.static(path.join(__dirname, 'static')));
+app.get('/', cors.frameAncestors, webAuth.middleware, (req, res) => {
 	res.sendFile('view/index.html' , {root: __dirname, lastModified: false, headers: {'Cache-Control': 'no-cache, no-store, must-revalidate', 'Expires': '0'}});
 });
-app.get('/stats', (req, res) => {
-	res.render('stats', {data: monthly_stats})
+app.get('/stats', cors.frameAncestors, webAuth.middleware, (req, res) => {
+	// Missing template (see the warning at the start) is a 404 rather than an error page with the stack trace
+	let stats = req.query.exclude_scanners === 'true' ? monthly_stats_without_scanners : monthly_stats;
+	res.render('stats', {data: redact(stats)}, (err, html) => {
+		if (err) return res.sendStatus(err.message.startsWith('Failed to lookup view') ? 404 : 500);
+		res.send(html);
+	});
 });
+app.use('/api', cors.middleware, webAuth.middleware, api);
 app.all('*', (req, res) => {
-	if (req.hostname === config.hostname || req.hostname === config.server_ip) {
+	let panel = adminPanels.match(req.path);
+	if (panel !== null) {
+		res.status(200).send(panel);
+	}
+	else if (config.emulation.catch_all === 'error') {
+		res.status(500).send('<html><head><title>500 Internal Server Error</title></head><body><h1>Internal Server Error</h1></body></html>');
+	}
+	else if (config.emulation.catch_all === 'echo' && (req.hostname === config.hostname || req.hostname === config.server_ip || !config.emulation.redirect_unknown_hosts)) {
 		let response = req.hostname ? req.method + ' ' + req.protocol + '://' + req.hostname + req.originalUrl : req.method + ' ' + req.originalUrl;
 		if (req.body.length !== 0) response+= "\r\n\r\n" + helper.formatHeaders(req.body);
 		res.status(200).send("<pre>" + escape(response) + "</pre>");
@@ -101,9 +312,28 @@ app.all('*', (req, res) => {
 		res.sendStatus(404);
 	}
 });
+/* Requests the HTTP parser rejects never reach express, the desync/smuggling probes among them are saved with the reason */
+server.on('clientError', (err, socket) => {
+	if (err.code && err.code.startsWith('HPE_') && socket.remoteAddress) {
+		let raw = err.rawPacket || Buffer.alloc(0);
+		let ip = helper.formatIpAddress(socket.remoteAddress);
+		let item = {
+			'ip': ip,
+			'service': 'http',
+			'request': 'Malformed HTTP request from ' + ip + ' (' + httpAnomalies.parserError(err) + ')',
+			'request_headers': protocolDetector.isPrintable(raw) ? raw.toString('utf8') : 'hex: ' + raw.toString('hex'),
+			'port': socket.localPort,
+			'malformed': httpAnomalies.parserError(err)
+		};
+		if (raw.length === 0) delete item.request_headers;
+		if (rateLimiter.take(ip)) emitData(item);
+	}
+	if (socket.writable) socket.end('HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n');
+	else socket.destroy();
+});
 const server_port = config.nginx_reverse_proxy === true ? config.express_js_alternative_port : 80;
 server.listen(server_port);
-console.log(chalk.green.bold(`Server running at http://${config.server_ip}:${server_port}/`));
+logger.info(`Server running at http://${config.server_ip}:${server_port}/`);
 
 /**
  * Emits data to the WebSocket clients and also saves it in the MySQL database
@@ -111,27 +341,75 @@ console.log(chalk.green.bold(`Server running at http://${config.server_ip}:${ser
  */
 const emitData = (item) => {
 	total_requests_number++;
+	live_stats.requests++;
+	live_stats.today++;
+	service_requests[item.service] = (service_requests[item.service] || 0) + 1;
 	item.timestamp = Date.now();
+	item.event_id = last_event_id = Math.max(last_event_id + 1, item.timestamp * 1000);
 	item.ip = helper.formatIpAddress(item.ip);
-	io.emit('broadcast', item);
+	if (config.high_value_ports.includes(item.port)) item.priority = true;
+	enrichers.enrich(item);
+	let shown = redact(item);
+	Object.keys(io.sockets.connected).forEach((id) => {
+		let socket = io.sockets.connected[id];
+		// Volatile: a client that can't keep up misses items instead of piling them up in memory
+		if (matchesFilter(socket, item)) socket.volatile.emit('broadcast', shown);
+	});
 	data[data.length] = item;
+	replay.push(item);
+	if (replay.length > REPLAY_EVENTS) replay.shift();
 	helper.saveToDatabase(item);
+	logger.request(item);
+	if (abuseIpDb) abuseIpDb.report(item);
+	if (item.uploads && item.uploads.length !== 0) samples.record(item);
+	if (syslog) syslog.send(item);
 };
 
+/* Throughput for the WebSocket clients, counted server-side so that it doesn't depend on their filters */
+const LIVE_STATS_INTERVAL = 5;
+setInterval(() => {
+	let day = new Date().toDateString();
+	if (day !== live_stats.day) live_stats = {'requests': live_stats.requests, 'today': 0, 'day': day};
+	io.emit('stats', {
+		'requests_per_second': Math.round(live_stats.requests / LIVE_STATS_INTERVAL * 10) / 10,
+		'requests_today': live_stats.today,
+		'clients': Object.keys(io.sockets.connected).length
+	});
+	live_stats.requests = 0;
+}, LIVE_STATS_INTERVAL * 1000);
+
 /* Cleaning Up Old Data */
 setInterval(() => {
 	data = helper.removeOldData(data);
 }, 1000);
 
-/* We need to manually kill tcpdump process in the case of program termination signal */
+/**
+ * Graceful shutdown: we need to manually kill tcpdump process in the case of program termination signal,
+ * stop accepting new connections and wait until the pending requests are saved
+ */
+const SHUTDOWN_TIMEOUT = 10 * 1000;
+let terminating = false;
 const terminate = () => {
+	if (terminating) return process.exit(1);
+	terminating = true;
 	try {
 		ping.tcpdumpProcess.kill();
+		if (ping6) ping6.tcpdumpProcess.kill();
 	} catch (error) {}
 
-	server.close(() => {
+	socket_servers.forEach((socket_server) => {
+		socket_server.close();
+	});
+	server.close();
+	deduplicator.flush(true);
+	helper.flushDatabase(() => {
 		process.exit(0);
 	});
+	// Not waiting forever for a hanging database
+	setTimeout(() => {
+		logger.warning('Pending requests were not saved within ' + (SHUTDOWN_TIMEOUT / 1000) + ' seconds, exiting anyway.');
+		process.exit(1);
+	}, SHUTDOWN_TIMEOUT).unref();
 };
 process.on('SIGTERM', terminate);
 process.on('SIGINT', terminate);
\ No newline at end of file
@@ -0,0 +1,109 @@
+"use strict";
+
+/**
+ * Moves the requests of the past months out of the `request` table into one table per month (`request_201809`, etc.),
+ * which can then be backed up with mysqldump and dropped, so that the live table doesn't grow for ever
+ * Usage: node bin/archive.js <YYYY-MM>
+ * Every month before the given one is archived. The hourly and daily stats are kept, so the charts still cover the archived
+ * months, and their number of requests is kept in `request_archive` for the dashboard's total. `/api/event` and `/api/export`
+ * read the archived tables with `include_archived=true`, while the summaries, `/api/recent`, `/api/search`, etc. only see the
+ * live table. bin/import.js leaves out the requests of the archived months, their stats would be recounted from the live table only
+ */
+
+const logger = require('../lib/logger');
+let helper, runMigrations;
+try {
+	helper = require('../lib/helper');
+	runMigrations = require('../lib/migrations');
+} catch (err) {
+	logger.error(err.code === 'MODULE_NOT_FOUND' ? 'config not found. Please create `./config.js` based on the `./config.js.template`.' : 'Invalid config: ' + err.message);
+	process.exit(1);
+}
+
+const match = /^(\d{4})-(\d{2})$/.exec(process.argv[2] || '');
+if (match === null || Number(match[2]) < 1 || Number(match[2]) > 12) {
+	logger.error('Usage: node bin/archive.js <YYYY-MM>');
+	process.exit(1);
+}
+const before = new Date(Number(match[1]), Number(match[2]) - 1, 1);
+
+const mysqlPool = helper.mysqlPool;
+
+/**
+ * @param connection
+ * @param {string} sql
+ * @param {Array} params
+ * @returns {Promise}
+ */
+const query = (connection, sql, params) => {
+	return new Promise((resolve, reject) => {
+		connection.query(sql, params, (error, results) => {
+			if (error) return reject(error);
+			resolve(results);
+		});
+	});
+};
+
+/**
+ * Copies the month into its table and deletes it from the live one, all or nothing
+ * @param connection
+ * @param {Date} from - First day of the month
+ * @returns {Promise} - Number of the archived requests
+ */
+const archiveMonth = (connection, from) => {
+	let to = new Date(from.getFullYear(), from.getMonth() + 1, 1);
+	let table = 'request_' + from.getFullYear() + String(from.getMonth() + 1).padStart(2, '0');
+	let archived = 0;
+	let columns;
+
+	return query(connection, 'CREATE TABLE IF NOT EXISTS ' + table + ' LIKE request', [])
+		// By name, the columns of a table archived into before may be in another order (the migrations add them at the end)
+		.then(() => query(connection, "SELECT COLUMN_NAME AS column_name FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'request'", []))
+		.then((rows) => {
+			columns = rows.map((row) => '`' + row['column_name'] + '`').join(', ');
+			return query(connection, 'START TRANSACTION', []);
+		})
+		.then(() => query(connection, 'INSERT INTO ' + table + ' (' + columns + ') SELECT ' + columns + ' FROM request WHERE date >= ? AND date < ?', [from, to]))
+		.then((results) => {
+			archived = results.affectedRows;
+			// Archiving the same month again adds up to the requests archived before
+			return query(connection, 'INSERT INTO request_archive (table_name, total) SELECT ?, IFNULL(SUM(count), 0) FROM request WHERE date >= ? AND date < ? ON DUPLICATE KEY UPDATE total = total + VALUES(total)', [table, from, to]);
+		})
+		.then(() => query(connection, 'DELETE FROM request WHERE date >= ? AND date < ?', [from, to]))
+		.then(() => query(connection, 'COMMIT', []))
+		.then(() => {
+			logger.info('Archived ' + archived + ' requests', ' into ' + table + '.');
+			return archived;
+		})
+		.catch((error) => query(connection, 'ROLLBACK', []).then(() => { throw error; }, () => { throw error; }));
+};
+
+const run = () => {
+	mysqlPool.getConnection((err, connection) => {
+		if (err) {
+			logger.error('Cannot connect to the MySQL server. Error Code: ' + err.code);
+			return mysqlPool.end(() => { process.exit(1); });
+		}
+
+		let total = 0;
+		query(connection, 'SELECT MIN(date) AS oldest FROM request WHERE date < ?', [before]).then((rows) => {
+			let queue = Promise.resolve();
+			if (rows[0]['oldest'] === null) return queue;
+			for (let month = new Date(rows[0]['oldest'].getFullYear(), rows[0]['oldest'].getMonth(), 1); month < before; month = new Date(month.getFullYear(), month.getMonth() + 1, 1)) {
+				let from = month;
+				queue = queue.then(() => archiveMonth(connection, from)).then((archived) => { total+= archived; });
+			}
+			return queue;
+		}).then(() => {
+			logger.info('Archived ' + total + ' requests in total.');
+		}).catch((error) => {
+			logger.error('Archiving failed after ' + total + ' requests. Error Code: ' + error.code);
+			process.exitCode = 1;
+		}).then(() => {
+			connection.release();
+			mysqlPool.end(() => {});
+		});
+	});
+};
+
+runMigrations(mysqlPool, run);
@@ -0,0 +1,197 @@
+"use strict";
+
+/**
+ * Imports the requests from a JSON Lines file into the database, e.g. an `/api/export?format=jsonl` of another sensor
+ * or a spill file, then recounts the hourly stats of the imported period
+ * Usage: node bin/import.js <file.jsonl> [--skip-duplicates]
+ * With `--skip-duplicates` the requests with the same date, IP address, service and port as an existing one are left out
+ * The requests of the months moved out by bin/archive.js are left out too, their stats would be recounted without the archived ones
+ */
+
+const fs = require('fs');
+const readline = require('readline');
+const logger = require('../lib/logger');
+let helper, runMigrations;
+try {
+	helper = require('../lib/helper');
+	runMigrations = require('../lib/migrations');
+} catch (err) {
+	logger.error(err.code === 'MODULE_NOT_FOUND' ? 'config not found. Please create `./config.js` based on the `./config.js.template`.' : 'Invalid config: ' + err.message);
+	process.exit(1);
+}
+
+const BATCH_SIZE = 500;
+
+const file = process.argv[2];
+const skip_duplicates = process.argv.includes('--skip-duplicates');
+if (!file || !fs.existsSync(file)) {
+	logger.error('Usage: node bin/import.js <file.jsonl> [--skip-duplicates]');
+	process.exit(1);
+}
+
+const mysqlPool = helper.mysqlPool;
+const columns = ['date', 'ip', 'service', 'request', 'request_headers'].concat(helper.optionalColumns);
+// Values of the NOT NULL columns missing in the older exports
+const defaults = {'duration_ms': 0, 'count': 1};
+
+/**
+ * @param {string} line - Exported item (`timestamp`) or spilled row (`date`), both in milliseconds
+ * @returns {null|object} - Row to insert
+ */
+const parseLine = (line) => {
+	let item;
+	try {
+		item = JSON.parse(line);
+	} catch (err) {
+		return null;
+	}
+	let date = new Date(item.timestamp !== undefined ? item.timestamp : item.date);
+	if (!item.ip || !item.service || isNaN(date.getTime())) return null;
+
+	let row = Object.assign({'date': date}, defaults);
+	columns.forEach((column) => {
+		if (column !== 'date' && item[column] !== undefined) row[column] = item[column];
+	});
+	helper.fitColumns(row);
+	// The exported requests are decompressed, while the spilled rows are stored as they were to be inserted
+	if (item.timestamp !== undefined) helper.compressColumns(row);
+
+	return row;
+};
+
+/**
+ * @param {Date} date
+ * @returns {string} - Table bin/archive.js moves the month of the date into
+ */
+const monthTable = (date) => 'request_' + date.getFullYear() + String(date.getMonth() + 1).padStart(2, '0');
+
+/**
+ * @returns {Promise} - Set of the tables of the archived months, including those dropped since
+ */
+const archivedMonths = () => {
+	let sql = "SELECT table_name FROM request_archive UNION SELECT TABLE_NAME FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME REGEXP '^request_[0-9]{6}$'";
+
+	return new Promise((resolve, reject) => {
+		mysqlPool.query(sql, (error, results) => {
+			if (error) return reject(error);
+			resolve(new Set(results.map((row) => row['table_name'])));
+		});
+	});
+};
+
+/**
+ * @param {Array} rows
+ * @returns {Promise} - Rows which are not in the database yet
+ */
+const withoutDuplicates = (rows) => {
+	let key = (row) => [row.date.getTime(), row.ip, row.service, row.port || ''].join('|');
+	let from = new Date(Math.min.apply(null, rows.map((row) => row.date.getTime())));
+	let to = new Date(Math.max.apply(null, rows.map((row) => row.date.getTime())));
+
+	return new Promise((resolve, reject) => {
+		mysqlPool.query('SELECT date, ip, service, port FROM request WHERE date >= ? AND date <= ?', [from, to], (error, results) => {
+			if (error) return reject(error);
+			let existing = new Set(results.map(key));
+			resolve(rows.filter((row) => !existing.has(key(row))));
+		});
+	});
+};
+
+/**
+ * @param {Array} rows
+ * @returns {Promise}
+ */
+const insertRows = (rows) => {
+	return new Promise((resolve, reject) => {
+		let values = rows.map((row) => columns.map((column) => row[column] !== undefined ? row[column] : null));
+		mysqlPool.query('INSERT INTO request (' + columns.join(', ') + ') VALUES ?', [values], (error) => {
+			if (error) return reject(error);
+			resolve();
+		});
+	});
+};
+
+/**
+ * One row the database rejects (e.g. ER_DATA_TOO_LONG) fails the whole batch, so the batch is then inserted row by row
+ * and only such rows are left out. The import still stops on the connection errors
+ * @param {Array} rows
+ * @returns {Promise} - Numbers of the `inserted` and the `rejected` rows
+ */
+const insertBatch = (rows) => {
+	return (skip_duplicates ? withoutDuplicates(rows) : Promise.resolve(rows)).then((rows) => {
+		if (rows.length === 0) return {'inserted': 0, 'rejected': 0};
+		return insertRows(rows).then(() => ({'inserted': rows.length, 'rejected': 0}), (error) => {
+			if (helper.isTransientError(error)) throw error;
+			return rows.reduce((queue, row) => queue.then((result) => insertRows([row]).then(() => {
+				result.inserted++;
+				return result;
+			}, (error) => {
+				if (helper.isTransientError(error)) throw error;
+				logger.warning('Left out the request of ' + row.ip + ' from ' + row.date.toISOString() + ', the database rejected it. Error Code: ' + error.code);
+				result.rejected++;
+				return result;
+			})), Promise.resolve({'inserted': 0, 'rejected': 0}));
+		});
+	});
+};
+
+const run = (archived) => {
+	let stats = {'imported': 0, 'skipped': 0, 'rejected': 0, 'invalid': 0, 'archived': 0, 'from': null, 'to': null};
+	let archived_hit = new Set();
+	let batch = [];
+	let queue = Promise.resolve();
+	let flush = () => {
+		let rows = batch;
+		batch = [];
+		queue = queue.then(() => insertBatch(rows)).then((result) => {
+			stats.imported+= result.inserted;
+			stats.rejected+= result.rejected;
+			stats.skipped+= rows.length - result.inserted - result.rejected;
+		});
+	};
+
+	let lines = readline.createInterface({input: fs.createReadStream(file), crlfDelay: Infinity});
+	lines.on('line', (line) => {
+		if (line.trim().length === 0) return;
+		let row = parseLine(line);
+		if (row === null) return stats.invalid++;
+		if (archived.has(monthTable(row.date))) {
+			archived_hit.add(monthTable(row.date));
+			return stats.archived++;
+		}
+		if (stats.from === null || row.date < stats.from) stats.from = row.date;
+		if (stats.to === null || row.date > stats.to) stats.to = row.date;
+		batch.push(row);
+		if (batch.length >= BATCH_SIZE) {
+			flush();
+			// Not reading faster than the database takes the rows
+			lines.pause();
+			queue.then(() => { lines.resume(); }, () => {});
+		}
+	});
+	lines.on('close', () => {
+		if (batch.length !== 0) flush();
+		if (stats.archived !== 0) logger.warning('Left out the ' + stats.archived + ' requests of the archived months (' + Array.from(archived_hit).sort().join(', ') + '), their stats would be recounted without the archived requests.');
+		queue.then(() => {
+			if (stats.imported === 0) return;
+			return new Promise((resolve, reject) => {
+				helper.reaggregateHours(stats.from, stats.to, (error) => error ? reject(error) : resolve());
+			});
+		}).then(() => {
+			logger.info('Imported ' + stats.imported + ' requests.', ' Skipped duplicates: ' + stats.skipped + ', rejected by the database: ' + stats.rejected + ', in the archived months: ' + stats.archived + ', invalid lines: ' + stats.invalid + '.');
+			mysqlPool.end(() => {});
+		}).catch((error) => {
+			logger.error('Import failed after ' + stats.imported + ' requests. Error Code: ' + error.code);
+			mysqlPool.end(() => {});
+			process.exitCode = 1;
+		});
+	});
+};
+
+runMigrations(mysqlPool, () => {
+	archivedMonths().then(run, (error) => {
+		logger.error('Cannot list the archived months. Error Code: ' + error.code);
+		mysqlPool.end(() => {});
+		process.exitCode = 1;
+	});
+});
@@ -0,0 +1,34 @@
+"use strict";
+
+/**
+ * Lists the SSH host keys of `ssh_host_keys` with their fingerprints, or replaces them with new ones
+ * Usage: node bin/ssh-host-keys.js [--regenerate]
+ * With `--regenerate` every key is replaced by a new one of the same type (RSA for the missing ones), the clients which
+ * connected before will see the fingerprint change. Restart the app afterwards
+ */
+
+const fs = require('fs');
+const path = require('path');
+const logger = require('../lib/logger');
+const hostKeys = require('../lib/ssh-host-keys');
+let config;
+try {
+	config = require('../lib/config');
+} catch (err) {
+	logger.error(err.code === 'MODULE_NOT_FOUND' ? 'config not found. Please create `./config.js` based on the `./config.js.template`.' : 'Invalid config: ' + err.message);
+	process.exit(1);
+}
+
+const regenerate = process.argv.includes('--regenerate');
+
+config.ssh_host_keys.forEach((key_path) => {
+	let key = fs.existsSync(key_path) ? fs.readFileSync(key_path) : null;
+	if (regenerate) {
+		let type = key !== null && hostKeys.keyType(key).startsWith('ecdsa') ? 'ecdsa' : 'rsa';
+		key = hostKeys.generate(type);
+		fs.mkdirSync(path.dirname(key_path), {'recursive': true});
+		fs.writeFileSync(key_path, key, {'mode': 0o600});
+	}
+	if (key === null) return logger.warning(key_path + ' does not exist, create it with `--regenerate`.');
+	logger.info(key_path + ' ', hostKeys.keyType(key) + ' ' + hostKeys.fingerprint(key));
+});
@@ -34,10 +34,149 @@ CREATE TABLE `request` (
   `http_request_path` varchar(1024) DEFAULT NULL,
   `username` varchar(128) DEFAULT NULL,
   `password` varchar(128) DEFAULT NULL,
-  PRIMARY KEY (`id`)
+  `duration_ms` int(10) unsigned NOT NULL DEFAULT '0',
+  `request_size` int(10) unsigned DEFAULT NULL,
+  `botnet_family` varchar(32) DEFAULT NULL,
+  `port` smallint(5) unsigned DEFAULT NULL,
+  `count` int(10) unsigned NOT NULL DEFAULT '1',
+  `user_agent` varchar(512) DEFAULT NULL,
+  `scanner_name` varchar(64) DEFAULT NULL,
+  `payload_kind` varchar(16) DEFAULT NULL,
+  `hassh` char(32) DEFAULT NULL,
+  `exploit` varchar(64) DEFAULT NULL,
+  `truncated` tinyint(1) DEFAULT NULL,
+  `data_received` tinyint(1) DEFAULT NULL,
+  `tags` varchar(255) DEFAULT NULL,
+  `malformed` varchar(64) DEFAULT NULL,
+  `docker_image` varchar(255) DEFAULT NULL,
+  `docker_command` varchar(1024) DEFAULT NULL,
+  `end_reason` varchar(16) DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `idx_date` (`date`),
+  KEY `idx_service` (`service`),
+  FULLTEXT KEY `ft_request` (`request`,`request_headers`,`http_request_path`)
 ) ENGINE=InnoDB AUTO_INCREMENT=567132 DEFAULT CHARSET=utf8;
 /*!40101 SET character_set_client = @saved_cs_client */;
 
+--
+-- Table structure for table `stats_hourly`
+--
+
+DROP TABLE IF EXISTS `stats_hourly`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!40101 SET character_set_client = utf8 */;
+CREATE TABLE `stats_hourly` (
+  `hour_bucket` datetime NOT NULL,
+  `service` varchar(16) NOT NULL DEFAULT '',
+  `total` int(11) NOT NULL DEFAULT '0',
+  `scanners` int(11) NOT NULL DEFAULT '0',
+  PRIMARY KEY (`hour_bucket`,`service`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `stats_hourly_port`
+--
+
+DROP TABLE IF EXISTS `stats_hourly_port`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!40101 SET character_set_client = utf8 */;
+CREATE TABLE `stats_hourly_port` (
+  `hour_bucket` datetime NOT NULL,
+  `port` smallint(5) unsigned NOT NULL,
+  `total` int(11) NOT NULL DEFAULT '0',
+  `scanners` int(11) NOT NULL DEFAULT '0',
+  PRIMARY KEY (`hour_bucket`,`port`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `stats_hourly_user_agent`
+--
+
+DROP TABLE IF EXISTS `stats_hourly_user_agent`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!40101 SET character_set_client = utf8 */;
+CREATE TABLE `stats_hourly_user_agent` (
+  `hour_bucket` datetime NOT NULL,
+  `user_agent` varchar(200) NOT NULL,
+  `total` int(11) NOT NULL DEFAULT '0',
+  `scanners` int(11) NOT NULL DEFAULT '0',
+  PRIMARY KEY (`hour_bucket`,`user_agent`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `delivery`
+--
+
+DROP TABLE IF EXISTS `delivery`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!40101 SET character_set_client = utf8 */;
+CREATE TABLE `delivery` (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `integration` varchar(16) NOT NULL,
+  `payload` mediumtext NOT NULL,
+  `attempts` int(11) NOT NULL DEFAULT '1',
+  `next_attempt` datetime NOT NULL,
+  `last_error` varchar(255) DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `next_attempt` (`next_attempt`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `request_archive`
+--
+
+DROP TABLE IF EXISTS `request_archive`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!40101 SET character_set_client = utf8 */;
+CREATE TABLE `request_archive` (
+  `table_name` varchar(16) NOT NULL,
+  `total` bigint(20) NOT NULL DEFAULT '0',
+  PRIMARY KEY (`table_name`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `sample`
+--
+
+DROP TABLE IF EXISTS `sample`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!40101 SET character_set_client = utf8 */;
+CREATE TABLE `sample` (
+  `sha256` char(64) NOT NULL,
+  `size` int(11) NOT NULL,
+  `file_name` varchar(255) NOT NULL,
+  `service` varchar(16) NOT NULL,
+  `first_ip` varchar(128) NOT NULL,
+  `first_seen` datetime NOT NULL,
+  `last_seen` datetime NOT NULL,
+  `uploads` int(11) NOT NULL DEFAULT '1',
+  `detection` varchar(255) DEFAULT NULL,
+  `checked_at` datetime DEFAULT NULL,
+  PRIMARY KEY (`sha256`),
+  KEY `last_seen` (`last_seen`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `stats_daily`
+--
+
+DROP TABLE IF EXISTS `stats_daily`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!40101 SET character_set_client = utf8 */;
+CREATE TABLE `stats_daily` (
+  `day_bucket` date NOT NULL,
+  `total` int(11) NOT NULL DEFAULT '0',
+  `unique_ips` int(11) NOT NULL DEFAULT '0',
+  PRIMARY KEY (`day_bucket`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
 --
 -- Dumping events for database 'tail-f'
 --
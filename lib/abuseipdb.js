@@ -0,0 +1,136 @@
+"use strict";
+
+const https = require('https');
+const querystring = require('querystring');
+const logger = require('./logger');
+const config = require('./config');
+const helper = require('./helper');
+
+// https://www.abuseipdb.com/categories
+const CATEGORY_FTP_BRUTE_FORCE = 5;
+const CATEGORY_PORT_SCAN = 14;
+const CATEGORY_HACKING = 15;
+const CATEGORY_BRUTE_FORCE = 18;
+const CATEGORY_WEB_APP_ATTACK = 21;
+const CATEGORY_SSH = 22;
+
+const MAX_COMMENT_LENGTH = 1024;
+const REQUEST_TIMEOUT = 30 * 1000;
+
+/**
+ * Reports the attackers' IP addresses to AbuseIPDB (API v2)
+ * Reports are queued and sent in batches once every `interval` seconds, the same IP address
+ * is reported at most once per `cooldown` seconds. The reports failing on the network or a server error go to the delivery queue
+ */
+class AbuseIpDbReporter {
+	/**
+	 * @param options - `abuseipdb` section of the config
+	 * @param deliveryQueue - @see DeliveryQueue
+	 */
+	constructor(options, deliveryQueue) {
+		this.options = options;
+		this.queue = new Map();
+		this.reported = new Map();
+		this.deliveryQueue = deliveryQueue;
+		deliveryQueue.register('abuseipdb', (payload, callback) => { this.send(payload.ip, payload.report, callback); });
+
+		setInterval(() => { this.flush(); }, options.interval * 1000);
+	}
+
+	/**
+	 * Queues the request if it meets the configured criteria
+	 * @param item - Request as emitted by the services
+	 */
+	report(item) {
+		let categories = this.categories(item);
+		if (categories.length === 0 || helper.isPrivateIp(item.ip)) return;
+		if (this.queue.has(item.ip) || Date.now() - (this.reported.get(item.ip) || 0) < this.options.cooldown * 1000) return;
+
+		this.queue.set(item.ip, {'categories': categories, 'comment': this.comment(item), 'timestamp': item.timestamp || Date.now()});
+	}
+
+	/**
+	 * @param item
+	 * @returns {Array} - Categories of the report, empty if the request should not be reported
+	 */
+	categories(item) {
+		if (item.username !== undefined && this.options.report_login_attempts) {
+			if (item.service === 'ssh') return [CATEGORY_BRUTE_FORCE, CATEGORY_SSH];
+			if (item.service === 'ftp') return [CATEGORY_BRUTE_FORCE, CATEGORY_FTP_BRUTE_FORCE];
+			return [CATEGORY_BRUTE_FORCE];
+		}
+		if (!this.options.services.includes(item.service)) return [];
+		if (item.http_request_path !== undefined) return [CATEGORY_WEB_APP_ATTACK];
+
+		return [CATEGORY_PORT_SCAN, CATEGORY_HACKING];
+	}
+
+	/**
+	 * Comment built from the request, without our own IP address
+	 * @param item
+	 */
+	comment(item) {
+		let comment = 'Honeypot, ' + item.service + ': ' + (item.request || 'connection');
+		if (item.username !== undefined) comment+= ' (login attempt with username "' + item.username + '")';
+
+		return comment.split(config.server_ip).join('[honeypot]').substr(0, MAX_COMMENT_LENGTH);
+	}
+
+	/* Sends the queued reports and forgets the IP addresses whose cooldown has expired */
+	flush() {
+		let now = Date.now();
+		this.reported.forEach((reported_at, ip) => {
+			if (now - reported_at >= this.options.cooldown * 1000) this.reported.delete(ip);
+		});
+		this.queue.forEach((report, ip) => {
+			this.reported.set(ip, now);
+			this.send(ip, report, (err) => {
+				if (err) this.deliveryQueue.add('abuseipdb', {'ip': ip, 'report': report}, err);
+			});
+		});
+		this.queue.clear();
+	}
+
+	/**
+	 * @param {string} ip
+	 * @param report - Categories, comment and timestamp
+	 * @param {function} callback - Called with the error when worth retrying
+	 */
+	send(ip, report, callback) {
+		let done = (err) => {
+			if (callback) callback(err);
+			callback = null;
+		};
+		let body = querystring.stringify({
+			'ip': ip,
+			'categories': report.categories.join(','),
+			'comment': report.comment,
+			'timestamp': new Date(report.timestamp).toISOString()
+		});
+		let req = https.request({
+			'method': 'POST',
+			'hostname': 'api.abuseipdb.com',
+			'path': '/api/v2/report',
+			'headers': {
+				'Key': this.options.api_key,
+				'Accept': 'application/json',
+				'Content-Type': 'application/x-www-form-urlencoded',
+				'Content-Length': Buffer.byteLength(body)
+			}
+		}, (res) => {
+			res.resume();
+			if (res.statusCode >= 500) return done(new Error('HTTP Status: ' + res.statusCode));
+			// 429 is also returned for the IP addresses reported by us within the last 15 minutes
+			if (res.statusCode !== 200 && res.statusCode !== 429) logger.warning('AbuseIPDB report for ' + ip + ' failed. HTTP Status: ' + res.statusCode);
+			done(null);
+		});
+		req.setTimeout(REQUEST_TIMEOUT, () => { req.abort(); });
+		req.on('error', (err) => {
+			logger.warning('Cannot reach AbuseIPDB. Error Code: ' + err.code);
+			done(err);
+		});
+		req.end(body);
+	}
+}
+
+module.exports = AbuseIpDbReporter;
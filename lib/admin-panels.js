@@ -0,0 +1,67 @@
+"use strict";
+
+const fs = require('fs');
+const config = require('./config');
+
+// Login pages looking close enough to the real ones for the scanners fingerprinting the title and the form fields
+const pages = {
+	'phpmyadmin': '<!DOCTYPE html>\n<html lang="en" dir="ltr">\n<head>\n<meta charset="utf-8">\n<title>phpMyAdmin</title>\n'
+		+ '<link rel="stylesheet" type="text/css" href="./themes/pmahomme/css/theme.css?v=4.6.6deb5">\n</head>\n<body id="loginform">\n'
+		+ '<div class="container">\n<h1>Welcome to <bdo dir="ltr" lang="en">phpMyAdmin</bdo></h1>\n'
+		+ '<form method="post" action="index.php" name="login_form" class="disableAjax login hide js-show">\n<fieldset>\n<legend>Log in</legend>\n'
+		+ '<div class="item"><label for="input_username">Username:</label><input type="text" name="pma_username" id="input_username" value="" size="24" class="textfield"/></div>\n'
+		+ '<div class="item"><label for="input_password">Password:</label><input type="password" name="pma_password" id="input_password" value="" size="24" class="textfield" /></div>\n'
+		+ '<input type="hidden" name="server" value="1" />\n</fieldset>\n<fieldset class="tblFooters"><input value="Go" type="submit" id="input_go" /></fieldset>\n'
+		+ '</form>\n</div>\n</body>\n</html>\n',
+	'jenkins': '<!DOCTYPE html>\n<html>\n<head>\n<title>Sign in [Jenkins]</title>\n<meta name="ROOT" content="">\n</head>\n'
+		+ '<body id="jenkins" class="yui-skin-sam jenkins-2.138.2" data-version="2.138.2">\n<div id="main-panel">\n'
+		+ '<div name="login" style="margin: 2em; margin-top: 20vh;">\n<form method="post" name="login" action="j_acegi_security_check">\n'
+		+ '<table><tr><td>User:</td><td><input autocorrect="off" autocapitalize="off" type="text" name="j_username" id="j_username"></td></tr>\n'
+		+ '<tr><td>Password:</td><td><input type="password" name="j_password"></td></tr>\n'
+		+ '<tr><td align="right"><input id="remember_me" type="checkbox" name="remember_me"></td><td><label for="remember_me">Keep me signed in</label></td></tr></table>\n'
+		+ '<input name="from" type="hidden"><input name="Submit" type="submit" value="Sign in">\n</form>\n</div>\n</div>\n</body>\n</html>\n',
+	'router': '<!DOCTYPE html>\n<html>\n<head>\n<meta http-equiv="Content-Type" content="text/html; charset=utf-8">\n<title>Login</title>\n</head>\n'
+		+ '<body>\n<div class="login-box">\n<h2>Wireless Router</h2>\n<form method="post" action="login.cgi">\n'
+		+ '<p>User Name: <input type="text" name="username" maxlength="31"></p>\n'
+		+ '<p>Password: <input type="password" name="password" maxlength="31"></p>\n'
+		+ '<p><input type="submit" value="Login"></p>\n</form>\n<p class="copyright">Firmware Version: V1.0.0.34_10.0.31</p>\n</div>\n</body>\n</html>\n'
+};
+
+// Form fields of the credentials, the built-in pages' as well as the common ones of the custom pages
+const usernameFields = ['pma_username', 'j_username', 'username', 'user', 'login', 'email', 'log', 'uname'];
+const passwordFields = ['pma_password', 'j_password', 'password', 'pass', 'passwd', 'pwd', 'psw'];
+
+// Path prefixes of `emulation.admin_panels` with the HTML, the longest first so that the most specific one matches
+const panels = Object.keys(config.emulation.admin_panels).map((prefix) => {
+	let page = config.emulation.admin_panels[prefix];
+	return {'prefix': prefix.toLowerCase(), 'html': pages[page] !== undefined ? pages[page] : fs.readFileSync(page, 'utf8')};
+}).sort((a, b) => b.prefix.length - a.prefix.length);
+
+/**
+ * @param {string} path
+ * @returns {null|string} - HTML of the fake admin panel the path belongs to
+ */
+const match = (path) => {
+	let panel = panels.find((panel) => path.toLowerCase().startsWith(panel.prefix));
+
+	return panel ? panel.html : null;
+};
+
+/**
+ * @param body - Parsed form submitted to the panel
+ * @returns {null|object} - Username and password, if the form had any of the fields
+ */
+const credentials = (body) => {
+	if (!body || typeof body !== 'object') return null;
+	let username = usernameFields.find((field) => typeof body[field] === 'string');
+	let password = passwordFields.find((field) => typeof body[field] === 'string');
+	if (username === undefined && password === undefined) return null;
+
+	return {'username': username !== undefined ? body[username] : '', 'password': password !== undefined ? body[password] : ''};
+};
+
+module.exports = {
+	pages: pages,
+	match: match,
+	credentials: credentials
+};
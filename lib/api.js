@@ -0,0 +1,919 @@
+"use strict";
+
+const express = require('express');
+const net = require('net');
+const config = require('./config');
+const helper = require('./helper');
+const cidr = require('./cidr');
+const tcp_ports = require('./tcp-ports');
+const todayStats = require('./today-stats');
+const deobfuscator = require('./deobfuscator');
+const RdapClient = require('./rdap');
+const CustomSocketServer = require('./custom-socket-server');
+const redact = require('./redact');
+
+const router = express.Router();
+const mysqlPool = helper.mysqlPool;
+const rdap = config.rdap.enabled ? new RdapClient(config.rdap) : null;
+
+const columns = ['id', 'date', 'ip', 'service', 'request', 'request_headers', 'http_request_path', 'username', 'password', 'duration_ms', 'request_size', 'botnet_family', 'port', 'count', 'user_agent', 'scanner_name', 'payload_kind', 'hassh', 'exploit', 'truncated', 'data_received', 'tags', 'malformed', 'docker_image', 'docker_command', 'end_reason'];
+
+// Services the requests are saved under: the TCP ports, the web server and the ICMP echo requests
+const services = new Set(Object.values(tcp_ports).concat(['http', 'https', 'ping', 'icmpv6', 'portscan']));
+// Upper bounds (milliseconds) of the time between the requests buckets of `/api/timing`, the last one is open-ended
+const timingBuckets = [100, 1000, 10 * 1000, 60 * 1000, 10 * 60 * 1000, 3600 * 1000, Infinity];
+// Tables of the stats page, @see helper.monthlyStats
+const monthlyTables = ['services', 'credentials', 'requests', 'ips', 'botnets', 'payloads'];
+
+/**
+ * Converts a `request` table row into the same shape as the items emitted to the WebSocket clients
+ * @param row
+ */
+const rowToItem = (row) => {
+	let item = {};
+	columns.forEach((column) => {
+		if (column === 'date') item['timestamp'] = row['date'].getTime();
+		else if (column === 'request' || column === 'request_headers') item[column] = helper.decompress(row[column]);
+		else item[column] = row[column];
+	});
+
+	return item;
+};
+
+/* Every JSON response goes out with the `redact_fields` replaced */
+router.use((req, res, next) => {
+	let json = res.json.bind(res);
+	res.json = (body) => json(redact(body));
+	next();
+});
+
+/**
+ * Promise wrapper around the pool query
+ * @param {string} sql
+ * @param {Array} params
+ */
+const query = (sql, params) => {
+	return new Promise((resolve, reject) => {
+		mysqlPool.query(sql, params, (error, results) => {
+			if (error) return reject(error);
+			resolve(results);
+		});
+	});
+};
+
+/**
+ * Accepts either a unix timestamp in milliseconds or any string understood by `Date`
+ * @param value
+ * @param {Date} fallback
+ */
+const parseDate = (value, fallback) => {
+	if (value === undefined || value.length === 0) return fallback;
+	let date = new Date(isNaN(value) ? value : Number(value));
+
+	return isNaN(date.getTime()) ? null : date;
+};
+
+/**
+ * `exclude_scanners=true` leaves out the requests of the known research scanners
+ * @param req
+ * @returns {string} - Extra condition of the WHERE clause
+ */
+const scannerCondition = (req) => {
+	return req.query.exclude_scanners === 'true' ? ' AND scanner_name IS NULL' : '';
+};
+
+/**
+ * Requests of an hourly rollup, less the known scanners' for `exclude_scanners=true`
+ * @param req
+ * @returns {string} - Expression summed over the rollup
+ */
+const rollupTotal = (req) => {
+	return req.query.exclude_scanners === 'true' ? 'total - scanners' : 'total';
+};
+
+/**
+ * `include_archived=true` reads the tables bin/archive.js moved the past months into as well, those overlapping the dates
+ * @param req
+ * @param {Date} [from]
+ * @param {Date} [to]
+ * @returns {Promise} - Names of the tables to read the requests from, `request` last
+ */
+const requestTables = (req, from, to) => {
+	if (req.query.include_archived !== 'true') return Promise.resolve(['request']);
+
+	return query("SELECT TABLE_NAME AS name FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME REGEXP '^request_[0-9]{6}$' ORDER BY TABLE_NAME").then((rows) => {
+		return rows.map((row) => row['name']).filter((table) => {
+			if (from === undefined) return true;
+			let month = new Date(Number(table.substr(8, 4)), Number(table.substr(12, 2)) - 1, 1);
+			return month < to && new Date(month.getFullYear(), month.getMonth() + 1, 1) > from;
+		}).concat('request');
+	});
+};
+
+/**
+ * @param {Array} tables - Given by `requestTables`
+ * @param {function} select - SELECT of the requests from the given table
+ * @param {Array} params - Parameters of a single SELECT
+ * @returns {Array} - The SELECTs of all the tables combined with UNION ALL and their parameters
+ */
+const unionQuery = (tables, select, params) => {
+	if (tables.length === 1) return [select(tables[0]), params];
+
+	return [tables.map((table) => '(' + select(table) + ')').join(' UNION ALL '), [].concat(...tables.map(() => params))];
+};
+
+/**
+ * `data_received=false` keeps only the connections which didn't send anything (connect scans), `true` the rest
+ * Only the plain TCP services tell, the other requests are left out either way
+ * @param req
+ * @returns {string} - Extra condition of the WHERE clause
+ */
+const dataReceivedCondition = (req) => {
+	if (req.query.data_received === 'true') return ' AND data_received = 1';
+	if (req.query.data_received === 'false') return ' AND data_received = 0';
+
+	return '';
+};
+
+/**
+ * Answers with JSON, or with CSV for the `.csv` variant of the route (e.g. /api/ports.csv), for the spreadsheets
+ * @param req
+ * @param res
+ * @param {Array} rows - Flat objects, their keys are the header line
+ */
+const sendRows = (req, res, rows) => {
+	if (!req.path.endsWith('.csv')) return res.json(rows);
+
+	rows = redact(rows);
+	let header = rows.length !== 0 ? Object.keys(rows[0]) : [];
+	res.set('Content-Type', 'text/csv; charset=utf-8');
+	res.set('Content-Disposition', 'attachment; filename="' + req.path.slice(1).replace(/\//g, '_') + '"');
+	res.send(helper.formatCsvRow(header) + rows.map((row) => {
+		return helper.formatCsvRow(header.map((key) => row[key] instanceof Date ? row[key].toISOString() : row[key]));
+	}).join(''));
+};
+
+/**
+ * Streams raw requests as CSV or JSON Lines
+ * Rows are written as they arrive from MySQL, so large exports are never buffered in memory
+ * e.g. /api/export?format=csv&from=2018-10-01&to=2018-10-15&service=ssh&include_archived=true
+ */
+router.get('/export', (req, res) => {
+	let format = req.query.format || 'csv';
+	if (format !== 'csv' && format !== 'jsonl') return res.status(400).send('Unsupported format. Use `csv` or `jsonl`.');
+
+	let from = parseDate(req.query.from, new Date(Date.now() - 24 * 3600 * 1000));
+	let to = parseDate(req.query.to, new Date());
+	if (from === null || to === null) return res.status(400).send('Invalid `from` or `to` date.');
+
+	let conditions = 'date >= ? AND date < ?';
+	let params = [from, to];
+	if (req.query.service) {
+		conditions+= ' AND service = ?';
+		params.push(req.query.service);
+	}
+	conditions+= scannerCondition(req) + dataReceivedCondition(req);
+
+	requestTables(req, from, to).then((tables) => {
+		let [query, query_params] = unionQuery(tables, (table) => 'SELECT ' + columns.join(', ') + ' FROM ' + table + ' WHERE ' + conditions, params);
+		query+= ' ORDER BY id ASC';
+
+		mysqlPool.getConnection((err, connection) => {
+			if (!connection) return res.sendStatus(503);
+
+			if (format === 'csv') {
+				res.set('Content-Type', 'text/csv; charset=utf-8');
+				res.set('Content-Disposition', 'attachment; filename="requests.csv"');
+				res.write(helper.formatCsvRow(columns));
+			}
+			else {
+				res.set('Content-Type', 'application/x-ndjson; charset=utf-8');
+				res.set('Content-Disposition', 'attachment; filename="requests.jsonl"');
+			}
+
+			// Client went away mid-export: keep draining the query so the connection gets released
+			let aborted = false;
+			res.on('close', () => {
+				if (res.writableFinished) return;
+				aborted = true;
+				connection.resume();
+			});
+
+			connection.query(query, query_params)
+				.on('error', () => {
+					res.end();
+				})
+				.on('result', (row) => {
+					if (aborted) return;
+					// Decompressed once, by rowToItem for the JSON Lines
+					let line;
+					if (format === 'csv') {
+						row = redact(Object.assign(row, {'request': helper.decompress(row['request']), 'request_headers': helper.decompress(row['request_headers'])}));
+						line = helper.formatCsvRow(columns.map((column) => row[column] instanceof Date ? row[column].toISOString() : row[column]));
+					}
+					else line = JSON.stringify(redact(rowToItem(row))) + '\n';
+					if (!res.write(line)) {
+						connection.pause();
+						res.once('drain', () => {
+							connection.resume();
+						});
+					}
+				})
+				.on('end', () => {
+					connection.release();
+					res.end();
+				});
+		});
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Request counts per hour (or per day) for the line charts
+ * Completed hours come from the `stats_hourly` rollup, the current hour is counted live
+ * Hourly buckets are only served for ranges up to 72 hours to keep the response small
+ * e.g. /api/timeseries?hours=48&bucket=hour
+ */
+router.get(['/timeseries', '/timeseries.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+	let bucket = req.query.bucket || (hours <= 72 ? 'hour' : 'day');
+	if (bucket !== 'hour' && bucket !== 'day') return res.status(400).send('`bucket` should be either `hour` or `day`.');
+	if (bucket === 'hour' && hours > 72) bucket = 'day';
+
+	let current_hour = new Date();
+	current_hour.setMinutes(0, 0, 0);
+	let from = new Date(current_hour.getTime() - (hours - 1) * 3600 * 1000);
+	if (bucket === 'day') from.setHours(0);
+
+	let service_filter = req.query.service ? ' AND service = ?' : '';
+	let rollup_params = [from];
+	let live_params = [current_hour];
+	if (req.query.service) {
+		rollup_params.push(req.query.service);
+		live_params.push(req.query.service);
+	}
+
+	Promise.all([
+		query('SELECT ' + (bucket === 'hour' ? 'hour_bucket' : 'DATE(hour_bucket)') + ' AS bucket, SUM(' + rollupTotal(req) + ') AS total FROM stats_hourly WHERE hour_bucket >= ?' + service_filter + ' GROUP BY bucket', rollup_params),
+		query('SELECT IFNULL(SUM(count), 0) AS total FROM request WHERE date >= ?' + service_filter + scannerCondition(req), live_params)
+	]).then(([rollup, live]) => {
+		let totals = {};
+		rollup.forEach((row) => {
+			totals[new Date(row['bucket']).getTime()] = Number(row['total']);
+		});
+		let live_bucket = new Date(current_hour);
+		if (bucket === 'day') live_bucket.setHours(0);
+		totals[live_bucket.getTime()] = (totals[live_bucket.getTime()] || 0) + Number(live[0]['total']);
+
+		let data = [];
+		for (let date = new Date(from); date <= live_bucket; bucket === 'hour' ? date.setHours(date.getHours() + 1) : date.setDate(date.getDate() + 1)) {
+			data.push({'timestamp': date.getTime(), 'total': totals[date.getTime()] || 0});
+		}
+		if (req.path.endsWith('.csv')) return sendRows(req, res, data);
+		res.json({'bucket': bucket, 'data': data});
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Most recent requests, optionally of a single service
+ * e.g. /api/recent?service=ssh&limit=50
+ */
+router.get('/recent', (req, res) => {
+	let limit = Math.min(parseInt(req.query.limit || 50, 10) || 50, 500);
+	if (req.query.service && !services.has(req.query.service)) return res.status(400).send('Unknown `service`.');
+
+	let where = (req.query.service ? 'service = ?' : '1') + scannerCondition(req) + dataReceivedCondition(req);
+	let params = req.query.service ? [req.query.service, limit] : [limit];
+	query('SELECT ' + columns.join(', ') + ' FROM request WHERE ' + where + ' ORDER BY id DESC LIMIT ?', params).then((rows) => {
+		res.json(rows.map(rowToItem));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Single request with everything saved about it, for linking to a capture
+ * Binary payloads (saved as hex) are given as text too, and the base64 blobs of the payload decoded
+ * e.g. /api/event/567132, /api/event/120417?include_archived=true
+ */
+router.get('/event/:id', (req, res) => {
+	if (!/^\d+$/.test(req.params.id)) return res.status(400).send('`id` should be a number.');
+
+	requestTables(req).then((tables) => {
+		return query(...unionQuery(tables, (table) => 'SELECT ' + columns.join(', ') + ' FROM ' + table + ' WHERE id = ?', [req.params.id]));
+	}).then((rows) => {
+		if (rows.length === 0) return res.sendStatus(404);
+		let item = rowToItem(rows[0]);
+		let payload = item.request_headers || '';
+		if (payload.startsWith('hex: ')) {
+			payload = Buffer.from(payload.substr(5), 'hex').toString('latin1');
+			item.payload_text = payload.replace(/[^\x20-\x7e\r\n\t]/g, '.');
+		}
+		item.decoded = deobfuscator.decodeBase64(payload.split(/\r?\n/));
+		res.json(item);
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Telnet shell session replayed the way the client saw it, as plain text or as an asciinema cast (`.cast`)
+ * Only the commands are saved, so their output is simulated again with the current `persona` (as told by the
+ * `X-Transcript-Output` header and the cast's title), and only the session's duration, so the steps are spread evenly over it
+ * e.g. /api/session/567132.cast, played with `asciinema play`
+ */
+router.get(/^\/session\/(\d+)(\.cast)?$/, (req, res) => {
+	query('SELECT id, date, service, request_headers, username, password, duration_ms, end_reason FROM request WHERE id = ?', [req.params[0]]).then((rows) => {
+		if (rows.length === 0) return res.sendStatus(404);
+		let row = rows[0];
+		if (row['service'] !== 'telnet' || row['username'] === null) return res.status(400).send('Not an interactive shell session.');
+		if (redact.fields.has('request_headers')) return res.status(403).send('The commands are redacted (`redact_fields`).');
+
+		// The decoded base64 blobs are appended after a blank line, otherwise the commands end with a line break
+		// The empty commands are kept, they are commands of the session too
+		let parts = (helper.decompress(row['request_headers']) || '').split('\r\n\r\nDecoded ');
+		let commands = parts.length > 1 ? parts[0] : parts[0].replace(/\r\n$/, '');
+		let steps = CustomSocketServer.telnetTranscript(row['username'], row['password'], commands.length !== 0 ? commands.split('\r\n') : [], row['end_reason'] === 'command_limit');
+		res.set('X-Transcript-Output', 're-simulated');
+		if (!req.params[1]) return res.type('text/plain').send(steps.join(''));
+
+		let interval = (row['duration_ms'] || steps.length * 1000) / 1000 / steps.length;
+		let cast = [JSON.stringify({'version': 2, 'width': 80, 'height': 24, 'timestamp': Math.floor(row['date'].getTime() / 1000), 'title': 'Request ' + row['id'] + ' (output re-simulated)'})];
+		steps.forEach((step, i) => {
+			cast.push(JSON.stringify([Math.round(i * interval * 1000) / 1000, 'o', step]));
+		});
+		res.attachment('session-' + row['id'] + '.cast');
+		res.type('application/x-asciicast').send(cast.join('\n') + '\n');
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Everything about an IP address: its requests per service, first and last seen, and with `rdap` enabled in the config,
+ * the registered network name and abuse contact. The RDAP part is left out with `rdap_error` when the lookup fails
+ * e.g. /api/ip/192.0.2.1
+ */
+router.get('/ip/:ip', (req, res) => {
+	if (!net.isIP(req.params.ip)) return res.status(400).send('`ip` should be an IP address.');
+	let ip = helper.formatIpAddress(req.params.ip);
+
+	query('SELECT service, SUM(count) AS total, MIN(date) AS first_seen, MAX(date) AS last_seen, MAX(scanner_name) AS scanner_name FROM request WHERE ip = ? GROUP BY service ORDER BY total DESC', [ip]).then((rows) => {
+		if (rows.length === 0) return res.sendStatus(404);
+		let details = {
+			'ip': ip,
+			'total': rows.reduce((total, row) => total + Number(row['total']), 0),
+			'first_seen': Math.min(...rows.map((row) => row['first_seen'].getTime())),
+			'last_seen': Math.max(...rows.map((row) => row['last_seen'].getTime())),
+			'scanner_name': rows.map((row) => row['scanner_name']).find((name) => name) || null,
+			'services': rows.map((row) => ({'service': row['service'], 'total': Number(row['total'])}))
+		};
+		if (rdap === null || helper.isPrivateIp(ip)) return res.json(details);
+
+		rdap.lookup(ip).then((registration) => {
+			details.rdap = registration;
+		}, (err) => {
+			details.rdap_error = err.message;
+		}).then(() => {
+			res.json(details);
+		});
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Full-text search over the request, headers/payload and HTTP path, most recent first
+ * Supports MySQL boolean mode syntax, e.g. /api/search?q="etc passwd" or /api/search?q=+wget -curl
+ */
+router.get('/search', (req, res) => {
+	let limit = Math.min(parseInt(req.query.limit || 50, 10) || 50, 500);
+	if (!req.query.q || req.query.q.trim().length === 0) return res.status(400).send('`q` is required.');
+
+	query('SELECT ' + columns.join(', ') + ' FROM request WHERE MATCH(request, request_headers, http_request_path) AGAINST (? IN BOOLEAN MODE)' + scannerCondition(req) + ' ORDER BY id DESC LIMIT ?', [req.query.q, limit]).then((rows) => {
+		res.json(rows.map(rowToItem));
+	}).catch((error) => {
+		if (error.code === 'ER_PARSE_ERROR') return res.status(400).send('Invalid search query.');
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Requests by day of week and hour of day over the past `hours`, from the `stats_hourly` rollup plus the current hour
+ * Returns a 7x24 matrix, `data[day][hour]`, the days start from Sunday
+ * e.g. /api/heatmap?hours=720
+ */
+router.get('/heatmap', (req, res) => {
+	let hours = parseInt(req.query.hours || 24 * 30, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+
+	let current_hour = new Date();
+	current_hour.setMinutes(0, 0, 0);
+	let from = new Date(current_hour.getTime() - (hours - 1) * 3600 * 1000);
+
+	Promise.all([
+		query('SELECT DAYOFWEEK(hour_bucket) - 1 AS day, HOUR(hour_bucket) AS hour, SUM(total) AS total FROM stats_hourly WHERE hour_bucket >= ? AND hour_bucket < ? GROUP BY day, hour', [from, current_hour]),
+		query('SELECT IFNULL(SUM(count), 0) AS total FROM request WHERE date >= ?', [current_hour])
+	]).then(([rollup, live]) => {
+		let data = [];
+		for (let day = 0; day < 7; day++) data.push(new Array(24).fill(0));
+		rollup.forEach((row) => {
+			data[row['day']][row['hour']] = Number(row['total']);
+		});
+		data[current_hour.getDay()][current_hour.getHours()]+= Number(live[0]['total']);
+		res.json({'days': ['Sunday', 'Monday', 'Tuesday', 'Wednesday', 'Thursday', 'Friday', 'Saturday'], 'data': data});
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Requests per service between the dates from the `stats_hourly` rollup
+ * @param {Date} from
+ * @param {Date} to
+ * @returns {Promise} - Resolves with `total` and `services` (service => number of requests)
+ */
+const rollupTotals = (from, to) => {
+	return query('SELECT service, SUM(total) AS total FROM stats_hourly WHERE hour_bucket >= ? AND hour_bucket < ? GROUP BY service', [from, to]).then((rows) => {
+		let totals = {'total': 0, 'services': {}};
+		rows.forEach((row) => {
+			totals.services[row['service']] = Number(row['total']);
+			totals.total+= Number(row['total']);
+		});
+		return totals;
+	});
+};
+
+/**
+ * @param {number} current
+ * @param {number} previous
+ * @returns {object} - Absolute and percentage change, the percentage is null when there was nothing before
+ */
+const delta = (current, previous) => ({
+	'current': current,
+	'previous': previous,
+	'absolute': current - previous,
+	'percentage': previous === 0 ? null : Math.round((current - previous) / previous * 1000) / 10
+});
+
+/**
+ * Requests during the past `hours` compared to the preceding period of the same length, in total and per service
+ * Only the completed hours are compared, so both periods come from the rollup
+ * e.g. /api/stats/compare?hours=168
+ */
+router.get('/stats/compare', (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+
+	let current_hour = new Date();
+	current_hour.setMinutes(0, 0, 0);
+	let from = new Date(current_hour.getTime() - hours * 3600 * 1000);
+	let previous_from = new Date(current_hour.getTime() - 2 * hours * 3600 * 1000);
+
+	Promise.all([rollupTotals(from, current_hour), rollupTotals(previous_from, from)]).then(([current, previous]) => {
+		let services = {};
+		Object.keys(Object.assign({}, previous.services, current.services)).forEach((service) => {
+			services[service] = delta(current.services[service] || 0, previous.services[service] || 0);
+		});
+		res.json({
+			'hours': hours,
+			'from': from.getTime(),
+			'to': current_hour.getTime(),
+			'total': delta(current.total, previous.total),
+			'services': services
+		});
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Histogram of the time between consecutive requests, tells a steady drizzle of the botnets from the burst campaigns
+ * Only the most recent `limit` requests (up to 50000) are looked at, so that the cost stays the same however big the table is
+ * `burstiness` is the coefficient of variation: around 1 for random arrivals, well above it for bursts
+ * e.g. /api/timing?limit=10000&service=ssh
+ */
+router.get(['/timing', '/timing.csv'], (req, res) => {
+	let limit = parseInt(req.query.limit || 10000, 10);
+	if (isNaN(limit) || limit < 2 || limit > 50000) return res.status(400).send('`limit` should be between 2 and 50000.');
+	if (req.query.service !== undefined && !services.has(req.query.service)) return res.status(400).send('Unknown `service`.');
+
+	let params = req.query.service !== undefined ? [req.query.service, limit] : [limit];
+	query('SELECT date FROM request' + (req.query.service !== undefined ? ' WHERE service = ?' : '') + ' ORDER BY id DESC LIMIT ?', params).then((rows) => {
+		let intervals = [];
+		for (let i = 1; i < rows.length; i++) {
+			intervals.push(Math.abs(rows[i - 1]['date'].getTime() - rows[i]['date'].getTime()));
+		}
+		let counts = timingBuckets.map(() => 0);
+		intervals.forEach((interval) => {
+			counts[timingBuckets.findIndex((bucket) => interval < bucket)]++;
+		});
+		let histogram = timingBuckets.map((bucket, i) => ({
+			'from_ms': i === 0 ? 0 : timingBuckets[i - 1],
+			'to_ms': bucket === Infinity ? null : bucket,
+			'total': counts[i]
+		}));
+		if (req.path.endsWith('.csv')) return sendRows(req, res, histogram);
+
+		let sorted = intervals.slice().sort((a, b) => a - b);
+		let mean = intervals.reduce((sum, interval) => sum + interval, 0) / (intervals.length || 1);
+		let stddev = Math.sqrt(intervals.reduce((sum, interval) => sum + Math.pow(interval - mean, 2), 0) / (intervals.length || 1));
+		res.json({
+			'requests': rows.length,
+			'from': rows.length !== 0 ? rows[rows.length - 1]['date'].getTime() : null,
+			'median_ms': sorted.length !== 0 ? sorted[Math.floor(sorted.length / 2)] : null,
+			'mean_ms': Math.round(mean),
+			'burstiness': mean !== 0 ? Math.round(stddev / mean * 100) / 100 : null,
+			'histogram': histogram
+		});
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Services with unusually many requests during the past 24 hours compared to the `days` before
+ * Flagged when the count is `z` standard deviations above the daily mean or `multiplier` times the mean,
+ * services with fewer than `min` requests are never flagged
+ * e.g. /api/anomalies?days=7&z=3&multiplier=5&min=20
+ */
+router.get('/anomalies', (req, res) => {
+	let days = parseInt(req.query.days || 7, 10);
+	if (isNaN(days) || days < 2 || days > 60) return res.status(400).send('`days` should be between 2 and 60.');
+	let z = parseFloat(req.query.z || 3);
+	let multiplier = parseFloat(req.query.multiplier || 5);
+	let min = parseInt(req.query.min || 20, 10);
+	if (isNaN(z) || isNaN(multiplier) || isNaN(min)) return res.status(400).send('`z`, `multiplier` and `min` should be numbers.');
+
+	// Windows of 24 completed hours going back from the current hour, the 0th one is compared to the rest
+	let current_hour = new Date();
+	current_hour.setMinutes(0, 0, 0);
+	let from = new Date(current_hour.getTime() - (days + 1) * 24 * 3600 * 1000);
+
+	query('SELECT service, FLOOR((TIMESTAMPDIFF(HOUR, hour_bucket, ?) - 1) / 24) AS day, SUM(total) AS total FROM stats_hourly WHERE hour_bucket >= ? AND hour_bucket < ? GROUP BY service, day', [current_hour, from, current_hour]).then((rows) => {
+		let services = {};
+		rows.forEach((row) => {
+			services[row['service']] = services[row['service']] || new Array(days + 1).fill(0);
+			services[row['service']][row['day']] = Number(row['total']);
+		});
+		let anomalies = [];
+		Object.keys(services).forEach((service) => {
+			let [current, ...previous] = services[service];
+			let mean = previous.reduce((sum, total) => sum + total, 0) / days;
+			let stddev = Math.sqrt(previous.reduce((sum, total) => sum + Math.pow(total - mean, 2), 0) / days);
+			let z_score = stddev !== 0 ? (current - mean) / stddev : null;
+			let ratio = mean !== 0 ? current / mean : null;
+			if (current < min) return;
+			if ((z_score !== null && z_score >= z) || (ratio !== null && ratio >= multiplier) || mean === 0) {
+				anomalies.push({
+					'service': service,
+					'total': current,
+					'mean': Math.round(mean * 10) / 10,
+					'stddev': Math.round(stddev * 10) / 10,
+					'z_score': z_score !== null ? Math.round(z_score * 10) / 10 : null,
+					'ratio': ratio !== null ? Math.round(ratio * 10) / 10 : null
+				});
+			}
+		});
+		anomalies.sort((a, b) => (b.ratio === null ? Infinity : b.ratio) - (a.ratio === null ? Infinity : a.ratio));
+		res.json(anomalies);
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Request counts per destination port, the busiest first
+ * Completed hours come from the `stats_hourly_port` rollup, the current hour is counted live
+ * e.g. /api/ports?hours=24
+ */
+router.get(['/ports', '/ports.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+
+	let current_hour = new Date();
+	current_hour.setMinutes(0, 0, 0);
+	let from = new Date(current_hour.getTime() - (hours - 1) * 3600 * 1000);
+
+	Promise.all([
+		query('SELECT port, SUM(' + rollupTotal(req) + ') AS total FROM stats_hourly_port WHERE hour_bucket >= ? AND hour_bucket < ? GROUP BY port', [from, current_hour]),
+		query('SELECT port, SUM(count) AS total FROM request WHERE date >= ? AND port IS NOT NULL' + scannerCondition(req) + ' GROUP BY port', [current_hour])
+	]).then(([rollup, live]) => {
+		let totals = {};
+		rollup.concat(live).forEach((row) => {
+			totals[row['port']] = (totals[row['port']] || 0) + Number(row['total']);
+		});
+		let ports = Object.keys(totals).map((port) => ({'port': Number(port), 'total': totals[port]}));
+		ports.sort((a, b) => b.total - a.total);
+		sendRows(req, res, ports);
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Connections per destination port which didn't send anything (connect scans) compared to all, the most probed first
+ * Counted from the requests themselves, only the plain TCP services tell
+ * e.g. /api/connect_only?hours=24
+ */
+router.get(['/connect_only', '/connect_only.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+
+	query('SELECT port, SUM(count) AS total, SUM(IF(data_received = 0, count, 0)) AS connect_only FROM request WHERE date >= ? AND data_received IS NOT NULL' + scannerCondition(req) + ' GROUP BY port ORDER BY connect_only DESC', [new Date(Date.now() - hours * 3600 * 1000)]).then((rows) => {
+		sendRows(req, res, rows.map((row) => ({
+			'port': row['port'],
+			'total': Number(row['total']),
+			'connect_only': Number(row['connect_only']),
+			'ratio': Math.round(Number(row['connect_only']) / Number(row['total']) * 1000) / 1000
+		})));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * HTTP User-Agents by the number of requests, the missing ones are counted as `(none)`
+ * Completed hours come from the `stats_hourly_user_agent` rollup, the current hour is counted live
+ * e.g. /api/user_agents?hours=24&limit=100
+ */
+router.get(['/user_agents', '/user_agents.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+	let limit = Math.min(parseInt(req.query.limit || 100, 10) || 100, 1000);
+
+	let current_hour = new Date();
+	current_hour.setMinutes(0, 0, 0);
+	let from = new Date(current_hour.getTime() - (hours - 1) * 3600 * 1000);
+
+	Promise.all([
+		query('SELECT user_agent, SUM(' + rollupTotal(req) + ') AS total FROM stats_hourly_user_agent WHERE hour_bucket >= ? AND hour_bucket < ? GROUP BY user_agent', [from, current_hour]),
+		query("SELECT IFNULL(NULLIF(LEFT(user_agent, 200), ''), '(none)') AS user_agent, SUM(count) AS total FROM request WHERE date >= ? AND http_request_path IS NOT NULL" + scannerCondition(req) + ' GROUP BY 1', [current_hour])
+	]).then(([rollup, live]) => {
+		let totals = new Map();
+		rollup.concat(live).forEach((row) => {
+			totals.set(row['user_agent'], (totals.get(row['user_agent']) || 0) + Number(row['total']));
+		});
+		let user_agents = Array.from(totals, ([user_agent, total]) => ({'user_agent': user_agent, 'total': total}));
+		user_agents.sort((a, b) => b.total - a.total);
+		sendRows(req, res, user_agents.slice(0, limit));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * One of the tables of the stats page (`services`, `credentials`, `requests`, `ips`, `botnets` or `payloads`)
+ * for the past month, as cached for the page
+ * e.g. /api/stats.csv?table=credentials
+ */
+router.get(['/stats', '/stats.csv'], (req, res) => {
+	let table = req.query.table || 'services';
+	if (!monthlyTables.includes(table)) return res.status(400).send('`table` should be one of ' + monthlyTables.join(', ') + '.');
+	let rows = helper.monthlyStats.target(req.query.exclude_scanners === 'true')[table];
+	if (rows === undefined) return res.sendStatus(503);
+
+	sendRows(req, res, rows);
+});
+
+/**
+ * Headline numbers: requests since the midnight and during the past hour, unique IP addresses since the midnight
+ * Served from the in-memory counts which are refreshed every minute, yesterday's unique IP addresses from the daily rollup
+ * e.g. /api/summary
+ */
+router.get('/summary', (req, res) => {
+	Promise.all([
+		todayStats.snapshot(),
+		query('SELECT unique_ips FROM stats_daily WHERE day_bucket = DATE_SUB(CURDATE(), INTERVAL 1 DAY)')
+	]).then(([summary, yesterday]) => {
+		summary.unique_ips_yesterday = yesterday.length !== 0 ? yesterday[0]['unique_ips'] : null;
+		res.json(summary);
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Requests and distinct IP addresses per day over the past `days` (completed days only), from the `stats_daily` rollup
+ * e.g. /api/unique_ips?days=30
+ */
+router.get(['/unique_ips', '/unique_ips.csv'], (req, res) => {
+	let days = parseInt(req.query.days || 30, 10);
+	if (isNaN(days) || days < 1 || days > 365) return res.status(400).send('`days` should be between 1 and 365.');
+
+	query('SELECT day_bucket, total, unique_ips FROM stats_daily WHERE day_bucket >= DATE_SUB(CURDATE(), INTERVAL ? DAY) ORDER BY day_bucket', [days]).then((rows) => {
+		sendRows(req, res, rows.map((row) => ({
+			'day': new Date(row['day_bucket']).getTime(),
+			'total': row['total'],
+			'unique_ips': row['unique_ips']
+		})));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Busiest source subnets, a botnet spread over a /24 shows up here rather than as many small IP addresses
+ * e.g. /api/subnets?hours=24&prefix=24&prefix6=48&limit=50
+ */
+router.get(['/subnets', '/subnets.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+	let prefix4 = parseInt(req.query.prefix || 24, 10);
+	if (isNaN(prefix4) || prefix4 < 8 || prefix4 > 32) return res.status(400).send('`prefix` should be between 8 and 32.');
+	let prefix6 = parseInt(req.query.prefix6 || 48, 10);
+	if (isNaN(prefix6) || prefix6 < 16 || prefix6 > 128) return res.status(400).send('`prefix6` should be between 16 and 128.');
+	let limit = Math.min(parseInt(req.query.limit || 50, 10) || 50, 1000);
+
+	query('SELECT ip, SUM(count) AS total FROM request WHERE date >= ? GROUP BY ip', [new Date(Date.now() - hours * 3600 * 1000)]).then((rows) => {
+		let subnets = new Map();
+		rows.forEach((row) => {
+			let subnet = cidr.subnet(row['ip'], prefix4, prefix6);
+			if (subnet === null) return;
+			let entry = subnets.get(subnet) || {'subnet': subnet, 'total': 0, 'ips': 0};
+			entry.total+= Number(row['total']);
+			entry.ips++;
+			subnets.set(subnet, entry);
+		});
+		let list = Array.from(subnets.values());
+		list.sort((a, b) => b.total - a.total);
+		sendRows(req, res, list.slice(0, limit));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Port scans (see `port_scan` in the config) over the past `hours`, the most recent first
+ * e.g. /api/scans?hours=24&limit=100
+ */
+router.get(['/scans', '/scans.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+	let limit = Math.min(parseInt(req.query.limit || 100, 10) || 100, 1000);
+
+	query("SELECT date, ip, duration_ms, request_headers FROM request WHERE date >= ? AND service = 'portscan'" + scannerCondition(req) + ' ORDER BY date DESC LIMIT ?', [new Date(Date.now() - hours * 3600 * 1000), limit]).then((rows) => {
+		sendRows(req, res, rows.map((row) => {
+			let ports = (helper.decompress(row['request_headers']) || '').replace(/^ports: /, '').trim();
+			return {
+				'timestamp': row['date'].getTime(),
+				'ip': row['ip'],
+				'ports': ports.length !== 0 ? ports.split(', ').length : 0,
+				'duration_ms': row['duration_ms'],
+				'port_list': ports
+			};
+		}));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Emulated TCP ports with the server handling them and whether they are listened on: `listening`, `failed`
+ * (taken by another service or not permitted, see `error`), `starting` or `disabled` by `enabled_ports`/`disabled_ports`
+ * e.g. /api/services
+ */
+router.get(['/services', '/services.csv'], (req, res) => {
+	let started = new Map(CustomSocketServer.instances.map((server) => [server.port, server]));
+
+	sendRows(req, res, Object.keys(tcp_ports).map(Number).sort((a, b) => a - b).map((port) => {
+		let server = started.get(port) || null;
+		let status = 'disabled';
+		if (server !== null) status = server.listening !== 0 ? 'listening' : (server.failed !== null ? 'failed' : 'starting');
+		return {
+			'port': port,
+			'service': tcp_ports[port],
+			'handler': CustomSocketServer.handler(tcp_ports[port]),
+			'status': status,
+			'error': server !== null ? server.failed : null,
+			'high_value': config.high_value_ports.includes(port),
+			'connections': server !== null ? server.connections : 0
+		};
+	}));
+});
+
+/**
+ * Requests and IP addresses per tag of the `enrichment` (Tor exit nodes, datacenters) over the past `hours`,
+ * with their share of all the requests
+ * e.g. /api/tags?hours=168
+ */
+router.get(['/tags', '/tags.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+	let since = new Date(Date.now() - hours * 3600 * 1000);
+
+	Promise.all([
+		query('SELECT IFNULL(SUM(count), 0) AS total FROM request WHERE date >= ?' + scannerCondition(req), [since]),
+		query('SELECT DISTINCT tags FROM request WHERE date >= ? AND tags IS NOT NULL' + scannerCondition(req), [since])
+	]).then(([totals, rows]) => {
+		let total = Number(totals[0]['total']);
+		// A request can have several tags, e.g. a Tor exit node in a datacenter, so the same IP address is counted once per tag
+		let tags = Array.from(new Set([].concat(...rows.map((row) => row['tags'].split(',')))));
+
+		return Promise.all(tags.map((tag) => {
+			return query('SELECT IFNULL(SUM(count), 0) AS total, COUNT(DISTINCT ip) AS ips FROM request WHERE date >= ? AND FIND_IN_SET(?, tags)' + scannerCondition(req), [since, tag]).then((rows) => {
+				return {'tag': tag, 'total': Number(rows[0]['total']), 'ips': rows[0]['ips']};
+			});
+		})).then((tags) => {
+			sendRows(req, res, tags.sort((a, b) => b.total - a.total).map((tag) => {
+				return Object.assign(tag, {'percent': total !== 0 ? Math.round(tag.total / total * 1000) / 10 : 0});
+			}));
+		});
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Files uploaded over FTP and SFTP by their SHA-256, the most recently uploaded first
+ * `detection` is the blocklist or VirusTotal match, null when it hasn't been looked up
+ * e.g. /api/samples?limit=100
+ */
+router.get(['/samples', '/samples.csv'], (req, res) => {
+	let limit = Math.min(parseInt(req.query.limit || 100, 10) || 100, 1000);
+
+	query('SELECT sha256, size, file_name, service, first_ip, first_seen, last_seen, uploads, detection, checked_at FROM sample ORDER BY last_seen DESC LIMIT ?', [limit]).then((rows) => {
+		sendRows(req, res, rows.map((row) => Object.assign({}, row, {'first_seen': row['first_seen'].getTime(), 'last_seen': row['last_seen'].getTime(), 'checked_at': row['checked_at'] && row['checked_at'].getTime()})));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Requests matching the exploit signatures over the past `hours`, the most frequent first
+ * e.g. /api/exploits?hours=168
+ */
+router.get(['/exploits', '/exploits.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+
+	query('SELECT exploit, SUM(count) AS total, COUNT(DISTINCT ip) AS ips, MAX(date) AS last_seen FROM request WHERE date >= ? AND exploit IS NOT NULL' + scannerCondition(req) + ' GROUP BY exploit ORDER BY total DESC', [new Date(Date.now() - hours * 3600 * 1000)]).then((rows) => {
+		sendRows(req, res, rows.map((row) => ({
+			'exploit': row['exploit'],
+			'total': Number(row['total']),
+			'ips': row['ips'],
+			'last_seen': row['last_seen'].getTime()
+		})));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Most tried username/password pairs over the past `hours`, counted from the requests themselves (no rollup involved),
+ * so that the ranking is global for the whole range
+ * e.g. /api/credentials/top?hours=168&limit=100
+ */
+router.get(['/credentials/top', '/credentials/top.csv'], (req, res) => {
+	let hours = parseInt(req.query.hours || 24, 10);
+	if (isNaN(hours) || hours < 1 || hours > 24 * 90) return res.status(400).send('`hours` should be between 1 and 2160.');
+	let limit = Math.min(parseInt(req.query.limit || 100, 10) || 100, 500);
+
+	query('SELECT username, password, SUM(count) AS total, COUNT(DISTINCT ip) AS ips FROM request WHERE date >= ? AND username IS NOT NULL AND password IS NOT NULL' + scannerCondition(req) + ' GROUP BY username, password ORDER BY total DESC LIMIT ?', [new Date(Date.now() - hours * 3600 * 1000), limit]).then((rows) => {
+		sendRows(req, res, rows.map((row) => ({
+			'username': row['username'],
+			'password': row['password'],
+			'total': Number(row['total']),
+			'ips': row['ips']
+		})));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+/**
+ * Username/password pairs matching the `LIKE` patterns, grouped with the number of attempts
+ * e.g. /api/credentials?username=admin&password=admin or /api/credentials?username=root&password=%25123%25
+ * Rows without a password (public key or `none` authentication) are only included when `password` is not given
+ */
+router.get('/credentials', (req, res) => {
+	let limit = Math.min(parseInt(req.query.limit || 100, 10) || 100, 1000);
+	let conditions = ['username IS NOT NULL'];
+	let params = [];
+	if (req.query.username !== undefined) {
+		conditions.push('username LIKE ?');
+		params.push(req.query.username);
+	}
+	if (req.query.password !== undefined) {
+		conditions.push('password LIKE ?');
+		params.push(req.query.password);
+	}
+	params.push(limit);
+
+	query('SELECT username, password, SUM(count) AS total, MAX(date) AS last_seen FROM request WHERE ' + conditions.join(' AND ') + scannerCondition(req) + ' GROUP BY username, password ORDER BY total DESC LIMIT ?', params).then((rows) => {
+		res.json(rows.map((row) => ({
+			'username': row['username'],
+			'password': row['password'],
+			'total': Number(row['total']),
+			'last_seen': row['last_seen'].getTime()
+		})));
+	}).catch(() => {
+		res.sendStatus(503);
+	});
+});
+
+module.exports = router;
@@ -0,0 +1,49 @@
+"use strict";
+
+const config = require('./config');
+
+// Compiled once, the signatures come from the config
+const signatures = config.botnet_signatures.map((signature) => ({
+	'family': signature.family,
+	'credentials': signature.credentials || [],
+	'commands': (signature.commands || []).map((pattern) => new RegExp(pattern, 'i')),
+	'sequence': (signature.sequence || []).map((command) => command.toLowerCase())
+}));
+
+/**
+ * @param {Array} commands
+ * @param {Array} sequence
+ * @returns {boolean} - Whether the commands contain the sequence in order
+ */
+const containsSequence = (commands, sequence) => {
+	if (sequence.length === 0) return false;
+	let position = 0;
+	commands.forEach((command) => {
+		if (position < sequence.length && command.trim().toLowerCase() === sequence[position]) position++;
+	});
+
+	return position === sequence.length;
+};
+
+/**
+ * Guesses the botnet behind the telnet session
+ * The commands are a much stronger signal than the credentials, since the dictionaries are shared between the families
+ * @param {string} username
+ * @param {string} password
+ * @param {Array} commands - Shell commands of the session
+ * @returns {string|null} - Botnet family
+ */
+const classify = (username, password, commands) => {
+	let commandMatch = signatures.find((signature) => {
+		return signature.commands.some((pattern) => commands.some((command) => pattern.test(command))) || containsSequence(commands, signature.sequence);
+	});
+	if (commandMatch) return commandMatch.family;
+
+	let credentialsMatch = signatures.find((signature) => signature.credentials.includes(username + ':' + password));
+
+	return credentialsMatch ? credentialsMatch.family : null;
+};
+
+module.exports = {
+	classify: classify
+};
@@ -0,0 +1,36 @@
+"use strict";
+
+// Telltale behavior of the IoT botnets brute-forcing telnet
+// `credentials` - username:password pairs from the hardcoded dictionaries
+// `commands` - regular expressions matched against every command of the session
+// `sequence` - commands sent in this order (other commands in between are allowed)
+module.exports = [
+	{
+		'family': 'Mirai',
+		'credentials': [
+			'root:xc3511', 'root:vizxv', 'root:xmhdipc', 'root:juantech', 'root:123456', 'root:54321', 'root:klv123', 'root:klv1234',
+			'root:Zte521', 'root:hi3518', 'root:jvbzd', 'root:anko', 'root:zlxx.', 'root:7ujMko0vizxv', 'root:7ujMko0admin',
+			'root:dreambox', 'root:realtek', 'root:00000000', 'root:ikwb', 'root:system', 'root:user', 'root:1111111', 'root:666666',
+			'admin:7ujMko0admin', 'admin:meinsm', 'admin:smcadmin', 'admin:1111', 'admin:admin1234', 'support:support',
+			'supervisor:supervisor', 'guest:12345', 'mother:fucker', 'Administrator:admin', 'service:service', 'tech:tech',
+			'888888:888888', '666666:666666', 'ubnt:ubnt'
+		],
+		'commands': ['^/bin/busybox (MIRAI|OKIRU|SATORI|MASUTA|OWARI|SORA|ECHOBOT|HAKAI)\\b'],
+		'sequence': ['enable', 'system', 'shell', 'sh']
+	},
+	{
+		'family': 'Hajime',
+		'credentials': [],
+		'commands': ['^/bin/busybox ECCHI\\b'],
+		'sequence': []
+	},
+	{
+		'family': 'Gafgyt',
+		'credentials': [],
+		'commands': [
+			'cd /tmp \\|\\| cd /var/run \\|\\| cd /mnt \\|\\| cd /root \\|\\| cd /',
+			'(wget|tftp|curl).*\\b(bins|gtop|bot|x86|mips|arm7?)\\.sh\\b'
+		],
+		'sequence': []
+	}
+];
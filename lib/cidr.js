@@ -0,0 +1,140 @@
+"use strict";
+
+const net = require('net');
+
+/**
+ * @param {string} ip - IPv4 or IPv6 address
+ * @returns {null|object} - Address family and the address as a number
+ */
+const parseIp = (ip) => {
+	let family = net.isIP(ip);
+	if (family === 4) {
+		return {'family': 4, 'value': ip.split('.').reduce((value, octet) => (value << 8n) + BigInt(octet), 0n)};
+	}
+	if (family === 6) {
+		// Expanding `::` and the embedded IPv4 address (e.g. ::ffff:1.2.3.4) into eight groups
+		let [head, tail] = ip.includes('::') ? ip.split('::') : [ip, null];
+		let groups = (part) => part.length === 0 ? [] : part.split(':').reduce((list, group) => {
+			if (group.includes('.')) {
+				let octets = group.split('.').map(Number);
+				return list.concat([(octets[0] << 8) + octets[1], (octets[2] << 8) + octets[3]].map((word) => word.toString(16)));
+			}
+			return list.concat(group);
+		}, []);
+		let head_groups = groups(head);
+		let tail_groups = tail === null ? [] : groups(tail);
+		let all = head_groups.concat(new Array(8 - head_groups.length - tail_groups.length).fill('0'), tail_groups);
+		return {'family': 6, 'value': all.reduce((value, group) => (value << 16n) + BigInt(parseInt(group, 16)), 0n)};
+	}
+
+	return null;
+};
+
+/**
+ * @param {string} cidr - e.g. `192.0.2.0/24`, `2001:db8::/32` or a single address
+ * @returns {object} - Network to match the addresses against
+ */
+const parse = (cidr) => {
+	let [address, prefix] = cidr.trim().split('/');
+	let ip = parseIp(address);
+	if (ip === null) throw new Error('Invalid IP address or subnet: ' + cidr);
+	let bits = ip.family === 4 ? 32 : 128;
+	prefix = prefix === undefined ? bits : parseInt(prefix, 10);
+	if (isNaN(prefix) || prefix < 0 || prefix > bits) throw new Error('Invalid IP address or subnet: ' + cidr);
+	let mask = ((1n << BigInt(prefix)) - 1n) << BigInt(bits - prefix);
+
+	return {'family': ip.family, 'mask': mask, 'network': ip.value & mask};
+};
+
+/**
+ * Lists like the datacenter ranges have tens of thousands of networks, which are looked up in for every request
+ * @param {Array} networks - Parsed with `parse`
+ * @returns {object} - First and last addresses of each family, sorted and with the overlapping networks merged
+ */
+const ranges = (networks) => {
+	let list = {4: [], 6: []};
+	networks.map((network) => {
+		let bits = network.family === 4 ? 32n : 128n;
+		return {'family': network.family, 'first': network.network, 'last': network.network | (((1n << bits) - 1n) ^ network.mask)};
+	}).sort((a, b) => a.first < b.first ? -1 : a.first > b.first ? 1 : 0).forEach((range) => {
+		let family = list[range.family];
+		let previous = family[family.length - 1];
+		if (previous && range.first <= previous.last + 1n) {
+			if (range.last > previous.last) previous.last = range.last;
+		}
+		else family.push({'first': range.first, 'last': range.last});
+	});
+
+	return list;
+};
+
+/**
+ * @param {object} list - Made with `ranges`
+ * @param {string} ip
+ * @returns {boolean}
+ */
+const contains = (list, ip) => {
+	let address = parseIp(ip);
+	if (address === null) return false;
+	let family = list[address.family];
+	// Binary search for the last range starting at the address or before it
+	let low = 0;
+	let high = family.length - 1;
+	while (low <= high) {
+		let middle = (low + high) >> 1;
+		if (family[middle].first <= address.value) low = middle + 1;
+		else high = middle - 1;
+	}
+
+	return high >= 0 && address.value <= family[high].last;
+};
+
+/**
+ * @param {number} family
+ * @param {BigInt} value
+ * @returns {string} - Address in the usual notation, IPv6 with the longest run of zero groups shortened to `::`
+ */
+const formatIp = (family, value) => {
+	if (family === 4) return [24n, 16n, 8n, 0n].map((shift) => (value >> shift) & 0xffn).join('.');
+
+	let groups = [112n, 96n, 80n, 64n, 48n, 32n, 16n, 0n].map((shift) => ((value >> shift) & 0xffffn).toString(16));
+	let zeros = (':' + groups.join(':') + ':').match(/:(0:)+/g) || [];
+	let longest = zeros.reduce((longest, run) => run.length > longest.length ? run : longest, '');
+	if (longest.length <= 3) return groups.join(':');
+
+	return (':' + groups.join(':') + ':').replace(longest, '::').replace(/^:(?!:)|(?<!:):$/g, '');
+};
+
+/**
+ * @param {string} ip
+ * @param {number} prefix4 - Prefix length for the IPv4 addresses
+ * @param {number} prefix6 - Prefix length for the IPv6 addresses
+ * @returns {null|string} - Subnet the address belongs to, e.g. `192.0.2.0/24`
+ */
+const subnet = (ip, prefix4, prefix6) => {
+	let address = parseIp(ip);
+	if (address === null) return null;
+	let network = parse(ip + '/' + (address.family === 4 ? prefix4 : prefix6));
+
+	return formatIp(network.family, network.network) + '/' + (address.family === 4 ? prefix4 : prefix6);
+};
+
+/**
+ * @param {string} ip
+ * @returns {string} - Address in the canonical form (IPv4-mapped IPv6 as IPv4, IPv6 compressed, lowercase and without the zone), anything else as is
+ */
+const normalize = (ip) => {
+	let address = parseIp(String(ip).replace(/%.*$/, ''));
+	if (address === null) return ip;
+	if (address.family === 6 && (address.value >> 32n) === 0xffffn) return formatIp(4, address.value & 0xffffffffn);
+
+	return formatIp(address.family, address.value);
+};
+
+module.exports = {
+	parse: parse,
+	normalize: normalize,
+	ranges: ranges,
+	contains: contains,
+	subnet: subnet
+};
@@ -0,0 +1,135 @@
+"use strict";
+
+const fs = require('fs');
+const net = require('net');
+const cidr = require('./cidr');
+
+const isPort = (port) => Number.isInteger(port) && port > 0 && port < 65536;
+const isCount = (count) => Number.isInteger(count) && count >= 0;
+const isRegExp = (pattern) => {
+	try {
+		new RegExp(pattern, 'i');
+		return true;
+	} catch (err) {
+		return false;
+	}
+};
+
+/**
+ * Catches the mistakes which would otherwise show up as confusing behavior at runtime
+ * @param config - With the defaults filled in
+ * @returns {Array} - Error messages
+ */
+const validate = (config) => {
+	let errors = [];
+	if (!net.isIP(config.server_ip)) errors.push('`server_ip` should be an IP address.');
+	if (!isPort(config.express_js_alternative_port)) errors.push('`express_js_alternative_port` should be a port number.');
+	['enabled_ports', 'disabled_ports', 'high_value_ports', 'listen_addresses', 'trusted_proxies', 'known_scanners', 'ssh_host_keys', 'cors_allowed_origins', 'redact_fields', 'exploit_signatures', 'botnet_signatures'].forEach((key) => {
+		if (!Array.isArray(config[key])) errors.push('`' + key + '` should be a list.');
+	});
+	if (errors.length !== 0) return errors;
+
+	config.enabled_ports.concat(config.disabled_ports, config.high_value_ports).filter((port) => !isPort(port)).forEach((port) => {
+		errors.push('`' + port + '` in `enabled_ports`/`disabled_ports`/`high_value_ports` is not a port number.');
+	});
+	let pasv_range = /^(\d+)-(\d+)$/.exec(config.ftp_pasv_range);
+	if (pasv_range === null || !isPort(Number(pasv_range[1])) || !isPort(Number(pasv_range[2])) || Number(pasv_range[1]) > Number(pasv_range[2])) {
+		errors.push('`ftp_pasv_range` should be a range of ports, e.g. 50000-50100.');
+	}
+	config.listen_addresses.filter((address) => !net.isIP(address)).forEach((address) => {
+		errors.push('`' + address + '` in `listen_addresses` is not an IP address.');
+	});
+	config.cors_allowed_origins.forEach((origin) => {
+		let url = null;
+		try {
+			url = new URL(origin);
+		} catch (err) {}
+		if (origin !== '*' && (url === null || !['http:', 'https:'].includes(url.protocol) || url.origin !== origin)) errors.push('`' + origin + '` in `cors_allowed_origins` should be an origin like https://soc.example.com (no path or trailing slash).');
+	});
+	config.known_scanners.forEach((scanner) => {
+		if (!scanner.name || !Array.isArray(scanner.networks)) return errors.push('Every one of the `known_scanners` should have a `name` and a list of `networks`.');
+		scanner.networks.forEach((network) => {
+			try {
+				cidr.parse(network);
+			} catch (err) {
+				errors.push('`known_scanners` ' + scanner.name + ': ' + err.message);
+			}
+		});
+	});
+	[config.enrichment.tor_exit_list].concat(Object.values(config.enrichment.datacenter_ranges)).filter((file) => file && !fs.existsSync(file)).forEach((file) => {
+		errors.push('`enrichment` list ' + file + ' does not exist.');
+	});
+	if (config.ssh_host_keys.length === 0) errors.push('`ssh_host_keys` should list at least one key file.');
+	['max_connections_per_ip', 'max_ssh_sessions'].forEach((key) => {
+		if (!isCount(config[key])) errors.push('`' + key + '` should be a whole number, 0 or more.');
+	});
+	if (!isCount(config.port_scan.threshold)) errors.push('`port_scan.threshold` should be a whole number, 0 or more.');
+	if (!(config.port_scan.window > 0)) errors.push('`port_scan.window` should be a positive number of seconds.');
+	if (!(Number.isInteger(config.mysql_connect_retry.attempts) && config.mysql_connect_retry.attempts >= 1)) errors.push('`mysql_connect_retry.attempts` should be a whole number, 1 or more.');
+	['initial_delay', 'max_delay'].forEach((key) => {
+		if (!(config.mysql_connect_retry[key] > 0)) errors.push('`mysql_connect_retry.' + key + '` should be a positive number of seconds.');
+	});
+	if (!(config.database_size.warning_bytes >= 0)) errors.push('`database_size.warning_bytes` should be a number of bytes, 0 or more.');
+	if (!(config.database_size.interval > 0)) errors.push('`database_size.interval` should be a positive number of seconds.');
+	if (!(config.http_dedup_window >= 0)) errors.push('`http_dedup_window` should be a number of seconds, 0 or more.');
+	// Timeouts are in seconds, either general (`idle`, `session`) or per service (e.g. `telnet_session`)
+	for (let key in config.timeouts) {
+		if (!(config.timeouts[key] > 0)) errors.push('`timeouts.' + key + '` should be a positive number of seconds.');
+	}
+	for (let key in config.limits) {
+		if (!(config.limits[key] > 0)) errors.push('`limits.' + key + '` should be a positive number of bytes.');
+	}
+	for (let key in config.sampling) {
+		if (!(Number.isInteger(config.sampling[key]) && config.sampling[key] >= 1)) errors.push('`sampling.' + key + '` should be a whole number, 1 or more.');
+	}
+	for (let key in config.tarpit) {
+		if (!(config.tarpit[key] >= 0)) errors.push('`tarpit.' + key + '` should be a number of milliseconds.');
+	}
+	if (config.samples.blocklist_file && !fs.existsSync(config.samples.blocklist_file)) errors.push('`samples.blocklist_file` ' + config.samples.blocklist_file + ' does not exist.');
+	['requests_per_minute', 'timeout'].forEach((key) => {
+		if (!(config.samples[key] > 0)) errors.push('`samples.' + key + '` should be a positive number.');
+	});
+	if (config.rdap.enabled) {
+		if (!/^https:\/\//.test(config.rdap.url)) errors.push('`rdap.url` should be an https:// URL the IP address is appended to.');
+		['cache_ttl', 'requests_per_minute', 'timeout'].forEach((key) => {
+			if (!(config.rdap[key] > 0)) errors.push('`rdap.' + key + '` should be a positive number.');
+		});
+	}
+	if (config.syslog.host && !isPort(config.syslog.port)) errors.push('`syslog.port` should be a port number.');
+	if (!['udp', 'tcp'].includes(config.syslog.protocol)) errors.push('`syslog.protocol` should be either `udp` or `tcp`.');
+	['max_entries', 'max_attempts'].forEach((key) => {
+		if (!isCount(config.delivery_queue[key]) || config.delivery_queue[key] === 0) errors.push('`delivery_queue.' + key + '` should be a whole number, 1 or more.');
+	});
+	['retry_interval', 'max_retry_interval'].forEach((key) => {
+		if (!(config.delivery_queue[key] > 0)) errors.push('`delivery_queue.' + key + '` should be a positive number.');
+	});
+	if (!(config.emulation.ssh_accept_probability >= 0 && config.emulation.ssh_accept_probability <= 1)) errors.push('`emulation.ssh_accept_probability` should be between 0 and 1.');
+	if (!isCount(config.emulation.ssh_accept_after)) errors.push('`emulation.ssh_accept_after` should be a whole number, 0 or more.');
+	if (!isCount(config.emulation.telnet_max_commands) || config.emulation.telnet_max_commands === 0) errors.push('`emulation.telnet_max_commands` should be a whole number, 1 or more.');
+	['hostname', 'distro', 'kernel', 'arch', 'ssh_version'].forEach((key) => {
+		if (typeof config.persona[key] !== 'string' || config.persona[key].length === 0) errors.push('`persona.' + key + '` should be a non-empty string.');
+	});
+	if (!Array.isArray(config.persona.users) || config.persona.users.length === 0) errors.push('`persona.users` should be a list of usernames.');
+	for (let prefix in config.emulation.admin_panels) {
+		let page = config.emulation.admin_panels[prefix];
+		if (!prefix.startsWith('/')) errors.push('`emulation.admin_panels` ' + prefix + ' should start with a slash.');
+		if (!['phpmyadmin', 'jenkins', 'router'].includes(page) && !fs.existsSync(page)) errors.push('`emulation.admin_panels` ' + prefix + ': ' + page + ' is neither a built-in page nor an existing file.');
+	}
+	if (!['echo', 'not_found', 'error'].includes(config.emulation.catch_all)) errors.push('`emulation.catch_all` should be one of `echo`, `not_found` or `error`.');
+	config.exploit_signatures.forEach((signature) => {
+		if (!signature.name || !Array.isArray(signature.patterns)) return errors.push('Every one of the `exploit_signatures` should have a `name` and a list of `patterns`.');
+		signature.patterns.forEach((pattern) => {
+			if (!isRegExp(pattern)) errors.push('`exploit_signatures` ' + signature.name + ': ' + pattern + ' is not a valid regular expression.');
+		});
+	});
+	config.botnet_signatures.forEach((signature) => {
+		if (!signature.family) return errors.push('Every one of the `botnet_signatures` should have a `family`.');
+		(signature.commands || []).forEach((pattern) => {
+			if (!isRegExp(pattern)) errors.push('`botnet_signatures` ' + signature.family + ': ' + pattern + ' is not a valid regular expression.');
+		});
+	});
+
+	return errors;
+};
+
+module.exports = validate;
@@ -0,0 +1,146 @@
+"use strict";
+
+const path = require('path');
+const logger = require('./logger');
+const tcpPorts = require('./tcp-ports');
+const botnetSignatures = require('./botnet-signatures');
+const exploitSignatures = require('./exploit-signatures');
+const validate = require('./config-validator');
+// Another file can be given with HONEYPOT_CONFIG, the tests run with `./config.js.template`
+const config = require(process.env.HONEYPOT_CONFIG || './../config');
+
+// Defaults for the options missing in `./config.js`, so that older configs keep working after an update
+// @see ./config.js.template
+const defaults = {
+	icmpv6_capture: true,
+	botnet_signatures: botnetSignatures,
+	exploit_signatures: exploitSignatures,
+	trusted_proxies: ['loopback'],
+	listen_addresses: [],
+	enabled_ports: [],
+	disabled_ports: [],
+	ftp_pasv_range: '50000-50100',
+	max_connections_per_ip: 20,
+	max_ssh_sessions: 200,
+	high_value_ports: [22, 2375, 3389, 6379, 27017],
+	ssh_host_keys: [path.join(__dirname, '..', 'etc', 'ssh2.private.key')],
+	known_scanners: [],
+	enrichment: {
+		tor_exit_list: '',
+		datacenter_ranges: {}
+	},
+	port_scan: {
+		threshold: 10,
+		window: 60
+	},
+	http_dedup_window: 0,
+	dashboard_auth_token: '',
+	cors_allowed_origins: [],
+	redact_fields: [],
+	spill_file: path.join(__dirname, '..', 'data', 'spill.jsonl'),
+	compression: {
+		request: false,
+		request_headers: false
+	},
+	database_size: {
+		warning_bytes: 0,
+		interval: 3600
+	},
+	mysql_connect_retry: {
+		attempts: 5,
+		initial_delay: 1,
+		max_delay: 30
+	},
+	http_rate_limit: {
+		requests_per_second: 10,
+		burst: 50,
+		summary_interval: 60
+	},
+	abuseipdb: {
+		api_key: '',
+		report_login_attempts: true,
+		services: [],
+		cooldown: 24 * 3600,
+		interval: 60
+	},
+	rdap: {
+		enabled: false,
+		url: 'https://rdap.org/ip/',
+		cache_ttl: 24 * 3600,
+		requests_per_minute: 10,
+		timeout: 5
+	},
+	samples: {
+		blocklist_file: '',
+		virustotal_api_key: '',
+		requests_per_minute: 4,
+		timeout: 10
+	},
+	syslog: {
+		host: '',
+		port: 514,
+		protocol: 'udp',
+		facility: 16
+	},
+	delivery_queue: {
+		max_entries: 10000,
+		max_attempts: 10,
+		retry_interval: 30,
+		max_retry_interval: 3600
+	},
+	limits: {
+		max_request_bytes: 4000,
+		max_payload_bytes: 64 * 1024,
+		capture_bytes: 16 * 1024,
+		high_value_payload_bytes: 1024 * 1024
+	},
+	tarpit: {},
+	sampling: {},
+	timeouts: {
+		idle: 5,
+		session: 60,
+		high_value_idle: 30,
+		high_value_session: 300
+	},
+	persona: {
+		hostname: 'localhost',
+		distro: 'BusyBox v1.19.4',
+		kernel: '3.10.14',
+		arch: 'mips',
+		ssh_version: 'OpenSSH_7.6',
+		users: ['root', 'admin']
+	},
+	emulation: {
+		mysql_version: '5.7.23-0ubuntu0.18.04.1',
+		ssh_accept_probability: 1,
+		ssh_accept_after: 0,
+		telnet_max_commands: 256,
+		http_server_header: 'Apache/2.4.29 (Ubuntu)',
+		smtp_banner: 'ESMTP Postfix (Ubuntu)',
+		docker_version: '18.06.1-ce',
+		ftp_files: [
+			{'path': '/backup.tar.gz', 'size': 48213504, 'date': '2018-06-02T03:14:00Z'},
+			{'path': '/db_dump.sql', 'size': 9812441, 'date': '2018-05-28T23:40:00Z'},
+			{'path': '/upload/'},
+			{'path': '/public_html/index.php', 'size': 4521, 'date': '2018-03-11T10:02:00Z'},
+			{'path': '/public_html/wp-config.php.bak', 'size': 3128, 'date': '2018-03-11T10:05:00Z'}
+		],
+		catch_all: 'echo',
+		admin_panels: {},
+		web_server_header: '',
+		redirect_unknown_hosts: true
+	}
+};
+
+for (let key in defaults) {
+	if (config[key] === undefined) config[key] = defaults[key];
+	else if (typeof defaults[key] === 'object' && !Array.isArray(defaults[key])) config[key] = Object.assign({}, defaults[key], config[key]);
+}
+
+let errors = validate(config);
+if (errors.length !== 0) throw new Error(errors.join(' '));
+// Not wrong, but most likely not what was meant
+if (config.https_only && !config.nginx_reverse_proxy) logger.warning('`https_only` redirects to HTTPS, which is only served by the Nginx reverse proxy (`nginx_reverse_proxy`).');
+if (config.nginx_reverse_proxy && Object.keys(tcpPorts).map(Number).includes(config.express_js_alternative_port)) logger.warning('`express_js_alternative_port` ' + config.express_js_alternative_port + ' is also one of the honeypot ports, disable it with `disabled_ports`.');
+
+module.exports = config;
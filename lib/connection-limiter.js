@@ -0,0 +1,63 @@
+"use strict";
+
+const EventEmitter = require('events');
+
+/**
+ * Concurrent connections per IP address, shared by all the TCP services
+ * Emits `summary` with the number of dropped connections per IP and service once every `summaryInterval` seconds
+ */
+class ConnectionLimiter extends EventEmitter {
+	/**
+	 * @param {number} limit - Concurrent connections allowed per IP address, 0 disables the limiter
+	 * @param {number} summaryInterval - Seconds between the summaries
+	 */
+	constructor(limit, summaryInterval) {
+		super();
+		this.limit = limit;
+		this.connections = new Map();
+		this.dropped = new Map();
+
+		setInterval(() => { this.flush(); }, summaryInterval * 1000);
+	}
+
+	/**
+	 * @param {string} ip
+	 * @param {string} service
+	 * @returns {boolean} - Whether the connection should be served, if so `release` has to be called once it is closed
+	 */
+	acquire(ip, service) {
+		if (!this.limit) return true;
+
+		let count = this.connections.get(ip) || 0;
+		if (count >= this.limit) {
+			let key = ip + '|' + service;
+			this.dropped.set(key, (this.dropped.get(key) || 0) + 1);
+			return false;
+		}
+		this.connections.set(ip, count + 1);
+
+		return true;
+	}
+
+	/**
+	 * @param {string} ip
+	 */
+	release(ip) {
+		if (!this.limit) return;
+
+		let count = (this.connections.get(ip) || 1) - 1;
+		if (count === 0) this.connections.delete(ip);
+		else this.connections.set(ip, count);
+	}
+
+	flush() {
+		this.dropped.forEach((dropped, key) => {
+			let ip = key.substr(0, key.indexOf('|'));
+			let service = key.substr(key.indexOf('|') + 1);
+			this.emit('summary', ip, service, dropped);
+		});
+		this.dropped.clear();
+	}
+}
+
+module.exports = ConnectionLimiter;
@@ -0,0 +1,34 @@
+"use strict";
+
+const config = require('./config');
+
+const origins = config.cors_allowed_origins;
+
+/**
+ * Lets the pages of the `cors_allowed_origins` (e.g. a SOC dashboard) call the API, same origin only when there are none
+ * The preflight requests carry no Authorization header, so they are answered before the auth
+ */
+const middleware = (req, res, next) => {
+	let origin = req.headers['origin'];
+	if (!origin || !(origins.includes('*') || origins.includes(origin))) return next();
+
+	res.set('Access-Control-Allow-Origin', origins.includes('*') ? '*' : origin);
+	res.set('Vary', 'Origin');
+	if (req.method !== 'OPTIONS') return next();
+
+	res.set('Access-Control-Allow-Methods', 'GET');
+	res.set('Access-Control-Allow-Headers', 'Authorization');
+	res.set('Access-Control-Max-Age', '3600');
+	res.sendStatus(204);
+};
+
+/* The same origins may embed the dashboard and the stats page in a frame */
+const frameAncestors = (req, res, next) => {
+	if (origins.length !== 0) res.set('Content-Security-Policy', "frame-ancestors 'self' " + origins.join(' '));
+	next();
+};
+
+module.exports = {
+	middleware: middleware,
+	frameAncestors: frameAncestors
+};
@@ -1,19 +1,113 @@
 "use strict";
 
 const {FtpSrv, FileSystem} = require('ftp-srv');
+const path = require('path');
+const stream = require('stream');
+const crypto = require('crypto');
+const config = require('./config');
 
+/**
+ * Stats in the shape ftp-srv formats the `ls -l` lines from
+ * @param {string} name
+ * @param {boolean} directory
+ * @param {number} size
+ * @param {string} date
+ */
+const stat = (name, directory, size, date) => ({
+	'name': name,
+	'size': directory ? 4096 : size,
+	'mtime': date ? new Date(date) : new Date(),
+	'uid': 'www-data',
+	'gid': 'www-data',
+	'mode': directory ? 0o40755 : 0o100644,
+	'isDirectory': () => directory
+});
+
+/**
+ * Fake file system of `emulation.ftp_files`, nothing is ever read from or written to the disk
+ * The operations are recorded, so that the session can be logged with what the client was after
+ */
 class MyFileSystem extends FileSystem {
-	constructor() {super(...arguments);}
-	currentDirectory() {}
-	get(fileName) {}
-	list(path = '.') {}
-	chdir(path = '.') {}
-	write(fileName, {append = false, start = undefined} = {}) {}
-	read(fileName, {start = undefined} = {}) {}
-	delete(path) {}
-	mkdir(path) {}
-	rename(from, to) {}
-	chmod(path, mode) {}
+	constructor() {
+		super(...arguments);
+		this.cwd = '/';
+		this.operations = [];
+		this.uploads = {};
+		// Directories are either listed with a trailing slash or implied by the paths of the files
+		this.files = {'/': stat('/', true)};
+		config.emulation.ftp_files.forEach((file) => {
+			let file_path = path.posix.resolve('/', file.path);
+			let directory = file.path.endsWith('/');
+			this.files[file_path] = stat(path.posix.basename(file_path), directory, file.size || 0, file.date);
+			for (let parent = path.posix.dirname(file_path); !this.files[parent]; parent = path.posix.dirname(parent)) {
+				this.files[parent] = stat(path.posix.basename(parent), true, 0, file.date);
+			}
+		});
+	}
+
+	resolve(fileName) {
+		return path.posix.resolve(this.cwd, fileName || '.');
+	}
+
+	currentDirectory() {
+		return this.cwd;
+	}
+
+	get(fileName) {
+		let file = this.files[this.resolve(fileName)];
+		if (!file) throw new Error('No such file or directory');
+		return file;
+	}
+
+	list(path_name = '.') {
+		let directory = this.resolve(path_name);
+		if (!this.files[directory] || !this.files[directory].isDirectory()) throw new Error('No such file or directory');
+		return Object.keys(this.files).filter((file_path) => file_path !== '/' && path.posix.dirname(file_path) === directory).map((file_path) => this.files[file_path]);
+	}
+
+	chdir(path_name = '.') {
+		let directory = this.resolve(path_name);
+		this.operations.push('CWD ' + directory);
+		if (!this.files[directory] || !this.files[directory].isDirectory()) throw new Error('No such file or directory');
+		this.cwd = directory;
+		return this.cwd;
+	}
+
+	write(fileName, {append = false, start = undefined} = {}) {
+		let file_path = this.resolve(fileName);
+		this.operations.push('STOR ' + file_path);
+		let upload = this.uploads[file_path] = {'size': 0, 'hash': crypto.createHash('sha256')};
+		// Uploads are counted and hashed but not kept
+		return new stream.Writable({
+			write: (chunk, encoding, callback) => {
+				upload.size+= chunk.length;
+				upload.hash.update(chunk);
+				callback();
+			}
+		});
+	}
+
+	read(fileName, {start = undefined} = {}) {
+		this.operations.push('RETR ' + this.resolve(fileName));
+		throw new Error('Permission denied');
+	}
+
+	delete(path_name) {
+		this.operations.push('DELE ' + this.resolve(path_name));
+	}
+
+	mkdir(path_name) {
+		this.operations.push('MKD ' + this.resolve(path_name));
+		return this.resolve(path_name);
+	}
+
+	rename(from, to) {
+		this.operations.push('RNFR ' + this.resolve(from) + ' RNTO ' + this.resolve(to));
+	}
+
+	chmod(path_name, mode) {
+		this.operations.push('SITE CHMOD ' + mode + ' ' + this.resolve(path_name));
+	}
 }
 
-module.exports = MyFileSystem;
\ No newline at end of file
+module.exports = MyFileSystem;
@@ -1,13 +1,36 @@
 "use strict";
 
-const config = require('./../config');
+const config = require('./config');
 const helper = require('./../lib/helper');
 const EventEmitter = require('events');
-const fs = require('fs');
+const crypto = require('crypto');
 const net = require('net');
 const FtpSrv = require('ftp-srv');
+const FtpFileSystem = require('./custom-ftp-file-system');
 const ssh2 = require('ssh2');
-const chalk = require('chalk');
+const sshHostKeys = require('./ssh-host-keys');
+const logger = require('./logger');
+const resp = require('./resp');
+const mysqlProtocol = require('./mysql-protocol');
+const rdpProtocol = require('./rdp-protocol');
+const socksProtocol = require('./socks-protocol');
+const httpRequest = require('./http-request');
+const dockerApi = require('./docker-api');
+const botnetClassifier = require('./botnet-classifier');
+const exploitClassifier = require('./exploit-classifier');
+const protocolDetector = require('./protocol-detector');
+const payloadClassifier = require('./payload-classifier');
+const deobfuscator = require('./deobfuscator');
+const ConnectionLimiter = require('./connection-limiter');
+const PortScanDetector = require('./port-scan-detector');
+
+// Shared by all the services, so that a single host cannot exhaust the file descriptors
+const connectionLimiter = new ConnectionLimiter(config.max_connections_per_ip, 60);
+const portScanDetector = new PortScanDetector(config.port_scan.threshold, config.port_scan.window);
+// Open SSH sessions of all the SSH ports, capped by `max_ssh_sessions`, and the connections dropped over it
+const sshSessions = {'open': 0, 'dropped': 0};
+// Every server started, @see /api/services
+const instances = [];
 
 class SocketServer extends EventEmitter {
 	/**
@@ -16,8 +39,17 @@ class SocketServer extends EventEmitter {
 	 */
 	constructor(port, name) {
 		super();
-		this.port = port;
+		this.port = Number(port);
 		this.name = name;
+		this.servers = [];
+		// Listeners started and the error code of the one which could not, open connections, @see /metrics
+		this.listening = 0;
+		this.failed = null;
+		this.connections = 0;
+		// Connections to the `high_value_ports` are read for longer and captured in full (up to its own limit)
+		this.highValue = config.high_value_ports.includes(this.port);
+		this.maxPayloadLength = this.highValue ? config.limits.high_value_payload_bytes : config.limits.capture_bytes;
+		instances.push(this);
 		this.start();
 	}
 
@@ -25,124 +57,1527 @@ class SocketServer extends EventEmitter {
 		throw new Error('You have to implement the `start` method!');
 	}
 
+	/**
+	 * Plain TCP server taking care of the connection housekeeping shared by the emulated services:
+	 * error handling, idle timeout and the total session time limit
+	 * @param {function} handler - Called with the socket, client's IP address and port
+	 */
+	createServer(handler) {
+		let onConnection = (socket) => {
+			let remoteAddress = socket.remoteAddress;
+			if (!remoteAddress) return socket.destroy();
+			let ip = helper.formatIpAddress(remoteAddress);
+			portScanDetector.record(ip, this.port);
+			if (!connectionLimiter.acquire(ip, this.name)) return socket.destroy();
+			this.connections++;
+			socket._connected_at = Date.now();
+			socket.on('error', (err) => {
+				socket.end();
+				socket.destroy();
+			});
+			// Keep reading until the client goes idle or closes the connection, but never longer than the session timeout
+			// A client sending a byte now and then is never idle, so it is cut off by the latter and saved with `end_reason` session_timeout
+			socket.setTimeout(this.timeout('idle'));
+			socket.on('timeout', () => {
+				socket.end();
+				socket.destroy();
+			});
+			let sessionTimer = setTimeout(() => {
+				socket._expired = true;
+				socket.destroy();
+			}, this.timeout('session'));
+			socket.on('close', () => {
+				clearTimeout(sessionTimer);
+				connectionLimiter.release(ip);
+				this.connections--;
+			});
+			handler(socket, ip, socket.remotePort);
+		};
+		this.listenOptions().forEach((options) => {
+			this.servers.push(this.track(net.createServer(onConnection).on('error', (err) => {
+				this.onError(err);
+			})).listen(options));
+		});
+	}
+
+	/**
+	 * Writes to the client, one byte at a time with the configured delay when the service is tarpitted
+	 * The session timeout still applies, so a tarpitted connection can't be held open for longer than that
+	 * @param socket
+	 * @param {string|Buffer} data
+	 * @param {boolean} [end] - Closes the connection once everything is written
+	 */
+	send(socket, data, end) {
+		let delay = config.tarpit[this.name];
+		if (!delay) return end ? socket.end(data) : socket.write(data);
+
+		let tarpit = socket._tarpit || (socket._tarpit = {'queue': Buffer.alloc(0), 'end': false, 'timer': null});
+		if (tarpit.end) return;
+		if (data) tarpit.queue = Buffer.concat([tarpit.queue, Buffer.from(data)]);
+		tarpit.end = !!end;
+		if (tarpit.timer) return;
+		let drip = () => {
+			tarpit.timer = null;
+			if (socket.destroyed) return;
+			if (tarpit.queue.length === 0) return tarpit.end && socket.end();
+			socket.write(tarpit.queue.slice(0, 1));
+			tarpit.queue = tarpit.queue.slice(1);
+			tarpit.timer = setTimeout(drip, delay);
+		};
+		drip();
+	}
+
+	/**
+	 * `listen` options for every address of the `listen_addresses`, all the interfaces when there are none
+	 * @returns {Array}
+	 */
+	listenOptions() {
+		if (config.listen_addresses.length === 0) return [{'port': this.port}];
+
+		// IPv6 listeners don't take the IPv4 traffic, otherwise `::` would collide with `0.0.0.0`
+		return config.listen_addresses.map((address) => ({'port': this.port, 'host': address, 'ipv6Only': net.isIPv6(address)}));
+	}
+
+	/* Stops accepting new connections. The open ones still go on, their requests are spilled once the database is being flushed */
+	close() {
+		this.servers.forEach((server) => {
+			server.close();
+		});
+	}
+
+	/**
+	 * Service specific timeout from the config (e.g. `telnet_session`), then the one of the `high_value_ports`,
+	 * the general one otherwise
+	 * @param {string} kind - Either `idle` or `session`
+	 * @returns {number} - Milliseconds
+	 */
+	timeout(kind) {
+		let seconds = config.timeouts[this.name + '_' + kind];
+		if (seconds === undefined && this.highValue) seconds = config.timeouts['high_value_' + kind];
+
+		return (seconds !== undefined ? seconds : config.timeouts[kind]) * 1000;
+	}
+
+	/**
+	 * Basic item emitted for a connection to the plain TCP server
+	 * @param socket
+	 * @param {string} ip
+	 * @param {number} port - Client's port
+	 */
+	connectionInfo(socket, ip, port) {
+		let info = {
+			'ip': ip,
+			'service': this.name,
+			'request': 'Connection from ' + ip + ':' + port,
+			'duration_ms': Date.now() - socket._connected_at,
+			'port': this.port,
+			// Connect-only probes (masscan, etc.) don't send anything
+			'data_received': socket.bytesRead !== 0
+		};
+		if (socket._expired) info.end_reason = 'session_timeout';
+
+		return info;
+	}
+
+	/**
+	 * Emits `bound` with `true` once the listener has started, see `onError` for the other way round
+	 * @param server
+	 * @returns - The same server
+	 */
+	track(server) {
+		server.once('listening', () => {
+			this.listening++;
+			this.emit('bound', true);
+		});
+
+		return server;
+	}
+
 	onError(err) {
-		if (err.code === 'EADDRINUSE') console.log(chalk.bgYellow.bold('Warning:') + ' Cannot start `' + this.name + '` service on port ' + this.port + '. Error Code: EADDRINUSE, Address already in use.');
-		else if (err.code === 'EACCES') console.log(chalk.bgYellow.bold('Warning:') + ' Cannot start `' + this.name + '` service on port ' + this.port + '. Error Code: EACCES, Permission Denied.');
+		if (err.code === 'EADDRINUSE') logger.warning('Cannot start `' + this.name + '` service on port ' + this.port + '. Error Code: EADDRINUSE, Address already in use.');
+		else if (err.code === 'EACCES') logger.warning('Cannot start `' + this.name + '` service on port ' + this.port + '. Error Code: EACCES, Permission Denied.');
 		else throw new Error(err);
+		this.failed = err.code;
+		this.emit('bound', false);
 	}
 }
 
+/**
+ * @param files - Size and SHA-256 hash (still being computed) of the uploaded files by their path
+ * @returns {Array} - Non-empty uploads with the hex digest, @see SampleTracker
+ */
+const hashUploads = (files) => Object.keys(files).filter((path) => files[path].size !== 0).map((path) => {
+	return {'path': path, 'size': files[path].size, 'sha256': files[path].hash.digest('hex')};
+});
+
 class SshSocketServer extends SocketServer {
 	start() {
-		new ssh2.Server({
-			hostKeys: [fs.readFileSync(__dirname + '/../etc/ssh2.private.key')],
-			banner: 'Hi there!',
-			ident: 'OpenSSH_7.6'
-		}, (client) => {
-			client.on('authentication', (ctx) => {
-				if (ctx.method !== 'password') return ctx.reject(['password']);
-				else if (ctx.method === 'password') {
-					if (client._client_info) {
+		this.listenOptions().forEach((options) => {
+			this.servers.push(this.track(new ssh2.Server({
+				hostKeys: sshHostKeys.load(config.ssh_host_keys),
+				ident: config.persona.ssh_version
+			}, (client) => {
+				client.on('authentication', (ctx) => {
+					if (ctx.method !== 'password') return ctx.reject(['password']);
+					else if (ctx.method === 'password') {
+						if (client._client_info) {
+							this.emit('data', {
+								'username': ctx.username,
+								'password': ctx.password,
+								'ip': client._client_info.ip,
+								'service': this.name,
+								'request': (ctx.username && ctx.username.length !== '') ? this.name + ' ' + ctx.username + '@' + config.server_ip + ':' + this.port : this.name + ' ' + config.server_ip + ':' + this.port,
+								'request_headers': helper.formatHeaders(client._client_info.header),
+								'duration_ms': Date.now() - client._connected_at,
+								'port': this.port,
+								'hassh': client._hassh
+							});
+						}
+						client._username = ctx.username;
+						// Like a real server, most of the guesses can be turned down, every one of them is logged anyway
+						client._attempts = (client._attempts || 0) + 1;
+						if (client._attempts <= config.emulation.ssh_accept_after || Math.random() >= config.emulation.ssh_accept_probability) return ctx.reject(['password']);
+						ctx.accept();
+					}
+				}).on('ready', () => {
+					// Giving the client a moment to open a session, the connection is closed right away otherwise
+					let timer = setTimeout(() => { client.end(); }, this.timeout('idle'));
+					client.on('session', (accept, reject) => {
+						clearTimeout(timer);
+						this.handleSession(client, accept());
+					});
+					// Port forwarding attempts are logged and refused, the target tells what the client was going to pivot to
+					client.on('tcpip', (accept, reject, info) => {
 						this.emit('data', {
-							'username': ctx.username,
-							'password': ctx.password,
+							'username': client._username,
 							'ip': client._client_info.ip,
 							'service': this.name,
-							'request': (ctx.username && ctx.username.length !== '') ? this.name + ' ' + ctx.username + '@' + config.server_ip + ':' + this.port : this.name + ' ' + config.server_ip + ':' + this.port,
-							'request_headers': helper.formatHeaders(client._client_info.header)
+							'request': 'direct-tcpip ' + (client._username ? client._username + '@' : '') + config.server_ip + ':' + this.port + ' to ' + info.destIP + ':' + info.destPort,
+							'http_request_path': info.destIP + ':' + info.destPort,
+							'request_headers': helper.formatHeaders({'destination': info.destIP + ':' + info.destPort, 'originator': info.srcIP + ':' + info.srcPort}),
+							'duration_ms': Date.now() - client._connected_at,
+							'port': this.port,
+							'hassh': client._hassh
 						});
-					}
-					ctx.accept();
+						reject();
+					});
+					client.on('close', () => {
+						clearTimeout(timer);
+					});
+				}).on('close', () => {
+					if (client._sftp) this.logSftp(client);
+				}).on('error', () => {
 					client.end();
+				});
+			}).on('connection', (client, info) => {
+				let ip = helper.formatIpAddress(info.ip);
+				portScanDetector.record(ip, this.port);
+				if (!this.acquireSession()) return client.end();
+				if (!connectionLimiter.acquire(ip, this.name)) {
+					sshSessions.open--;
+					return client.end();
 				}
-			}).on('ready', () => {
-				client.end();
-			}).on('error', () => {
-				client.end();
+				this.connections++;
+				// From the connection on, so that neither the slow password guessing nor opening one session after another
+				// keeps the client connected for longer
+				let lifetime = setTimeout(() => { this.expire(client); }, this.timeout('session'));
+				client.on('close', () => {
+					clearTimeout(lifetime);
+					sshSessions.open--;
+					connectionLimiter.release(ip);
+					this.connections--;
+				});
+				client._client_info = info;
+				client._connected_at = Date.now();
+				// ssh2 doesn't expose the algorithms offered by the client, they are only seen on its protocol stream
+				if (client._sshstream) client._sshstream.once('KEXINIT', (init) => {
+					try {
+						client._hassh = SshSocketServer.hassh(init);
+					} catch (err) {}
+				});
+			}).on('error', (err) => {
+				this.onError(err);
+			})).listen(options));
+		});
+	}
+
+	/**
+	 * Disconnects the client at the end of the session timeout, the SFTP sessions are logged with the `end_reason` on close
+	 * @param client
+	 */
+	expire(client) {
+		client._expired = true;
+		if (!client._sftp) {
+			this.emit('data', {
+				'username': client._username,
+				'ip': client._client_info.ip,
+				'service': this.name,
+				'request': this.name + ' ' + (client._username ? client._username + '@' : '') + config.server_ip + ':' + this.port + ' disconnected at the session timeout',
+				'duration_ms': Date.now() - client._connected_at,
+				'port': this.port,
+				'hassh': client._hassh,
+				'end_reason': 'session_timeout'
 			});
-		}).on('connection', (client, info) => {
-			client._client_info = info;
-		}).on('error', (err) => {
-			this.onError(err);
-		}).listen(this.port);
+		}
+		client.end();
+	}
+
+	/**
+	 * Every SSH session carries its own crypto state, so their total number (across the SSH ports) is capped by `max_ssh_sessions`
+	 * The dropped connections are summed up in a warning once a minute while the cap is being hit
+	 * @returns {boolean} - Whether the session can go on, the count has to be decreased once it is closed
+	 */
+	acquireSession() {
+		if (!config.max_ssh_sessions || sshSessions.open < config.max_ssh_sessions) {
+			sshSessions.open++;
+			return true;
+		}
+		if (sshSessions.dropped++ === 0) {
+			setTimeout(() => {
+				logger.warning('The limit of ' + config.max_ssh_sessions + ' concurrent SSH sessions was hit, ' + sshSessions.dropped + ' connections dropped during the past minute.');
+				sshSessions.dropped = 0;
+			}, 60 * 1000);
+		}
+
+		return false;
+	}
+
+	/**
+	 * Only the SFTP subsystem is served, shell and exec requests are rejected
+	 * @param client
+	 * @param session
+	 */
+	handleSession(client, session) {
+		session.on('sftp', (accept, reject) => {
+			client._sftp = {'operations': [], 'uploads': {}, 'files': {}, 'uploaded': 0};
+			this.serveSftp(client._sftp, accept());
+		});
+		session.on('subsystem', (accept, reject, info) => {
+			client._sftp = client._sftp || {'operations': [], 'uploads': {}, 'files': {}, 'uploaded': 0};
+			client._sftp.operations.push('SUBSYSTEM ' + info.name);
+			reject();
+		});
+	}
+
+	/**
+	 * Pretends to be an empty, writable file system, so that the client goes on with its uploads
+	 * @param sftp - Captured operations and the uploaded data, the size and hash of the whole files
+	 * @param sftpStream
+	 */
+	serveSftp(sftp, sftpStream) {
+		let handles = [];
+		let handle = (path) => {
+			let id = Buffer.alloc(4);
+			id.writeUInt32BE(handles.push(path) - 1, 0);
+			return id;
+		};
+		let handlePath = (id) => id.length === 4 ? handles[id.readUInt32BE(0)] : undefined;
+		let record = (operation) => {
+			if (sftp.operations.length < 256) sftp.operations.push(operation);
+		};
+
+		sftpStream.on('REALPATH', (reqid, path) => {
+			let name = path === '.' || path === '' ? '/root' : path;
+			sftpStream.name(reqid, [{'filename': name, 'longname': name, 'attrs': {}}]);
+		}).on('STAT', (reqid, path) => {
+			record('STAT ' + path);
+			sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.NO_SUCH_FILE);
+		}).on('LSTAT', (reqid, path) => {
+			record('LSTAT ' + path);
+			sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.NO_SUCH_FILE);
+		}).on('OPENDIR', (reqid, path) => {
+			record('OPENDIR ' + path);
+			sftpStream.handle(reqid, handle(path));
+		}).on('READDIR', (reqid, id) => {
+			sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.EOF);
+		}).on('OPEN', (reqid, filename, flags, attrs) => {
+			record('OPEN ' + filename);
+			sftpStream.handle(reqid, handle(filename));
+		}).on('READ', (reqid, id, offset, length) => {
+			sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.EOF);
+		}).on('WRITE', (reqid, id, offset, data) => {
+			let path = handlePath(id);
+			if (path === undefined) return sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.FAILURE);
+			let file = sftp.files[path] = sftp.files[path] || {'size': 0, 'hash': crypto.createHash('sha256')};
+			file.size+= data.length;
+			file.hash.update(data);
+			let chunk = data.slice(0, Math.max(0, this.maxPayloadLength - sftp.uploaded));
+			sftp.uploaded+= chunk.length;
+			sftp.uploads[path] = Buffer.concat([sftp.uploads[path] || Buffer.alloc(0), chunk]);
+			sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.OK);
+		}).on('FSTAT', (reqid, id) => {
+			sftpStream.attrs(reqid, {'mode': 0o100644, 'uid': 0, 'gid': 0, 'size': 0, 'atime': 0, 'mtime': 0});
+		}).on('CLOSE', (reqid, id) => {
+			sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.OK);
+		});
+		['REMOVE', 'RMDIR', 'MKDIR', 'SETSTAT'].forEach((operation) => {
+			sftpStream.on(operation, (reqid, path) => {
+				record(operation + ' ' + path);
+				sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.OK);
+			});
+		});
+		sftpStream.on('RENAME', (reqid, oldPath, newPath) => {
+			record('RENAME ' + oldPath + ' ' + newPath);
+			sftpStream.status(reqid, ssh2.SFTP_STATUS_CODE.OK);
+		});
+	}
+
+	logSftp(client) {
+		let sftp = client._sftp;
+		let request_headers = sftp.operations.join('\r\n') + '\r\n';
+		let uploads = hashUploads(sftp.files);
+		uploads.forEach((upload) => {
+			request_headers+= '\r\n' + upload.path + ' (' + upload.size + ' bytes, sha256 ' + upload.sha256 + '):\r\n' + sftp.uploads[upload.path].toString();
+		});
+		let info = {
+			'username': client._username,
+			'ip': client._client_info.ip,
+			'service': this.name,
+			'request': 'sftp ' + (client._username ? client._username + '@' : '') + config.server_ip + ':' + this.port,
+			'request_headers': request_headers,
+			'duration_ms': Date.now() - client._connected_at,
+			'port': this.port,
+			'hassh': client._hassh,
+			'uploads': uploads
+		};
+		if (client._expired) info.end_reason = 'session_timeout';
+		this.emit('data', info);
+	}
+
+	/**
+	 * HASSH fingerprint of the client: MD5 of the key exchange, encryption, MAC and compression algorithms it offers
+	 * @see https://github.com/salesforce/hassh
+	 * @param init - Client's KEXINIT as parsed by ssh2-streams
+	 * @returns {string}
+	 */
+	static hassh(init) {
+		let list = (algorithms) => Array.isArray(algorithms) ? algorithms.join(',') : String(algorithms || '');
+		let algorithms = init.algorithms;
+
+		return crypto.createHash('md5').update([list(algorithms.kex), list(algorithms.cs.encrypt), list(algorithms.cs.mac), list(algorithms.cs.compress)].join(';')).digest('hex');
 	}
 }
 
+// ftp-srv listens for every data connection of the passive mode on its own server, which is not the one tracked below
+const PassiveConnector = require('ftp-srv/src/connector/passive');
+const setupPassiveServer = PassiveConnector.prototype.setupServer;
+PassiveConnector.prototype.setupServer = function () {
+	return setupPassiveServer.apply(this, arguments).then((result) => {
+		// Taking a slot of `max_connections_per_ip` like the control connection does
+		if (this.dataServer) this.dataServer.on('connection', (socket) => {
+			let ip = helper.formatIpAddress(socket.remoteAddress || '');
+			if (!connectionLimiter.acquire(ip, 'ftp')) return socket.destroy();
+			socket.on('close', () => {
+				connectionLimiter.release(ip);
+			});
+		});
+		return result;
+	});
+};
+
 class FtpSocketServer extends SocketServer {
 	start() {
-		new FtpSrv('ftp://0.0.0.0:' + this.port, {
-			fs: require('./custom-ftp-file-system'),
-			greeting: 'Hi There!',
-			anonymous: true,
-			log: require('bunyan').createLogger({level: 60, name: 'noname'})
-		}).on('login', ({connection, username, password}, resolve, reject) => {
-			connection.close();
-			this.emit('data', {
-				'username': username,
-				'password': password,
-				'ip': connection.ip,
-				'service': this.name,
-				'request': 'ftp://' + username + ':' + password + '@' + config.server_ip + ':' + this.port
+		this.listenOptions().forEach((options) => {
+			let host = !options.host ? '0.0.0.0' : (net.isIPv6(options.host) ? '[' + options.host + ']' : options.host);
+			let ftpServer = new FtpSrv('ftp://' + host + ':' + this.port, {
+				greeting: config.persona.hostname + ' FTP server ready.',
+				anonymous: true,
+				pasv_url: config.server_ip,
+				pasv_range: config.ftp_pasv_range,
+				log: require('bunyan').createLogger({level: 60, name: 'noname'})
+			}).on('login', ({connection, username, password}, resolve, reject) => {
+				// Every login succeeds, the client is then served the fake file system and its operations are logged on close
+				let file_system = new FtpFileSystem(connection);
+				connection.commandSocket.once('close', () => {
+					this.logSession(connection, username, file_system);
+				});
+				resolve({'fs': file_system});
+				this.emit('data', {
+					'username': username,
+					'password': password,
+					'ip': connection.ip,
+					'service': this.name,
+					'request': 'ftp://' + username + ':' + password + '@' + config.server_ip + ':' + this.port,
+					'duration_ms': Date.now() - connection.commandSocket._connected_at,
+					'port': this.port
+				});
+			}).on('error', (err) => {
+				this.onError(err);
 			});
-		}).on('error', (err) => {
-			this.onError(err);
-		}).listen();
+			this.servers.push(this.track(ftpServer.server));
+			ftpServer.server.on('connection', (socket) => {
+				let ip = helper.formatIpAddress(socket.remoteAddress || '');
+				portScanDetector.record(ip, this.port);
+				if (!connectionLimiter.acquire(ip, this.name)) return socket.destroy();
+				this.connections++;
+				socket.setTimeout(this.timeout('idle'));
+				socket.on('timeout', () => {
+					socket.destroy();
+				});
+				let sessionTimer = setTimeout(() => {
+					socket._expired = true;
+					socket.destroy();
+				}, this.timeout('session'));
+				socket.on('close', () => {
+					clearTimeout(sessionTimer);
+					connectionLimiter.release(ip);
+					this.connections--;
+				});
+				socket._connected_at = Date.now();
+			});
+			ftpServer.listen();
+		});
+	}
+
+	/**
+	 * @param connection
+	 * @param {string} username
+	 * @param file_system - Fake file system of the session
+	 */
+	logSession(connection, username, file_system) {
+		if (file_system.operations.length === 0) return;
+		let request_headers = file_system.operations.join('\r\n') + '\r\n';
+		let uploads = hashUploads(file_system.uploads);
+		uploads.forEach((upload) => {
+			request_headers+= '\r\n' + upload.path + ' (' + upload.size + ' bytes uploaded, sha256 ' + upload.sha256 + ')';
+		});
+		let info = {
+			'username': username,
+			'ip': connection.ip,
+			'service': this.name,
+			'request': 'ftp session ' + username + '@' + config.server_ip + ':' + this.port,
+			'request_headers': request_headers,
+			'duration_ms': Date.now() - connection.commandSocket._connected_at,
+			'port': this.port,
+			'uploads': uploads
+		};
+		if (connection.commandSocket._expired) info.end_reason = 'session_timeout';
+		this.emit('data', info);
 	}
 }
 
+// Services that talk line by line, so we are sending a prompt after every line to get more data out of the client
+const lineOrientedServices = ['POP3', 'IMAP', 'nntp', 'finger', 'ident', 'memcached', 'rsync'];
+
 class GenericSocketServer extends SocketServer {
 	start() {
-		net.createServer((socket) => {
-			socket.setEncoding('utf8');
-			socket.on('error', (err) => {
-				socket.end();
-				socket.destroy();
+		this.createServer((socket, ip, port) => {
+			let payload = Buffer.alloc(0);
+			let truncated = false;
+			this.send(socket, 'Hi There ' + socket.remoteAddress + ':' + port + '\r\n');
+			socket.on('data', (data) => {
+				truncated = truncated || payload.length + data.length > this.maxPayloadLength;
+				payload = Buffer.concat([payload, data.slice(0, this.maxPayloadLength - payload.length)]);
+				// Whatever comes after the limit is lost anyway
+				if (payload.length >= this.maxPayloadLength) socket.destroy();
+				else if (lineOrientedServices.includes(this.name) && (data[data.length - 1] === 0x0d || data[data.length - 1] === 0x0a)) this.send(socket, '> ');
 			});
-			socket.write('Hi There ' + socket.remoteAddress + ':' + socket.remotePort + '\r\n');
-			socket.setTimeout(5000);
-			socket.on('timeout', () => {
-				this.log(socket);
-				socket.end();
-				socket.destroy();
+			socket.on('close', () => {
+				this.log(socket, ip, port, payload, truncated);
 			});
+		});
+	}
+
+	/**
+	 * Binary payloads are stored as hex, since they would be mangled as text
+	 * @param socket
+	 * @param {string} ip
+	 * @param {number} port
+	 * @param {Buffer} data
+	 * @param {boolean} truncated - Whether more was sent than captured
+	 */
+	log(socket, ip, port, data, truncated) {
+		let info = this.connectionInfo(socket, ip, port);
+		if (truncated) {
+			info.truncated = true;
+			data = protocolDetector.trimPartialCharacter(data);
+		}
+		if (data.length !== 0) {
+			info.request_headers = protocolDetector.isPrintable(data) ? data.toString('utf8') : 'hex: ' + data.toString('hex');
+			if (info.request_headers.trim().length === 0) delete info.request_headers;
+			info.payload_kind = payloadClassifier.classify(data);
+		}
+		// Tells the real protocol when the client is not speaking what is expected on the port
+		let detected = protocolDetector.detect(data);
+		if (detected !== null) {
+			info.request = detected.protocol + ' connection from ' + ip + ':' + port;
+			if (detected.details.username) info.username = detected.details.username;
+			// The server name tells which site the scanner was after, the same way as the HTTP path does
+			if (detected.details.sni) {
+				info.http_request_path = detected.details.sni;
+				info.request+= ' (SNI: ' + detected.details.sni + ')';
+			}
+			if (info.request_headers !== undefined && Object.keys(detected.details).length !== 0) info.request_headers = helper.formatHeaders(detected.details) + '\r\n' + info.request_headers;
+		}
+
+		this.emit('data', info);
+	}
+}
+
+// Applets of a typical router's busybox, the bots are probing for the missing ones
+const busyboxApplets = ['ash', 'awk', 'cat', 'cd', 'chmod', 'cp', 'cut', 'dd', 'echo', 'free', 'grep', 'head', 'hostname', 'id', 'kill', 'ls', 'mkdir', 'mount', 'mv', 'nproc', 'ps', 'pwd', 'rm', 'sed', 'sh', 'sort', 'tail', 'tftp', 'tr', 'uname', 'uniq', 'wc', 'wget', 'whoami'];
+
+const persona = config.persona;
+const shellUser = persona.users[0];
+const unameAll = 'Linux ' + persona.hostname + ' ' + persona.kernel + ' #1 SMP Tue Jul 21 10:20:12 UTC 2018 ' + persona.arch + ' GNU/Linux';
+
+// Files the bots usually look at to fingerprint the device, in line with the `persona`
+const telnetFiles = {
+	'/proc/cpuinfo': persona.arch.startsWith('mips')
+		? 'system type\t\t: MT7621\r\nmachine\t\t\t: Unknown\r\nprocessor\t\t: 0\r\ncpu model\t\t: MIPS 1004Kc V2.15\r\nBogoMIPS\t\t: 583.68\r\n'
+		: 'processor\t: 0\r\nvendor_id\t: GenuineIntel\r\nmodel name\t: Intel(R) Xeon(R) CPU E5-2650 v4 @ 2.20GHz\r\ncpu MHz\t\t: 2199.998\r\ncache size\t: 30720 KB\r\n',
+	'/etc/passwd': 'root:x:0:0:root:/root:/bin/sh\r\ndaemon:x:1:1:daemon:/usr/sbin:/bin/false\r\nnobody:x:65534:65534:nobody:/nonexistent:/bin/false\r\n' + persona.users.filter((user) => user !== 'root').map((user, i) => {
+		return user + ':x:' + (1000 + i) + ':' + (1000 + i) + ':' + user + ':/home/' + user + ':/bin/sh\r\n';
+	}).join(''),
+	'/etc/hostname': persona.hostname + '\r\n',
+	'/etc/issue': persona.distro + ' \\n \\l\r\n',
+	'/proc/version': 'Linux version ' + persona.kernel + ' (buildd@lcy01-amd64-017) #1 SMP Tue Jul 21 10:20:12 UTC 2018\r\n',
+	'/proc/mounts': 'rootfs / rootfs rw 0 0\r\nproc /proc proc rw,relatime 0 0\r\ntmpfs /tmp tmpfs rw,relatime 0 0\r\n'
+};
+// Banner and prompt of BusyBox's shell for a BusyBox `distro`, of bash otherwise
+const busyboxShell = persona.distro.startsWith('BusyBox');
+const telnetBanner = busyboxShell
+	? persona.distro + ' (2015-07-21 10:22:39 CST) built-in shell (ash)\r\nEnter \'help\' for a list of built-in commands.\r\n\r\n'
+	: 'Welcome to ' + persona.distro + ' (GNU/Linux ' + persona.kernel + ' ' + persona.arch + ')\r\n\r\n';
+const telnetPrompt = (busyboxShell ? '' : shellUser + '@' + persona.hostname + ':/') + (shellUser === 'root' ? '# ' : '$ ');
+
+const TELNET_PASSWORD_TIMEOUT = 3000;
+
+class TelnetSocketServer extends SocketServer {
+	start() {
+		this.createServer((socket, ip, port) => {
+			let session = {'buffer': '', 'state': 'username', 'username': undefined, 'password': undefined, 'commands': [], 'length': 0};
+			let passwordTimer = null;
+			this.send(socket, '\r\n' + persona.hostname + ' login: ');
 			socket.on('data', (data) => {
-				this.log(socket, data);
-				socket.end();
-				socket.destroy();
+				session.length+= data.length;
+				if (session.length > this.maxPayloadLength) return socket.destroy();
+				session.buffer+= this.stripNegotiation(data);
+				let lines = session.buffer.split(/\r\n|\r\0|\r|\n/);
+				session.buffer = lines.pop();
+				lines.forEach((line) => {
+					if (socket.destroyed) return;
+					if (session.state === 'username') {
+						session.username = line;
+						session.state = 'password';
+						this.send(socket, 'Password: ');
+						// Some bots never send the password, giving them the shell anyway
+						passwordTimer = setTimeout(() => {
+							if (session.state === 'password') this.startShell(socket, session);
+						}, TELNET_PASSWORD_TIMEOUT);
+					}
+					else if (session.state === 'password' && this.looksLikeCommand(line)) {
+						// No password, the bot went straight to the commands
+						this.startShell(socket, session);
+						this.runCommand(socket, session, line);
+					}
+					else if (session.state === 'password') {
+						session.password = line;
+						this.startShell(socket, session);
+					}
+					else {
+						this.runCommand(socket, session, line);
+					}
+				});
 			});
-		}).on('error', (err) => {
-			this.onError(err);
-		}).listen(this.port);
+			socket.on('close', () => {
+				clearTimeout(passwordTimer);
+				// Keeping the credentials sent without the line ending before the disconnect
+				if (session.state !== 'shell' && session.buffer.length !== 0) {
+					session[session.state] = session.buffer;
+					session.buffer = '';
+				}
+				this.log(socket, ip, port, session);
+			});
+		});
 	}
 
-	log(socket, data) {
-		let ip = socket.remoteAddress;
-		ip = helper.formatIpAddress(ip);
-		let info = {
-			'ip': ip,
-			'service': this.name,
-			'request': 'Connection from ' + ip + ':' + socket.remotePort
+	startShell(socket, session) {
+		session.state = 'shell';
+		this.send(socket, '\r\n' + telnetBanner + telnetPrompt);
+	}
+
+	runCommand(socket, session, line) {
+		session.commands.push(line);
+		// Disconnected by us rather than left, saved with the `end_reason`
+		if (session.commands.length >= config.emulation.telnet_max_commands) session.kicked = true;
+		if (session.kicked || /^\s*(exit|logout)\s*$/.test(line)) return this.send(socket, '', true);
+		this.send(socket, TelnetSocketServer.execute(line) + telnetPrompt);
+	}
+
+	/**
+	 * Whether the line sent instead of the password is a shell command
+	 * Single words are always taken for the password, since `enable` or `system` are in the botnets' dictionaries too
+	 * @param {string} line
+	 * @returns {boolean}
+	 */
+	looksLikeCommand(line) {
+		if (/[;|&]|^\s*\//.test(line)) return true;
+		let args = line.trim().split(/\s+/);
+
+		return args.length > 1 && (busyboxApplets.includes(args[0]) || args[0] === 'busybox');
+	}
+
+	/**
+	 * Removes the telnet option negotiation (IAC sequences) from the received data
+	 * @param {Buffer} data
+	 * @returns {string}
+	 */
+	stripNegotiation(data) {
+		let bytes = [];
+		for (let i = 0; i < data.length; i++) {
+			if (data[i] !== 255) bytes.push(data[i]);
+			else if (data[i + 1] === 255) bytes.push(data[++i]);
+			// Subnegotiation lasts until IAC SE
+			else if (data[i + 1] === 250) {
+				let end = data.indexOf(Buffer.from([255, 240]), i);
+				i = end === -1 ? data.length : end + 1;
+			}
+			// WILL, WONT, DO, DONT are followed by the option
+			else if (data[i + 1] >= 251 && data[i + 1] <= 254) i+= 2;
+			else i++;
+		}
+
+		return Buffer.from(bytes).toString('utf8');
+	}
+
+	/**
+	 * Fake output of the shell command line, just enough for the bots to carry on
+	 * `&&` and `||` are treated the same as `;`, as every command succeeds
+	 * @param {string} line
+	 * @returns {string}
+	 */
+	static execute(line) {
+		return line.split(/;|&&|\|\|/).map((command) => {
+			// Only the filters get the output of the previous stage, the canned output of anything else replaces it
+			return command.split('|').reduce((input, stage) => TelnetSocketServer.run(stage, input), null);
+		}).join('');
+	}
+
+	/**
+	 * @param {string} command
+	 * @param {string|null} input - Output of the previous stage of the pipe
+	 * @returns {string}
+	 */
+	static run(command, input) {
+		let args = command.trim().split(/\s+/);
+		let busybox = args[0] === '/bin/busybox' || args[0] === 'busybox';
+		if (busybox) args.shift();
+		if (args.length === 0 || args[0].length === 0) return input || '';
+		let argument = args.slice(1).filter((arg) => !arg.startsWith('-')).join(' ').replace(/^(['"])(.*)\1$/, '$2');
+
+		switch (args[0]) {
+			case 'echo': {
+				let text = args.slice(1).filter((arg) => arg !== '-e' && arg !== '-n').join(' ').replace(/^(['"])(.*)\1$/, '$2');
+				if (args[1] === '-e') text = text.replace(/\\x([0-9a-f]{2})/gi, (match, hex) => String.fromCharCode(parseInt(hex, 16)));
+				return text + '\r\n';
+			}
+			case 'grep':
+				return input === null ? '' : input.split('\r\n').filter((out) => out.length !== 0 && out.includes(argument)).map((out) => out + '\r\n').join('');
+			case 'head':
+			case 'tail': {
+				if (input === null) return '';
+				let count = parseInt((command.match(/-n?\s*(\d+)/) || [])[1] || 10, 10);
+				let lines = input.split('\r\n').filter((out) => out.length !== 0);
+				lines = args[0] === 'head' ? lines.slice(0, count) : lines.slice(-count);
+				return lines.map((out) => out + '\r\n').join('');
+			}
+			case 'wc':
+				return input === null ? '' : (input.split('\r\n').length - 1) + '\r\n';
+			case 'sort':
+			case 'uniq':
+			case 'cut':
+			case 'awk':
+			case 'sed':
+			case 'tr':
+				return input || '';
+			case 'cat':
+				if (input !== null && argument.length === 0) return input;
+				if (telnetFiles[argument]) return telnetFiles[argument];
+				return 'cat: can\'t open \'' + argument + '\': No such file or directory\r\n';
+			case 'uname':
+				return ({'-a': unameAll, '-m': persona.arch, '-r': persona.kernel, '-n': persona.hostname}[args[1]] || 'Linux') + '\r\n';
+			case 'id': {
+				let uid = shellUser === 'root' ? 0 : 1000;
+				return 'uid=' + uid + '(' + shellUser + ') gid=' + uid + '(' + shellUser + ')\r\n';
+			}
+			case 'whoami':
+				return shellUser + '\r\n';
+			case 'pwd':
+				return '/\r\n';
+			case 'hostname':
+				return persona.hostname + '\r\n';
+			case 'nproc':
+				return '1\r\n';
+			case 'ls':
+				return 'bin   dev   etc   lib   mnt   proc  root  sbin  sys   tmp   usr   var\r\n';
+			case 'ps':
+				return '  PID USER       VSZ STAT COMMAND\r\n    1 root      1372 S    init\r\n  312 root      1196 S    /usr/sbin/telnetd\r\n  389 root      2040 S    /usr/bin/httpd\r\n  426 root      1372 S    -sh\r\n';
+			case 'free':
+				return '             total       used       free     shared    buffers\r\nMem:         60152      41220      18932          0       3384\r\n-/+ buffers:              37836      22316\r\nSwap:            0          0          0\r\n';
+		}
+		if (busybox && !busyboxApplets.includes(args[0])) return args[0] + ': applet not found\r\n';
+		if (!busyboxApplets.includes(args[0]) && !['enable', 'system', 'shell', 'linuxshell'].includes(args[0])) return '-sh: ' + args[0] + ': not found\r\n';
+
+		return '';
+	}
+
+	/**
+	 * The session as the client saw it, rebuilt from the saved credentials and commands (with the current `persona`)
+	 * The client echoes what it types itself, so the input is a part of the output
+	 * @param {string} username
+	 * @param {null|string} password - null when the bot went straight to the commands
+	 * @param {Array} commands
+	 * @param {boolean} kicked - Whether the session was disconnected after the last command (`end_reason` command_limit)
+	 * @returns {Array} - Output of every step
+	 */
+	static transcript(username, password, commands, kicked) {
+		let steps = ['\r\n' + persona.hostname + ' login: ', username + '\r\nPassword: '];
+		steps.push((password !== null ? '\r\n' : '') + '\r\n' + telnetBanner + telnetPrompt);
+		commands.forEach((line, i) => {
+			let last = (kicked && i === commands.length - 1) || /^\s*(exit|logout)\s*$/.test(line);
+			steps.push(line + '\r\n' + (last ? '' : TelnetSocketServer.execute(line) + telnetPrompt));
+		});
+
+		return steps;
+	}
+
+	log(socket, ip, port, session) {
+		let info = this.connectionInfo(socket, ip, port);
+		if (session.username !== undefined) {
+			info.username = session.username;
+			info.password = session.password;
+			info.request = 'telnet ' + (session.username.length !== 0 ? session.username + '@' : '') + config.server_ip + ':' + this.port;
+			if (session.password === undefined) info.request+= ' (no password)';
+		}
+		if (session.commands.length !== 0) {
+			info.request_headers = session.commands.join('\r\n') + '\r\n';
+			// Saving the analysts from decoding the `echo <base64> | base64 -d | sh` droppers by hand
+			deobfuscator.decodeBase64(session.commands).forEach((item) => {
+				info.request_headers+= '\r\nDecoded ' + item.blob.substr(0, 32) + (item.blob.length > 32 ? '...' : '') + ':\r\n' + item.decoded + '\r\n';
+			});
+		}
+		else if (session.buffer.trim().length !== 0) info.request_headers = session.buffer;
+		let botnet_family = botnetClassifier.classify(session.username, session.password, session.commands);
+		if (botnet_family !== null) {
+			info.botnet_family = botnet_family;
+			info.request+= ' (' + botnet_family + ' botnet)';
+		}
+		if (session.kicked) {
+			info.end_reason = 'command_limit';
+			info.request+= ' (disconnected after ' + session.commands.length + ' commands)';
+		}
+
+		this.emit('data', info);
+	}
+}
+
+const redisConfig = {
+	'dir': '/var/lib/redis',
+	'dbfilename': 'dump.rdb',
+	'requirepass': '',
+	'masterauth': '',
+	'maxmemory': '0',
+	'maxmemory-policy': 'noeviction',
+	'appendonly': 'no',
+	'bind': '0.0.0.0',
+	'port': '6379',
+	'protected-mode': 'no',
+	'databases': '16',
+	'save': '900 1 300 10 60 10000'
+};
+
+// Commands commonly used to turn an exposed Redis into a cron/ssh-key dropper or to load a malicious module
+const redisAbuseCommands = ['SET', 'SLAVEOF', 'REPLICAOF', 'MODULE', 'EVAL', 'CONFIG SET DIR', 'CONFIG SET DBFILENAME'];
+
+class RedisSocketServer extends SocketServer {
+	start() {
+		this.startedAt = Date.now();
+		this.createServer((socket, ip, port) => {
+			let session = {'buffer': Buffer.alloc(0), 'commands': [], 'abuse': [], 'keys': {}, 'length': 0};
+			socket.on('data', (data) => {
+				session.buffer = Buffer.concat([session.buffer, data]);
+				session.length+= data.length;
+				let parsed;
+				try {
+					parsed = resp.parse(session.buffer);
+				} catch (err) {
+					session.commands.push(session.buffer.toString());
+					this.send(socket, resp.error('ERR Protocol error: ' + err.message), true);
+					return;
+				}
+				session.buffer = parsed.rest;
+				parsed.commands.forEach((args) => {
+					let command = args.join(' ');
+					session.commands.push(command);
+					let name = (args[0] + ' ' + (args[1] || '') + ' ' + (args[2] || '')).toUpperCase();
+					if (redisAbuseCommands.some((abuse) => (name + ' ').startsWith(abuse + ' '))) session.abuse.push(command.substr(0, 256));
+					if (!socket.destroyed) this.send(socket, this.reply(args, session), args[0].toUpperCase() === 'QUIT');
+				});
+				if (session.length >= this.maxPayloadLength) socket.destroy();
+			});
+			socket.on('close', () => {
+				let info = this.connectionInfo(socket, ip, port);
+				if (session.abuse.length !== 0) info.request = 'Redis abuse attempt from ' + ip + ': ' + session.abuse.join('; ');
+				if (session.commands.length !== 0) info.request_headers = session.commands.join('\r\n');
+				this.emit('data', info);
+			});
+		});
+	}
+
+	/**
+	 * @param {Array} args - Command name followed by its arguments
+	 * @param session
+	 * @returns {string} - RESP encoded reply
+	 */
+	reply(args, session) {
+		let command = args[0].toUpperCase();
+		let sub_command = (args[1] || '').toUpperCase();
+		switch (command) {
+			case 'PING':
+				return args.length > 1 ? resp.bulkString(args[1]) : resp.simpleString('PONG');
+			case 'ECHO':
+				return resp.bulkString(args[1]);
+			case 'QUIT':
+			case 'SELECT':
+			case 'FLUSHALL':
+			case 'FLUSHDB':
+			case 'SAVE':
+			case 'SLAVEOF':
+			case 'REPLICAOF':
+				return resp.simpleString('OK');
+			case 'BGSAVE':
+				return resp.simpleString('Background saving started');
+			case 'AUTH':
+				return resp.error('ERR Client sent AUTH, but no password is set');
+			case 'INFO':
+				return resp.bulkString(this.info(args[1], session));
+			case 'DBSIZE':
+				return resp.integer(Object.keys(session.keys).length);
+			case 'KEYS':
+				return resp.array(Object.keys(session.keys));
+			case 'SET':
+				if (args.length < 3) break;
+				session.keys[args[1]] = args[2];
+				return resp.simpleString('OK');
+			case 'GET':
+				if (args.length < 2) break;
+				return resp.bulkString(session.keys[args[1]]);
+			case 'DEL': {
+				if (args.length < 2) break;
+				let deleted = args.slice(1).filter((key) => key in session.keys);
+				deleted.forEach((key) => {
+					delete session.keys[key];
+				});
+				return resp.integer(deleted.length);
+			}
+			case 'COMMAND':
+				return resp.array([]);
+			case 'CONFIG':
+				if (sub_command === 'GET' && args.length === 3) {
+					let pattern = new RegExp('^' + args[2].replace(/[.+^${}()|[\]\\]/g, '\\$&').replace(/\*/g, '.*').replace(/\?/g, '.') + '$', 'i');
+					let items = [];
+					for (let key in redisConfig) {
+						if (pattern.test(key)) items.push(key, redisConfig[key]);
+					}
+					return resp.array(items);
+				}
+				if (sub_command === 'SET' && args.length === 4) return resp.simpleString('OK');
+				if (sub_command === 'RESETSTAT' || sub_command === 'REWRITE') return resp.simpleString('OK');
+				return resp.error("ERR Unknown subcommand or wrong number of arguments for '" + (args[1] || '') + "'. Try CONFIG HELP.");
+			case 'MODULE':
+				return resp.error('ERR Error loading the extension. Please check the server logs.');
+			default:
+				return resp.error("ERR unknown command '" + args[0] + "'");
+		}
+
+		return resp.error("ERR wrong number of arguments for '" + args[0].toLowerCase() + "' command");
+	}
+
+	info(section, session) {
+		let uptime = Math.floor((Date.now() - this.startedAt) / 1000) + 86400 * 37;
+		let sections = {
+			'server': [
+				'redis_version:4.0.9',
+				'redis_git_sha1:00000000',
+				'redis_git_dirty:0',
+				'redis_build_id:9435c3c2879311f3',
+				'redis_mode:standalone',
+				'os:Linux ' + config.persona.kernel + ' ' + config.persona.arch,
+				'arch_bits:' + (persona.arch.includes('64') ? 64 : 32),
+				'multiplexing_api:epoll',
+				'atomicvar_api:atomic-builtin',
+				'gcc_version:7.3.0',
+				'process_id:1094',
+				'run_id:2c1ac4ae4bd6a0c3e1f7d0e0fe0b61c2b9e0a2f1',
+				'tcp_port:6379',
+				'uptime_in_seconds:' + uptime,
+				'uptime_in_days:' + Math.floor(uptime / 86400),
+				'hz:10',
+				'lru_clock:' + (Math.floor(Date.now() / 1000) % 16777216),
+				'executable:/usr/bin/redis-server',
+				'config_file:/etc/redis/redis.conf'
+			],
+			'clients': [
+				'connected_clients:1',
+				'client_longest_output_list:0',
+				'client_biggest_input_buf:0',
+				'blocked_clients:0'
+			],
+			'memory': [
+				'used_memory:841848',
+				'used_memory_human:822.12K',
+				'used_memory_rss:4050944',
+				'used_memory_rss_human:3.86M',
+				'used_memory_peak:906112',
+				'used_memory_peak_human:884.88K',
+				'maxmemory:0',
+				'maxmemory_human:0B',
+				'maxmemory_policy:noeviction',
+				'mem_fragmentation_ratio:4.81',
+				'mem_allocator:jemalloc-3.6.0'
+			],
+			'persistence': [
+				'loading:0',
+				'rdb_changes_since_last_save:' + Object.keys(session.keys).length,
+				'rdb_bgsave_in_progress:0',
+				'rdb_last_save_time:' + (Math.floor(Date.now() / 1000) - 1791),
+				'rdb_last_bgsave_status:ok',
+				'aof_enabled:0',
+				'aof_rewrite_in_progress:0'
+			],
+			'stats': [
+				'total_connections_received:' + (1308 + Math.floor(uptime / 1200)),
+				'total_commands_processed:' + (25731 + Math.floor(uptime / 90)),
+				'instantaneous_ops_per_sec:0',
+				'rejected_connections:0',
+				'expired_keys:0',
+				'evicted_keys:0',
+				'keyspace_hits:0',
+				'keyspace_misses:0'
+			],
+			'replication': [
+				'role:master',
+				'connected_slaves:0',
+				'master_repl_offset:0',
+				'repl_backlog_active:0'
+			],
+			'cpu': [
+				'used_cpu_sys:' + (uptime / 2400).toFixed(2),
+				'used_cpu_user:' + (uptime / 3900).toFixed(2),
+				'used_cpu_sys_children:0.00',
+				'used_cpu_user_children:0.00'
+			],
+			'keyspace': Object.keys(session.keys).length !== 0 ? ['db0:keys=' + Object.keys(session.keys).length + ',expires=0,avg_ttl=0'] : []
 		};
-		if (data && data.toString().trim().length !== 0) info.request_headers = data.toString();
+
+		section = (section || 'default').toLowerCase();
+		let info = '';
+		for (let name in sections) {
+			if (section !== 'default' && section !== 'all' && section !== 'everything' && section !== name) continue;
+			if (info.length !== 0) info+= '\r\n';
+			info+= '# ' + name.charAt(0).toUpperCase() + name.substr(1) + '\r\n' + sections[name].map((line) => line + '\r\n').join('');
+		}
+
+		return info;
+	}
+}
+
+class MysqlSocketServer extends SocketServer {
+	start() {
+		this.connectionId = 1000 + Math.floor(Math.random() * 50000);
+		this.createServer((socket, ip, port) => {
+			let buffer = Buffer.alloc(0);
+			let response = null;
+			let scramble = mysqlProtocol.scramble();
+			socket.write(mysqlProtocol.handshake(this.connectionId++, config.emulation.mysql_version, scramble));
+			socket.on('data', (data) => {
+				buffer = Buffer.concat([buffer, data]);
+				if (buffer.length > this.maxPayloadLength) return socket.destroy();
+				let packet = mysqlProtocol.readPacket(buffer);
+				if (packet === null || response !== null) return;
+				response = mysqlProtocol.parseHandshakeResponse(packet.payload);
+				if (response.ssl) return socket.destroy();
+				socket.end(mysqlProtocol.error(1045, '28000', "Access denied for user '" + response.username + "'@'" + ip + "' (using password: " + (response.auth_response.length !== 0 ? 'YES' : 'NO') + ")", packet.sequence_id + 1));
+			});
+			socket.on('close', () => {
+				let info = this.connectionInfo(socket, ip, port);
+				if (response !== null && !response.ssl) {
+					info.username = response.username;
+					info.request = 'mysql://' + (response.username.length !== 0 ? response.username + '@' : '') + config.server_ip + ':' + this.port + (response.database ? '/' + response.database : '');
+					// The scramble together with the auth response allows to brute-force the password offline
+					info.request_headers = helper.formatHeaders({
+						'auth_plugin': response.auth_plugin || 'mysql_native_password',
+						'scramble': scramble.toString('hex'),
+						'auth_response': response.auth_response,
+						'connect_attrs': response.attributes
+					});
+				}
+				else if (response !== null) {
+					info.request_headers = 'SSL connection requested\r\n';
+				}
+				this.emit('data', info);
+			});
+		});
+	}
+}
+
+class RdpSocketServer extends SocketServer {
+	start() {
+		this.createServer((socket, ip, port) => {
+			let buffer = Buffer.alloc(0);
+			let request = null;
+			socket.on('data', (data) => {
+				buffer = Buffer.concat([buffer, data.slice(0, this.maxPayloadLength - buffer.length)]);
+				if (buffer.length >= this.maxPayloadLength) return socket.destroy();
+				if (request !== null) return;
+				request = rdpProtocol.parseConnectionRequest(buffer);
+				if (request === false) return socket.destroy();
+				// Confirming the connection, so that the scanners go on with the TLS handshake instead of disconnecting
+				if (request !== null) socket.write(rdpProtocol.connectionConfirm(rdpProtocol.selectProtocol(request.requested_protocols)));
+			});
+			socket.on('close', () => {
+				let info = this.connectionInfo(socket, ip, port);
+				if (request) {
+					let requested = request.requested_protocols !== null ? rdpProtocol.protocolNames(request.requested_protocols) : ['RDP'];
+					info.request = 'RDP connection from ' + ip + ':' + port + ' (' + requested.join(', ') + ')';
+					if (request.cookie !== null) info.username = request.cookie;
+				}
+				if (buffer.length !== 0) info.request_headers = 'hex: ' + buffer.toString('hex');
+				this.emit('data', info);
+			});
+		});
+	}
+}
+
+/**
+ * Open proxy probes: the handshake is played along up to the request, which tells where the client wanted to go
+ * (spam relays, the sites of credential stuffing, etc.), and the connection is then refused
+ */
+class SocksSocketServer extends SocketServer {
+	start() {
+		this.createServer((socket, ip, port) => {
+			let buffer = Buffer.alloc(0);
+			let pending = Buffer.alloc(0);
+			let session = {'version': null, 'stage': 'greeting', 'username': undefined, 'password': undefined, 'request': null};
+			socket.on('data', (data) => {
+				buffer = Buffer.concat([buffer, data.slice(0, this.maxPayloadLength - buffer.length)]);
+				if (buffer.length >= this.maxPayloadLength) return socket.destroy();
+				if (session.stage === 'done') return;
+				pending = Buffer.concat([pending, data]);
+				let consumed = this.handshake(socket, session, pending);
+				if (consumed === false) return socket.destroy();
+				pending = pending.slice(consumed);
+			});
+			socket.on('close', () => {
+				let info = this.connectionInfo(socket, ip, port);
+				if (session.version !== null) info.request = 'SOCKS' + session.version + ' connection from ' + ip + ':' + port;
+				if (session.request) {
+					info.request = 'SOCKS' + session.version + ' ' + session.request.command + ' to ' + session.request.host + ':' + session.request.port + ' from ' + ip + ':' + port;
+					info.http_request_path = session.request.host + ':' + session.request.port;
+				}
+				if (session.username !== undefined) {
+					info.username = session.username;
+					if (session.password !== undefined) info.password = session.password;
+				}
+				// Anything but SOCKS (HTTP proxy probes, etc.) is kept as text when it is
+				if (buffer.length !== 0) info.request_headers = session.version === null && protocolDetector.isPrintable(buffer) ? buffer.toString('utf8') : 'hex: ' + buffer.toString('hex');
+				this.emit('data', info);
+			});
+		});
+	}
+
+	/**
+	 * Goes through as many stages as the data received so far allows
+	 * @param socket
+	 * @param session - The stage reached, the credentials and the request
+	 * @param {Buffer} data - Data not consumed by the previous stages
+	 * @returns {boolean|number} - Bytes consumed, false if it is not SOCKS
+	 */
+	handshake(socket, session, data) {
+		let offset = 0;
+		while (session.stage !== 'done' && offset < data.length) {
+			let rest = data.slice(offset);
+			if (session.version === null) {
+				if (rest[0] !== 0x04 && rest[0] !== 0x05) return false;
+				session.version = rest[0];
+			}
+			let parsed = session.version === 4 ? socksProtocol.parseSocks4(rest)
+				: session.stage === 'greeting' ? socksProtocol.parseGreeting(rest)
+				: session.stage === 'auth' ? socksProtocol.parseAuth(rest) : socksProtocol.parseRequest(rest);
+			if (parsed === false) return false;
+			if (parsed === null) break;
+
+			if (session.version === 4) {
+				session.request = parsed;
+				if (parsed.userid.length !== 0) session.username = parsed.userid;
+				session.stage = 'done';
+				this.send(socket, socksProtocol.socks4Refused, true);
+			}
+			else if (session.stage === 'greeting') {
+				let method = socksProtocol.selectMethod(parsed.methods);
+				session.stage = method === socksProtocol.METHOD_USERNAME_PASSWORD ? 'auth' : 'request';
+				if (method === socksProtocol.METHOD_NONE_ACCEPTABLE) session.stage = 'done';
+				this.send(socket, Buffer.from([0x05, method]), session.stage === 'done');
+				offset+= parsed.length;
+			}
+			else if (session.stage === 'auth') {
+				session.username = parsed.username;
+				session.password = parsed.password;
+				session.stage = 'request';
+				this.send(socket, socksProtocol.authSucceeded);
+				offset+= parsed.length;
+			}
+			else {
+				session.request = parsed;
+				session.stage = 'done';
+				this.send(socket, socksProtocol.socks5Refused, true);
+			}
+		}
+
+		return offset;
+	}
+}
+
+// Alternate HTTP ports, answered the same way as a web server would
+const httpServices = ['http', 'http-proxy', 'http-alt', 'sun-answerbook', 'blackice-icecap', 'squid-http', 'ccproxy-http', 'vnc-http', 'zeus-admin', 'snet-sensor-mgmt'];
+
+const httpResponseBody = '<!DOCTYPE html>\n<html>\n<head><title>Apache2 Ubuntu Default Page: It works</title></head>\n<body><h1>It works!</h1></body>\n</html>\n';
+
+class HttpSocketServer extends SocketServer {
+	start() {
+		this.createServer((socket, ip, port) => {
+			let buffer = Buffer.alloc(0);
+			let request = null;
+			socket.on('data', (data) => {
+				buffer = Buffer.concat([buffer, data]).slice(0, this.maxPayloadLength);
+				if (request !== null && request.valid) return;
+				request = httpRequest.parse(buffer, this.maxPayloadLength);
+				if (request === null || !request.valid) return;
+
+				let response = [
+					'HTTP/1.1 200 OK',
+					'Date: ' + new Date().toUTCString(),
+					'Server: ' + config.emulation.http_server_header,
+					'Content-Length: ' + Buffer.byteLength(httpResponseBody),
+					'Content-Type: text/html; charset=UTF-8',
+					'Connection: close'
+				].join('\r\n') + '\r\n\r\n';
+				socket.end(request.method === 'HEAD' ? response : response + httpResponseBody);
+			});
+			socket.on('close', () => {
+				let info = this.connectionInfo(socket, ip, port);
+				if (request !== null && request.valid) {
+					info.request = request.method + ' ' + (request.headers['host'] ? 'http://' + request.headers['host'] + request.path : request.path);
+					info.http_request_path = request.path;
+					info.user_agent = request.headers['user-agent'];
+					info.request_headers = helper.formatHeaders(request.headers);
+					if (request.body.length !== 0) info.request_headers+= '\r\n' + request.body;
+					let exploit = exploitClassifier.classify(request.path, info.request_headers);
+					if (exploit !== null) info.exploit = exploit;
+				}
+				else if (buffer.toString().trim().length !== 0) {
+					info.request_headers = buffer.toString();
+				}
+				this.emit('data', info);
+			});
+		});
+	}
+}
+
+const httpStatuses = {200: 'OK', 201: 'Created', 204: 'No Content', 404: 'Not Found'};
+
+/**
+ * Docker API left open without TLS, the requests are answered one after the other on the same connection
+ * as the docker CLI and the SDKs keep it alive. The container-create attempts are the valuable part:
+ * the image and the command reveal the miner or the malware the attacker meant to run
+ */
+class DockerSocketServer extends SocketServer {
+	start() {
+		this.createServer((socket, ip, port) => {
+			let buffer = Buffer.alloc(0);
+			let received = 0;
+			let requests = [];
+			let session = dockerApi.session();
+			socket.on('data', (data) => {
+				received+= data.length;
+				buffer = Buffer.concat([buffer, data]).slice(0, this.maxPayloadLength);
+				let request;
+				while (!socket.destroyed && buffer.length !== 0 && (request = httpRequest.parse(buffer, this.maxPayloadLength)) !== null) {
+					if (!request.valid) {
+						requests.push(buffer.toString());
+						return socket.destroy();
+					}
+					buffer = buffer.slice(request.length);
+					requests.push(request);
+					this.respond(socket, request, dockerApi.respond(request, session));
+				}
+				if (received >= this.maxPayloadLength) socket.destroy();
+			});
+			socket.on('close', () => {
+				let info = this.connectionInfo(socket, ip, port);
+				let valid = requests.filter((request) => typeof request !== 'string');
+				if (valid.length !== 0) {
+					info.request = valid.map((request) => request.method + ' ' + request.path).join('\n').substr(0, 4096);
+					let main = valid.find((request) => /\/containers\/create\b/.test(request.path)) || valid[0];
+					info.http_request_path = main.path;
+					info.user_agent = main.headers['user-agent'];
+				}
+				if (requests.length !== 0) {
+					info.request_headers = requests.map((request) => {
+						if (typeof request === 'string') return request;
+						return request.method + ' ' + request.path + '\r\n' + helper.formatHeaders(request.headers) + (request.body.length !== 0 ? '\r\n' + request.body : '');
+					}).join('\r\n');
+				}
+				if (session.containers.length !== 0) {
+					info.request = 'Docker container create from ' + ip + ': ' + session.containers.map((container) => container.image + ' ' + container.command).join('; ').substr(0, 4000);
+					info.exploit = 'Docker API container create';
+				}
+				if (session.images.length !== 0) info.docker_image = session.images.join(', ');
+				if (session.commands.length !== 0) info.docker_command = session.commands.join('; ');
+				this.emit('data', info);
+			});
+		});
+	}
+
+	/**
+	 * @param socket
+	 * @param request - Parsed with `http-request`
+	 * @param response - `status` and `body` from `docker-api`
+	 */
+	respond(socket, request, response) {
+		let json = typeof response.body !== 'string';
+		let body = json ? JSON.stringify(response.body) + '\n' : response.body;
+		let close = (request.headers['connection'] || '').toLowerCase() === 'close' || request.version === '1.0';
+		let headers = [
+			'HTTP/1.1 ' + response.status + ' ' + httpStatuses[response.status],
+			'Api-Version: ' + dockerApi.API_VERSION,
+			'Content-Type: ' + (json ? 'application/json' : 'text/plain; charset=utf-8'),
+			'Docker-Experimental: false',
+			'Ostype: linux',
+			'Server: Docker/' + config.emulation.docker_version + ' (linux)',
+			'Date: ' + new Date().toUTCString()
+		];
+		if (response.status !== 204) headers.push('Content-Length: ' + Buffer.byteLength(body));
+		if (close) headers.push('Connection: close');
+		this.send(socket, headers.join('\r\n') + '\r\n\r\n' + (request.method === 'HEAD' || response.status === 204 ? '' : body), close);
+	}
+}
+
+const smtpServices = ['smtp', 'submission', 'smtps'];
+
+// The capabilities of a stock Postfix, without STARTTLS as we cannot speak TLS
+const smtpExtensions = ['PIPELINING', 'SIZE 10240000', 'VRFY', 'ETRN', 'AUTH PLAIN LOGIN', 'ENHANCEDSTATUSCODES', '8BITMIME', 'DSN'];
+
+class SmtpSocketServer extends SocketServer {
+	start() {
+		this.createServer((socket, ip, port) => {
+			let session = {'buffer': '', 'state': 'command', 'transcript': [], 'helo': null, 'mail_from': [], 'rcpt_to': [], 'message': '', 'messages': 0, 'auth': null};
+			socket.setEncoding('utf8');
+			this.send(socket, '220 ' + config.hostname + ' ' + config.emulation.smtp_banner + '\r\n');
+			socket.on('data', (data) => {
+				session.buffer+= data;
+				let lines = session.buffer.split('\n');
+				session.buffer = lines.pop();
+				if (session.buffer.length > this.maxPayloadLength) return socket.destroy();
+				lines.forEach((line) => {
+					if (socket.destroyed) return;
+					let reply = this.reply(line.replace(/\r$/, ''), session);
+					this.send(socket, reply ? reply + '\r\n' : '', session.state === 'quit');
+				});
+			});
+			socket.on('close', () => {
+				this.log(socket, ip, port, session);
+			});
+		});
+	}
+
+	/**
+	 * @param {string} line - Received line without the line ending
+	 * @param session
+	 * @returns {string|undefined} - Reply to send back
+	 */
+	reply(line, session) {
+		if (session.state === 'data') {
+			if (line === '.') {
+				session.state = 'command';
+				session.messages++;
+				return '250 2.0.0 Ok: queued as ' + Math.random().toString(16).substr(2, 10).toUpperCase();
+			}
+			// Dot-stuffing, the client doubles the leading dot of every line
+			if (line.startsWith('.')) line = line.substr(1);
+			session.message+= (line + '\r\n').substr(0, this.maxPayloadLength - session.message.length);
+			return;
+		}
+		if (session.state.startsWith('auth_')) {
+			let value = Buffer.from(line, 'base64').toString();
+			session.transcript.push(line);
+			if (session.state === 'auth_plain') {
+				let parts = value.split('\0');
+				session.auth = {'username': parts[1] || '', 'password': parts[2] || ''};
+				session.state = 'command';
+				return '535 5.7.8 Error: authentication failed: authentication failure';
+			}
+			if (session.state === 'auth_username') {
+				session.auth = {'username': value, 'password': ''};
+				session.state = 'auth_password';
+				return '334 ' + Buffer.from('Password:').toString('base64');
+			}
+			session.auth.password = value;
+			session.state = 'command';
+			return '535 5.7.8 Error: authentication failed: authentication failure';
+		}
+
+		if (session.transcript.length < 256) session.transcript.push(line);
+		let [command, ...args] = line.split(' ');
+		let argument = args.join(' ');
+		switch (command.toUpperCase()) {
+			case 'HELO':
+				session.helo = argument;
+				return '250 ' + config.hostname;
+			case 'EHLO':
+				session.helo = argument;
+				return ['250-' + config.hostname].concat(smtpExtensions.map((extension, i) => (i === smtpExtensions.length - 1 ? '250 ' : '250-') + extension)).join('\r\n');
+			case 'AUTH': {
+				let mechanism = (args[0] || '').toUpperCase();
+				if (mechanism === 'PLAIN' && args[1]) {
+					// authzid \0 authcid \0 password
+					let parts = Buffer.from(args[1], 'base64').toString().split('\0');
+					session.auth = {'username': parts[1] || '', 'password': parts[2] || ''};
+					return '535 5.7.8 Error: authentication failed: authentication failure';
+				}
+				if (mechanism === 'PLAIN') {
+					session.state = 'auth_plain';
+					return '334 ';
+				}
+				if (mechanism === 'LOGIN') {
+					if (args[1]) {
+						session.auth = {'username': Buffer.from(args[1], 'base64').toString(), 'password': ''};
+						session.state = 'auth_password';
+						return '334 ' + Buffer.from('Password:').toString('base64');
+					}
+					session.state = 'auth_username';
+					return '334 ' + Buffer.from('Username:').toString('base64');
+				}
+				return '535 5.7.8 Error: authentication failed: Invalid authentication mechanism';
+			}
+			case 'MAIL':
+				if (session.mail_from.length < 100) session.mail_from.push(this.address(argument));
+				return '250 2.1.0 Ok';
+			case 'RCPT':
+				if (session.mail_from.length === 0) return '503 5.5.1 Error: need MAIL command';
+				if (session.rcpt_to.length < 100) session.rcpt_to.push(this.address(argument));
+				return '250 2.1.5 Ok';
+			case 'DATA':
+				if (session.rcpt_to.length === 0) return '554 5.5.1 Error: no valid recipients';
+				session.state = 'data';
+				return '354 End data with <CR><LF>.<CR><LF>';
+			case 'RSET':
+			case 'NOOP':
+				return '250 2.0.0 Ok';
+			case 'VRFY':
+				return '252 2.0.0 ' + argument;
+			case 'QUIT':
+				session.state = 'quit';
+				return '221 2.0.0 Bye';
+			default:
+				if (command.length === 0) return '500 5.5.2 Error: bad syntax';
+				return '502 5.5.2 Error: command not recognized';
+		}
+	}
+
+	/**
+	 * @param {string} argument - e.g. `FROM:<user@example.com> SIZE=1024`
+	 * @returns {string} - e.g. `user@example.com`
+	 */
+	address(argument) {
+		let match = argument.match(/<([^>]*)>/);
+		return match ? match[1] : argument.replace(/^(FROM|TO):\s*/i, '').split(' ')[0];
+	}
+
+	log(socket, ip, port, session) {
+		let info = this.connectionInfo(socket, ip, port);
+		// Relay probes send mail to a domain which is not ours, hoping that we deliver it further
+		let open_relay = session.rcpt_to.some((address) => !address.toLowerCase().endsWith('@' + config.hostname.toLowerCase()));
+		if (session.auth !== null) {
+			info.username = session.auth.username;
+			info.password = session.auth.password;
+		}
+		if (session.rcpt_to.length !== 0) {
+			info.request = (open_relay ? 'SMTP open relay test from ' : 'SMTP mail from ') + ip + ': <' + session.mail_from.join('>, <') + '> to <' + session.rcpt_to.join('>, <') + '>';
+		}
+		else if (session.auth !== null) {
+			info.request = 'smtp://' + session.auth.username + '@' + config.server_ip + ':' + this.port;
+		}
+		if (session.transcript.length !== 0) {
+			info.request_headers = helper.formatHeaders({
+				'helo': session.helo,
+				'mail_from': session.mail_from.join(', '),
+				'rcpt_to': session.rcpt_to.join(', '),
+				'messages': session.messages,
+				'open_relay': open_relay
+			}) + '\r\n' + session.transcript.join('\r\n') + '\r\n';
+			if (session.message.length !== 0) info.request_headers+= '\r\n' + session.message;
+		}
 
 		this.emit('data', info);
 	}
 }
 
 /**
- * @param {number} port - Socket's Port Number
  * @param {string} name - Service Name
+ * @returns - Class of the server emulating the service
  */
-const CustomSocketServer = (port, name) => {
+const handlerClass = (name) => {
 	if (name === 'ssh') {
-		return new SshSocketServer(port, name);
+		return SshSocketServer;
 	}
 	else if (name === 'ftp') {
-		return new FtpSocketServer(port, name);
+		return FtpSocketServer;
+	}
+	else if (name === 'telnet') {
+		return TelnetSocketServer;
+	}
+	else if (name === 'redis') {
+		return RedisSocketServer;
+	}
+	else if (name === 'MySQL') {
+		return MysqlSocketServer;
+	}
+	else if (name === 'RDP') {
+		return RdpSocketServer;
+	}
+	else if (name === 'socks') {
+		return SocksSocketServer;
+	}
+	else if (name === 'docker') {
+		return DockerSocketServer;
+	}
+	else if (smtpServices.includes(name)) {
+		return SmtpSocketServer;
+	}
+	else if (httpServices.includes(name)) {
+		return HttpSocketServer;
 	}
 	else {
-		return new GenericSocketServer(port, name);
+		return GenericSocketServer;
 	}
 };
 
+/**
+ * @param {number} port - Socket's Port Number
+ * @param {string} name - Service Name
+ */
+const CustomSocketServer = (port, name) => {
+	let Server = handlerClass(name);
+	return new Server(port, name);
+};
+
+CustomSocketServer.connectionLimiter = connectionLimiter;
+CustomSocketServer.portScanDetector = portScanDetector;
+CustomSocketServer.instances = instances;
+/* Short name of the server emulating the service, e.g. `telnet`, `http` or `generic` */
+CustomSocketServer.handler = (name) => handlerClass(name).name.replace(/SocketServer$/, '').toLowerCase();
+CustomSocketServer.telnetTranscript = TelnetSocketServer.transcript;
+
 module.exports = CustomSocketServer;
\ No newline at end of file
@@ -0,0 +1,52 @@
+"use strict";
+
+const EventEmitter = require('events');
+
+/**
+ * Collapses the repeated requests (same IP, method and path) within the window into a single item with a `count`
+ * The first request of the window is held back and emitted as `data` once the window is over
+ */
+class Deduplicator extends EventEmitter {
+	/**
+	 * @param {number} window - Seconds, 0 disables the deduplication
+	 */
+	constructor(window) {
+		super();
+		this.window = window * 1000;
+		this.pending = new Map();
+
+		if (this.window) setInterval(() => { this.flush(); }, 1000);
+	}
+
+	/**
+	 * @param item
+	 * @param {string} method
+	 */
+	add(item, method) {
+		if (!this.window) return this.emit('data', item);
+
+		let key = item.ip + ' ' + method + ' ' + item.http_request_path;
+		let pending = this.pending.get(key);
+		if (!pending) {
+			this.pending.set(key, {'item': Object.assign({'count': 1}, item), 'started': Date.now()});
+			return;
+		}
+		pending.item.count++;
+		if (item.request_size !== undefined) pending.item.request_size = (pending.item.request_size || 0) + item.request_size;
+	}
+
+	/**
+	 * @param {boolean} all - Emit everything pending, e.g. on shutdown
+	 */
+	flush(all) {
+		let now = Date.now();
+		this.pending.forEach((pending, key) => {
+			if (!all && now - pending.started < this.window) return;
+			this.pending.delete(key);
+			if (pending.item.count > 1) pending.item.request+= ' (' + pending.item.count + ' times)';
+			this.emit('data', pending.item);
+		});
+	}
+}
+
+module.exports = Deduplicator;
@@ -0,0 +1,184 @@
+"use strict";
+
+const logger = require('./logger');
+const helper = require('./helper');
+
+// How often the due deliveries are looked for, and how many are retried at a time
+const CHECK_INTERVAL = 10 * 1000;
+const BATCH_SIZE = 100;
+
+/**
+ * Deliveries of the outbound integrations (syslog, AbuseIPDB) that failed, kept in the `delivery` table and retried
+ * with an exponential backoff from `retry_interval` up to `max_retry_interval` seconds, so that the alerts survive
+ * the collector being down for a while, and a restart of the honeypot meanwhile
+ * At most `max_entries` are kept, the oldest are dropped first. A delivery is given up after `max_attempts`
+ * While the database is unavailable, the deliveries to queue are kept in memory (up to `max_entries` too) until it is back
+ */
+class DeliveryQueue {
+	/**
+	 * @param options - `delivery_queue` section of the config
+	 */
+	constructor(options) {
+		this.options = options;
+		this.handlers = {};
+		this.processing = false;
+		// Deliveries which couldn't be queued in the database, and the number dropped since the last warning
+		this.unsaved = [];
+		this.lost = 0;
+		this.unavailable = false;
+
+		setInterval(() => {
+			this.flush(() => { this.trim(); });
+			this.process();
+		}, CHECK_INTERVAL);
+	}
+
+	/**
+	 * @param {string} integration - Name stored with its deliveries, e.g. `syslog`
+	 * @param {function} deliver - Called with the payload and a callback taking the error, if the delivery failed
+	 */
+	register(integration, deliver) {
+		this.handlers[integration] = deliver;
+	}
+
+	/**
+	 * Queues a delivery whose first attempt failed
+	 * @param {string} integration
+	 * @param payload - Anything JSON can hold
+	 * @param error - Why the first attempt failed
+	 */
+	add(integration, payload, error) {
+		let delivery = [integration, JSON.stringify(payload), 1, this.nextAttempt(1), DeliveryQueue.reason(error)];
+		helper.mysqlPool.query('INSERT INTO delivery (integration, payload, attempts, next_attempt, last_error) VALUES (?)', [delivery], (error) => {
+			if (error) return this.keep([delivery], error);
+			this.unavailable = false;
+		});
+	}
+
+	/**
+	 * Keeps the deliveries in memory until they can be queued in the database, warns once until it is back
+	 * @param {Array} deliveries - Rows of the `delivery` table
+	 * @param error - Why they couldn't be inserted
+	 */
+	keep(deliveries, error) {
+		if (!this.unavailable) logger.warning('Cannot queue the deliveries in the database, they are kept in memory until it is back. Error Code: ' + error.code);
+		this.unavailable = true;
+		this.unsaved = this.unsaved.concat(deliveries);
+		if (this.unsaved.length > this.options.max_entries) {
+			this.lost+= this.unsaved.length - this.options.max_entries;
+			this.unsaved = this.unsaved.slice(-this.options.max_entries);
+		}
+	}
+
+	/**
+	 * Queues the deliveries kept in memory in the database, and tells how many have been dropped meanwhile
+	 * @param {function} callback - Called once they are queued, not if the database is still unavailable
+	 */
+	flush(callback) {
+		if (this.lost !== 0) {
+			logger.warning('The database is unavailable and the ' + this.options.max_entries + ' deliveries kept in memory are the most, dropped the ' + this.lost + ' oldest.');
+			this.lost = 0;
+		}
+		if (this.unsaved.length === 0) return callback();
+
+		let deliveries = this.unsaved;
+		this.unsaved = [];
+		helper.mysqlPool.query('INSERT INTO delivery (integration, payload, attempts, next_attempt, last_error) VALUES ?', [deliveries], (error) => {
+			if (error) {
+				// Put back ahead of those kept meanwhile
+				let kept = deliveries.concat(this.unsaved);
+				this.unsaved = [];
+				return this.keep(kept, error);
+			}
+			this.unavailable = false;
+			logger.info('Queued the ' + deliveries.length + ' deliveries kept in memory', ' in the database.');
+			callback();
+		});
+	}
+
+	/**
+	 * Retries the queued deliveries of the integration right away, e.g. once the connection to the collector is back
+	 * @param {string} integration
+	 */
+	retryNow(integration) {
+		helper.mysqlPool.query('UPDATE delivery SET next_attempt = ? WHERE integration = ?', [new Date(), integration], (error) => {
+			if (!error) this.process();
+		});
+	}
+
+	/* Drops the oldest deliveries beyond `max_entries` */
+	trim() {
+		let sql = 'DELETE FROM delivery WHERE id <= (SELECT id FROM (SELECT id FROM delivery ORDER BY id DESC LIMIT 1 OFFSET ?) AS oldest_kept)';
+		helper.mysqlPool.query(sql, [this.options.max_entries], (error, results) => {
+			if (!error && results.affectedRows !== 0) logger.warning('The delivery queue is full (' + this.options.max_entries + '), dropped the ' + results.affectedRows + ' oldest deliveries.');
+		});
+	}
+
+	/* Retries the due deliveries of the registered integrations, one after the other */
+	process() {
+		let integrations = Object.keys(this.handlers);
+		if (this.processing || integrations.length === 0) return;
+
+		this.processing = true;
+		let sql = 'SELECT id, integration, payload, attempts FROM delivery WHERE integration IN (?) AND next_attempt <= ? ORDER BY id LIMIT ?';
+		helper.mysqlPool.query(sql, [integrations, new Date(), BATCH_SIZE], (error, rows) => {
+			if (error) {
+				this.processing = false;
+				return;
+			}
+			let next = (index) => {
+				if (index === rows.length) {
+					this.processing = false;
+					return;
+				}
+				let row = rows[index];
+				let payload;
+				try {
+					payload = JSON.parse(row['payload']);
+				} catch (err) {
+					helper.mysqlPool.query('DELETE FROM delivery WHERE id = ?', [row['id']]);
+					return next(index + 1);
+				}
+				this.handlers[row['integration']](payload, (err) => {
+					this.settle(row, err);
+					next(index + 1);
+				});
+			};
+			next(0);
+		});
+	}
+
+	/**
+	 * @param row - Delivery retried
+	 * @param err - Error of the attempt, if it failed
+	 */
+	settle(row, err) {
+		let attempts = row['attempts'] + 1;
+		if (!err) return helper.mysqlPool.query('DELETE FROM delivery WHERE id = ?', [row['id']]);
+		if (attempts >= this.options.max_attempts) {
+			logger.warning('Gave up the ' + row['integration'] + ' delivery after ' + attempts + ' attempts: ' + DeliveryQueue.reason(err));
+			return helper.mysqlPool.query('DELETE FROM delivery WHERE id = ?', [row['id']]);
+		}
+		helper.mysqlPool.query('UPDATE delivery SET attempts = ?, next_attempt = ?, last_error = ? WHERE id = ?', [attempts, this.nextAttempt(attempts), DeliveryQueue.reason(err), row['id']]);
+	}
+
+	/**
+	 * @param {number} attempts - Made so far
+	 * @returns {Date}
+	 */
+	nextAttempt(attempts) {
+		let delay = Math.min(this.options.retry_interval * Math.pow(2, attempts - 1), this.options.max_retry_interval);
+
+		return new Date(Date.now() + delay * 1000);
+	}
+
+	/**
+	 * @param error
+	 * @returns {string}
+	 */
+	static reason(error) {
+		return String(error.code || error.message || error).substr(0, 255);
+	}
+}
+
+module.exports = DeliveryQueue;
@@ -0,0 +1,34 @@
+"use strict";
+
+const protocolDetector = require('./protocol-detector');
+
+// Decoding no more than that per blob, the rest is cut off
+const MAX_DECODED_BYTES = 16 * 1024;
+// Shorter strings are more likely to be a word or a path than an encoded script
+const MIN_BLOB_LENGTH = 24;
+
+/**
+ * Decodes the base64 blobs of the commands, e.g. `echo <base64> | base64 -d | sh`
+ * @param {Array} commands
+ * @returns {Array} - `blob` and its `decoded` text, binary results are given as hex
+ */
+const decodeBase64 = (commands) => {
+	let decoded = [];
+	commands.forEach((command) => {
+		(command.match(/[A-Za-z0-9+\/]+={0,2}/g) || []).forEach((blob) => {
+			if (blob.length < MIN_BLOB_LENGTH || blob.length % 4 !== 0 || /^[a-z\/]+$/i.test(blob)) return;
+			let buffer = Buffer.from(blob, 'base64').slice(0, MAX_DECODED_BYTES);
+			if (buffer.length === 0) return;
+			decoded.push({
+				'blob': blob,
+				'decoded': protocolDetector.isPrintable(buffer) ? buffer.toString('utf8') : 'hex: ' + buffer.toString('hex')
+			});
+		});
+	});
+
+	return decoded;
+};
+
+module.exports = {
+	decodeBase64: decodeBase64
+};
@@ -0,0 +1,167 @@
+"use strict";
+
+// Just enough of the Docker Engine API (unauthenticated, port 2375) for the cryptominer droppers to get as far as
+// creating and starting their container, https://docs.docker.com/engine/api/v1.38/
+
+const crypto = require('crypto');
+const url = require('url');
+const config = require('./config');
+
+const API_VERSION = '1.38';
+
+const persona = config.persona;
+const goArch = {'x86_64': 'amd64', 'aarch64': 'arm64', 'i686': '386'};
+
+const version = {
+	'Version': config.emulation.docker_version,
+	'ApiVersion': API_VERSION,
+	'MinAPIVersion': '1.12',
+	'GitCommit': 'e68fc7a',
+	'GoVersion': 'go1.10.3',
+	'Os': 'linux',
+	'Arch': goArch[persona.arch] || persona.arch,
+	'KernelVersion': persona.kernel,
+	'BuildTime': '2018-08-21T17:23:03.000000000+00:00'
+};
+
+const info = {
+	'ID': 'K3ZQ:WJ2B:CXO7:5YFT:2HNQ:QJ4P:LRTD:VYMR:7C4U:BZQE:OSDJ:3NWE',
+	'Containers': 2,
+	'ContainersRunning': 2,
+	'ContainersPaused': 0,
+	'ContainersStopped': 0,
+	'Images': 5,
+	'Driver': 'overlay2',
+	'DockerRootDir': '/var/lib/docker',
+	'KernelVersion': persona.kernel,
+	'OperatingSystem': persona.distro,
+	'OSType': 'linux',
+	'Architecture': persona.arch,
+	'NCPU': 4,
+	'MemTotal': 8357658624,
+	'Name': persona.hostname,
+	'ServerVersion': config.emulation.docker_version,
+	'Swarm': {'LocalNodeState': 'inactive'}
+};
+
+// Commands kept per connection, the droppers retry in a loop
+const MAX_COMMANDS = 64;
+
+/**
+ * @returns {object} - State of one connection: the images pulled or run, the commands and the created containers
+ */
+const session = () => ({'images': [], 'commands': [], 'containers': []});
+
+/**
+ * @param {*} value - `Cmd` or `Entrypoint`, an array or a string
+ * @returns {Array}
+ */
+const commandArgs = (value) => Array.isArray(value) ? value.map(String) : (typeof value === 'string' && value.length !== 0 ? [value] : []);
+
+/**
+ * @param {string} body
+ * @returns {object}
+ */
+const parseJson = (body) => {
+	try {
+		let parsed = JSON.parse(body);
+		return parsed !== null && typeof parsed === 'object' ? parsed : {};
+	} catch (err) {
+		return {};
+	}
+};
+
+/**
+ * @param session
+ * @param {string} image
+ */
+const addImage = (session, image) => {
+	if (image && !session.images.includes(image)) session.images.push(image);
+};
+
+/**
+ * @param session
+ * @param {Array} args
+ */
+const addCommand = (session, args) => {
+	if (args.length !== 0 && session.commands.length < MAX_COMMANDS) session.commands.push(args.join(' '));
+};
+
+/**
+ * @param {number} status
+ * @param {*} body - Sent as JSON unless a string
+ * @returns {object}
+ */
+const reply = (status, body) => ({'status': status, 'body': body});
+
+/**
+ * Answers the way a Docker daemon would, remembering what the client wanted to run
+ * @param request - Parsed with `http-request`
+ * @param session - @see session
+ * @returns {object} - HTTP `status` and `body`
+ */
+const respond = (request, session) => {
+	let [path, query_string] = request.path.split('?');
+	let query = new url.URLSearchParams(query_string || '');
+	let method = request.method === 'HEAD' ? 'GET' : request.method;
+	// Every endpoint can be prefixed with the API version, e.g. `/v1.24/containers/create`
+	path = path.replace(/^\/v\d+\.\d+(?=\/)/, '').replace(/\/+$/, '');
+
+	if (path === '/_ping') return reply(200, 'OK');
+	if (method === 'GET' && path === '/version') return reply(200, version);
+	if (method === 'GET' && path === '/info') return reply(200, info);
+	if (method === 'GET' && path === '/images/json') return reply(200, []);
+	if (method === 'GET' && path === '/containers/json') {
+		return reply(200, session.containers.map((container) => ({
+			'Id': container.id,
+			'Names': ['/' + container.name],
+			'Image': container.image,
+			'Command': container.command,
+			'Created': container.created,
+			'State': 'running',
+			'Status': 'Up Less than a second'
+		})));
+	}
+	if (method === 'POST' && path === '/images/create') {
+		let image = query.get('fromImage') || '';
+		let tag = query.get('tag') || 'latest';
+		addImage(session, image + ':' + tag);
+		return reply(200, [
+			{'status': 'Pulling from ' + (image.includes('/') ? image : 'library/' + image), 'id': tag},
+			{'status': 'Digest: sha256:' + crypto.randomBytes(32).toString('hex')},
+			{'status': 'Status: Downloaded newer image for ' + image + ':' + tag}
+		].map((line) => JSON.stringify(line) + '\r\n').join(''));
+	}
+	if (method === 'POST' && path === '/containers/create') {
+		let body = parseJson(request.body);
+		let args = commandArgs(body['Entrypoint']).concat(commandArgs(body['Cmd']));
+		let container = {
+			'id': crypto.randomBytes(32).toString('hex'),
+			'name': query.get('name') || 'container_' + (session.containers.length + 1),
+			'image': typeof body['Image'] === 'string' ? body['Image'] : '',
+			'command': args.join(' '),
+			'created': Math.floor(Date.now() / 1000)
+		};
+		addImage(session, container.image);
+		addCommand(session, args);
+		session.containers.push(container);
+		return reply(201, {'Id': container.id, 'Warnings': null});
+	}
+	if (method === 'POST' && /^\/containers\/[^/]+\/exec$/.test(path)) {
+		addCommand(session, commandArgs(parseJson(request.body)['Cmd']));
+		return reply(201, {'Id': crypto.randomBytes(32).toString('hex')});
+	}
+	if (method === 'POST' && /^\/exec\/[^/]+\/start$/.test(path)) return reply(200, '');
+	if (method === 'POST' && /^\/containers\/[^/]+\/wait$/.test(path)) return reply(200, {'StatusCode': 0});
+	if ((method === 'POST' && /^\/containers\/[^/]+\/(start|stop|restart|kill|attach)$/.test(path)) || (method === 'DELETE' && /^\/containers\/[^/]+$/.test(path))) {
+		return reply(204, '');
+	}
+
+	return reply(404, {'message': 'page not found'});
+};
+
+module.exports = {
+	API_VERSION: API_VERSION,
+	session: session,
+	respond: respond
+};
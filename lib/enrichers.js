@@ -0,0 +1,94 @@
+"use strict";
+
+const fs = require('fs');
+const config = require('./config');
+const logger = require('./logger');
+const cidr = require('./cidr');
+const scanners = require('./scanners');
+
+// The lists are files kept up to date by a cron job (e.g. https://check.torproject.org/torbulkexitlist), re-read this often
+const RELOAD_INTERVAL = 3600 * 1000;
+
+/**
+ * @param {string} file - One IP address or subnet per line, `#` starts a comment
+ * @returns {object} - Ranges made with `cidr.ranges`, empty if the file cannot be read
+ */
+const loadNetworks = (file) => {
+	let networks = [];
+	try {
+		fs.readFileSync(file, 'utf8').split(/\r?\n/).forEach((line) => {
+			line = line.replace(/#.*$/, '').trim();
+			if (line.length === 0) return;
+			try {
+				networks.push(cidr.parse(line));
+			} catch (err) {}
+		});
+	} catch (err) {
+		logger.warning('Cannot read ' + file + ' of `enrichment`. Error Code: ' + err.code);
+	}
+
+	return cidr.ranges(networks);
+};
+
+/**
+ * Tags the addresses listed in a file, the list is re-read once an hour
+ * @param {string} tag
+ * @param {string} file
+ */
+const networkList = (tag, file) => {
+	let networks = loadNetworks(file);
+	setInterval(() => { networks = loadNetworks(file); }, RELOAD_INTERVAL);
+
+	return {
+		'name': tag,
+		'enrich': (item) => {
+			if (cidr.contains(networks, item.ip)) addTag(item, tag);
+		}
+	};
+};
+
+/**
+ * @param item
+ * @param {string} tag
+ */
+const addTag = (item, tag) => {
+	item.tags = item.tags ? item.tags + ',' + tag : tag;
+};
+
+/**
+ * Every request goes through these before it is sent to the live feed and saved, each one sets its own fields:
+ * `scanner_name` for the `known_scanners`, `tags` (comma-separated) for the Tor exit nodes and the datacenters
+ * More can be added with `register`, an enricher is an object with a `name` and an `enrich(item)` function
+ */
+const enrichers = [{
+	'name': 'known_scanners',
+	'enrich': (item) => {
+		let scanner_name = scanners.match(item.ip);
+		if (scanner_name !== null) item.scanner_name = scanner_name;
+	}
+}];
+if (config.enrichment.tor_exit_list) enrichers.push(networkList('tor', config.enrichment.tor_exit_list));
+Object.keys(config.enrichment.datacenter_ranges).forEach((provider) => {
+	enrichers.push(networkList('datacenter:' + provider, config.enrichment.datacenter_ranges[provider]));
+});
+
+/**
+ * @param enricher
+ */
+const register = (enricher) => {
+	enrichers.push(enricher);
+};
+
+/**
+ * @param item - Request as emitted by the services
+ */
+const enrich = (item) => {
+	enrichers.forEach((enricher) => {
+		enricher.enrich(item);
+	});
+};
+
+module.exports = {
+	register: register,
+	enrich: enrich
+};
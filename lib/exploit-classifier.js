@@ -0,0 +1,30 @@
+"use strict";
+
+const config = require('./config');
+
+// Compiled once, the signatures come from the config
+const signatures = config.exploit_signatures.map((signature) => ({
+	'name': signature.name,
+	'patterns': signature.patterns.map((pattern) => new RegExp(pattern, 'i'))
+}));
+
+/**
+ * Tells which well-known exploit the HTTP request carries, if any
+ * @param {string} path - Requested path with the query string
+ * @param {string} payload - Headers and body
+ * @returns {string|null} - Name of the matching signature
+ */
+const classify = (path, payload) => {
+	let decoded = path || '';
+	try {
+		decoded = decodeURIComponent(decoded.replace(/\+/g, ' '));
+	} catch (err) {}
+	let texts = [path || '', decoded, payload || ''];
+	let match = signatures.find((signature) => signature.patterns.some((pattern) => texts.some((text) => pattern.test(text))));
+
+	return match ? match.name : null;
+};
+
+module.exports = {
+	classify: classify
+};
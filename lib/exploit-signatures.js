@@ -0,0 +1,19 @@
+"use strict";
+
+// Well-known exploits probed over HTTP, the first matching signature wins so the specific ones go first
+// `name` - label stored with the request
+// `patterns` - regular expressions matched against the path (also URL-decoded), the headers and the body
+module.exports = [
+	{'name': 'Log4Shell (CVE-2021-44228)', 'patterns': ['\\$\\{(jndi|\\$\\{[^}]*\\}|[a-z]+:[^}]*j[^}]*n[^}]*d[^}]*i)']},
+	{'name': 'Shellshock (CVE-2014-6271)', 'patterns': ['\\(\\)\\s*\\{\\s*:;?\\s*\\}\\s*;']},
+	{'name': 'Spring4Shell (CVE-2022-22965)', 'patterns': ['class\\.module\\.classLoader']},
+	{'name': 'PHPUnit RCE (CVE-2017-9841)', 'patterns': ['/phpunit/.*eval-stdin\\.php']},
+	{'name': 'ThinkPHP RCE (CVE-2018-20062)', 'patterns': ['invokefunction&function=call_user_func_array', '\\\\think\\\\app/invokefunction']},
+	{'name': 'Apache path traversal (CVE-2021-41773)', 'patterns': ['/cgi-bin/(\\.%2e|%2e%2e|\\.\\.)/']},
+	{'name': 'GPON router RCE (CVE-2018-10561)', 'patterns': ['/GponForm/diag_Form']},
+	{'name': 'PHP CGI argument injection (CVE-2012-1823)', 'patterns': ['allow_url_include', 'auto_prepend_file\\s*=\\s*php://input']},
+	{'name': 'PHP code injection', 'patterns': ['<\\?php', 'base64_decode\\s*\\(', 'eval\\s*\\(\\s*\\$_(POST|GET|REQUEST)']},
+	{'name': 'Command injection', 'patterns': ['[;|`]\\s*(wget|curl|tftp)\\s+\\S', '\\$\\(\\s*(wget|curl|echo|id|uname)\\b', '/bin/(ba)?sh\\s+-c\\b']},
+	{'name': 'SQL injection', 'patterns': ['union(\\s|\\+|/\\*\\*/)+(all(\\s|\\+)+)?select\\b', '\'\\s*or\\s+\'?1\'?\\s*=\\s*\'?1', '\\b(sleep|benchmark)\\s*\\(\\s*\\d']},
+	{'name': 'Path traversal', 'patterns': ['(\\.\\./){2,}', '/etc/passwd', 'win\\.ini']}
+];
@@ -1,20 +1,52 @@
 "use strict";
 
-const config = require('./../config');
+const config = require('./config');
 const mysqlPool = require('mysql').createPool(config.mysql_connection_string);
 const EventEmitter = require('events');
-const chalk = require('chalk');
+const logger = require('./logger');
+const cidr = require('./cidr');
+const runMigrations = require('./migrations');
+const fs = require('fs');
+const path = require('path');
+const zlib = require('zlib');
+
+// Set once the MySQL server is reachable, before that nothing is saved (MySQL is optional)
+let databaseAvailable = false;
+// Inserts not settled yet (in flight, waiting for a retry or being spilled), the shutdown waits for them
+let pendingInserts = 0;
+const pendingRetries = new Set();
+let shutdownCallback = null;
+// Set once the pool is being ended, which can only be done once
+let closing = false;
 
 class Mysql extends EventEmitter {
 	constructor() {
 		super();
+		this.connect(1);
+	}
 
+	/**
+	 * The MySQL server may still be starting (e.g. another container), so the connection is retried with a growing delay
+	 * @param {number} attempt
+	 */
+	connect(attempt) {
+		let retry = config.mysql_connect_retry;
 		mysqlPool.query('SELECT 1 + 1 AS two', (error) => {
 			if (error) {
-				console.log(chalk.bgYellow.bold('Warning:') + ' Cannot connect to the MySQL server. Error Code: ' + error.code);
-				return;
+				if (attempt >= retry.attempts) {
+					logger.warning('Cannot connect to the MySQL server. Error Code: ' + error.code + '. Nothing will be saved.');
+					return;
+				}
+				let delay = Math.min(retry.initial_delay * Math.pow(2, attempt - 1), retry.max_delay);
+				logger.warning('Cannot connect to the MySQL server. Error Code: ' + error.code + '. Retrying in ' + delay + ' seconds (attempt ' + attempt + ' of ' + retry.attempts + ').');
+				return setTimeout(() => { this.connect(attempt + 1); }, delay * 1000);
 			}
-			this.init();
+			databaseAvailable = true;
+			runMigrations(mysqlPool, () => {
+				replaySpillFile(() => {
+					this.init();
+				});
+			});
 		});
 	}
 
@@ -22,17 +54,29 @@ class Mysql extends EventEmitter {
 		this.getTotalRequestsNumber();
 		this.getRecentSshCredentials();
 		this.getMonthlyStats();
+		this.aggregateHour();
+		this.aggregateDay();
+		this.checkDatabaseSize();
 		setInterval(() => { this.getRecentSshCredentials(); }, 60 * 1000); // once a minute
+		setInterval(() => { flushSkippedRequests(); }, SKIPPED_FLUSH_INTERVAL);
+		setInterval(() => { this.checkDatabaseSize(); }, config.database_size.interval * 1000);
+		setInterval(() => {
+			this.aggregateHour();
+			this.aggregateDay();
+		}, 600 * 1000); // every 10 minutes
 		setInterval(() => { this.getMonthlyStats(); }, 3600 * 24 * 1000); // once a day
 	}
 
+	/**
+	 * Emits `total_requests_number` with the requests moved out by bin/archive.js counted in, even once their tables are dropped
+	 */
 	getTotalRequestsNumber() {
 		mysqlPool.getConnection((err, connection) => {
-			connection.query('SELECT COUNT(*) as cnt FROM request', (error, results, fields) => {
+			connection.query('SELECT (SELECT IFNULL(SUM(count), 0) FROM request) + (SELECT IFNULL(SUM(total), 0) FROM request_archive) as cnt', (error, results, fields) => {
 				connection.release();
 				if (error) throw error;
 
-				this.emit('total_requests_number', results[0].cnt);
+				this.emit('total_requests_number', Number(results[0].cnt));
 			});
 		});
 	}
@@ -53,30 +97,158 @@ class Mysql extends EventEmitter {
 		});
 	}
 
+	/**
+	 * Rolls up every completed hour since the last aggregated one into `stats_hourly` (per service), `stats_hourly_port`
+	 * and `stats_hourly_user_agent` (HTTP requests only), with the requests of the known scanners among them (`scanners`)
+	 * The current hour is never aggregated, since it is still receiving requests
+	 * On failure (e.g. the MySQL server restarting), the same hours are rolled up on the next run
+	 */
+	aggregateHour() {
+		rollups.forEach((rollup) => {
+			let table = rollup[0];
+			mysqlPool.query('SELECT DATE_ADD(MAX(hour_bucket), INTERVAL 1 HOUR) AS next_bucket FROM ' + table, (error, results) => {
+				if (error) return logger.warning('Cannot roll up the hours into ' + table + '. Error Code: ' + error.code);
+				let range = "date >= IFNULL(?, DATE_FORMAT(DATE_SUB(NOW(), INTERVAL 1 MONTH), '%Y-%m-%d %H:00:00')) AND date < DATE_FORMAT(NOW(), '%Y-%m-%d %H:00:00')";
+				mysqlPool.query(hourlyRollupQuery(rollup, range), [results[0].next_bucket], (error) => {
+					if (error) logger.warning('Cannot roll up the hours into ' + table + '. Error Code: ' + error.code);
+				});
+			});
+		});
+	}
+
+	/**
+	 * Rolls up every completed day since the last aggregated one into `stats_daily`
+	 * Unlike the requests, the unique IP addresses of the hours don't add up to the ones of the day, hence the separate rollup
+	 * Like the hours, the days missed on failure are rolled up on the next run
+	 */
+	aggregateDay() {
+		mysqlPool.query('SELECT DATE_ADD(MAX(day_bucket), INTERVAL 1 DAY) AS next_bucket FROM stats_daily', (error, results) => {
+			if (error) return logger.warning('Cannot roll up the days into stats_daily. Error Code: ' + error.code);
+			mysqlPool.query(dailyRollupQuery, [results[0].next_bucket || new Date(Date.now() - 31 * 24 * 3600 * 1000), new Date()], (error) => {
+				if (error) logger.warning('Cannot roll up the days into stats_daily. Error Code: ' + error.code);
+			});
+		});
+	}
+
+	/**
+	 * Emits `database_size` with the bytes taken by the tables and indexes (as reported by InnoDB, so roughly),
+	 * warns once the `database_size.warning_bytes` are exceeded, before the disk fills up and the inserts start failing
+	 */
+	checkDatabaseSize() {
+		mysqlPool.query('SELECT SUM(data_length + index_length) AS size FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE()', (error, results) => {
+			if (error) return;
+			let size = Number(results[0].size);
+			this.emit('database_size', size);
+			if (config.database_size.warning_bytes && size >= config.database_size.warning_bytes) {
+				logger.warning('The database takes ' + Math.round(size / 1024 / 1024) + ' MB, over the ' + Math.round(config.database_size.warning_bytes / 1024 / 1024) + ' MB of `database_size.warning_bytes`. Please free up some space or remove the older requests.');
+			}
+		});
+	}
+
+	/* Tables of the stats page, once with and once without the known scanners (`exclude_scanners`) */
 	getMonthlyStats() {
-		monthlyStats.services()
-			.then(monthlyStats.credentials())
-			.then(monthlyStats.requests())
+		let collect = (exclude_scanners) => monthlyStats.services(exclude_scanners)
+			.then(() => monthlyStats.credentials(exclude_scanners))
+			.then(() => monthlyStats.requests(exclude_scanners))
+			.then(() => monthlyStats.ips(exclude_scanners))
+			.then(() => monthlyStats.botnets(exclude_scanners))
+			.then(() => monthlyStats.payloads(exclude_scanners));
+		collect(false)
+			.then(() => collect(true))
 			.then(() => {
-				this.emit('monthly_stats', monthlyStats.data);
+				this.emit('monthly_stats', monthlyStats.data, monthlyStats.data_without_scanners);
 			});
 	}
 }
 
+// Hourly rollups: table, column and expression of the key, condition of the counted requests
+const rollups = [
+	['stats_hourly', 'service', "IFNULL(service, '')", '1'],
+	['stats_hourly_port', 'port', 'port', 'port IS NOT NULL'],
+	// Missing and empty User-Agents are counted together
+	['stats_hourly_user_agent', 'user_agent', "IFNULL(NULLIF(LEFT(user_agent, 200), ''), '(none)')", 'http_request_path IS NOT NULL']
+];
+
+/**
+ * @param {Array} rollup - One of the `rollups`
+ * @param {string} range - Condition on the dates of the requests rolled up
+ * @returns {string} - Query counting the requests of the hours, and of the known scanners among them (`scanners`)
+ */
+const hourlyRollupQuery = ([table, column, expression, condition], range) => `
+	INSERT INTO ${table} (hour_bucket, ${column}, total, scanners)
+	SELECT
+		DATE_FORMAT(date, '%Y-%m-%d %H:00:00') AS bucket, ${expression} AS bucket_key, SUM(count), SUM(IF(scanner_name IS NULL, 0, count))
+	FROM
+		request
+	WHERE ${range} AND ${condition}
+	GROUP BY bucket, bucket_key
+	ON DUPLICATE KEY UPDATE total = VALUES(total), scanners = VALUES(scanners)
+`;
+
+// Requests and unique IP addresses per day, from the day of the first date up to the day before the second one (today at most)
+const dailyRollupQuery = `
+	INSERT INTO stats_daily (day_bucket, total, unique_ips)
+	SELECT
+		DATE(date) AS bucket, SUM(count), COUNT(DISTINCT ip)
+	FROM
+		request
+	WHERE date >= DATE(?) AND date < LEAST(DATE(?), CURDATE())
+	GROUP BY bucket
+	ON DUPLICATE KEY UPDATE total = VALUES(total), unique_ips = VALUES(unique_ips)
+`;
+
+/**
+ * Recounts the hours between the dates (both included) in all the rollups, e.g. after importing older requests
+ * The current hour (and day) is left out, it is aggregated as usual once completed
+ * @param {Date} from
+ * @param {Date} to
+ * @param {function} callback - Called with the error, if any
+ */
+const reaggregateHours = (from, to, callback) => {
+	let remaining = rollups.length + 1;
+	let failed = false;
+	let done = (error) => {
+		if (failed) return;
+		if (error) {
+			failed = true;
+			return callback(error);
+		}
+		if (--remaining === 0) callback(null);
+	};
+	mysqlPool.query(dailyRollupQuery, [from, new Date(new Date(to).getTime() + 24 * 3600 * 1000)], done);
+	let range = "date >= DATE_FORMAT(?, '%Y-%m-%d %H:00:00') AND date < LEAST(DATE_ADD(DATE_FORMAT(?, '%Y-%m-%d %H:00:00'), INTERVAL 1 HOUR), DATE_FORMAT(NOW(), '%Y-%m-%d %H:00:00'))";
+	rollups.forEach((rollup) => {
+		mysqlPool.query(hourlyRollupQuery(rollup, range), [from, to], done);
+	});
+};
+
+/**
+ * @param {boolean} exclude_scanners
+ * @returns {string} - Extra condition of the WHERE clause
+ */
+const scannerCondition = (exclude_scanners) => exclude_scanners ? ' AND scanner_name IS NULL' : '';
+
 const monthlyStats = {
 	data: {},
-	services: () => {
+	data_without_scanners: {},
+	/**
+	 * @param {boolean} exclude_scanners
+	 * @returns {object} - Tables computed with or without the known scanners
+	 */
+	target: (exclude_scanners) => exclude_scanners ? monthlyStats.data_without_scanners : monthlyStats.data,
+	services: (exclude_scanners) => {
 		return new Promise((resolve, reject) => {
 			mysqlPool.getConnection((err, connection) => {
-				if (!connection) return;
+				if (!connection) return resolve();
 				let query = `
 					SELECT 
 						service,
-						COUNT(*) AS total,
-						(COUNT(*) / (SELECT COUNT(*) FROM request WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH))) * 100 AS percentage
+						SUM(count) AS total,
+						AVG(duration_ms) AS avg_duration_ms,
+						(SUM(count) / (SELECT SUM(count) FROM request WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH)${scannerCondition(exclude_scanners)})) * 100 AS percentage
 					FROM
 						request
-					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH)
+					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH)${scannerCondition(exclude_scanners)}
 					GROUP BY service
 					ORDER BY total DESC
 				`;
@@ -87,25 +259,27 @@ const monthlyStats = {
 					results.forEach((row) => {
 						rows.push({
 							'service': row['service'],
-							'total': row['total'],
-							'percentage': row['percentage']
+							'total': Number(row['total']),
+							'avg_duration_ms': Math.round(row['avg_duration_ms']),
+							'percentage': Number(row['percentage'])
 						});
 					});
-					monthlyStats.data['services'] = rows;
+					monthlyStats.target(exclude_scanners)['services'] = rows;
 					resolve();
 				});
 			});
 		})
 	},
-	credentials: () => {
-		new Promise((resolve, reject) => {
+	credentials: (exclude_scanners) => {
+		return new Promise((resolve, reject) => {
 			mysqlPool.getConnection((err, connection) => {
+				if (!connection) return resolve();
 				let query = `
 					SELECT 
-						CONCAT(username, ':',password) as credentials, COUNT(*) AS total
+						CONCAT(username, ':', IFNULL(password, '')) as credentials, SUM(count) AS total
 					FROM
 						request
-					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH) AND username IS NOT NULL AND username != ''
+					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH) AND username IS NOT NULL AND username != ''${scannerCondition(exclude_scanners)}
 					GROUP BY username, password
 					ORDER BY total DESC
 					LIMIT 0, 256
@@ -117,24 +291,25 @@ const monthlyStats = {
 					results.forEach((row) => {
 						rows.push({
 							'credentials': row['credentials'],
-							'total': row['total']
+							'total': Number(row['total'])
 						});
 					});
-					monthlyStats.data['credentials'] = rows;
+					monthlyStats.target(exclude_scanners)['credentials'] = rows;
 					resolve();
 				});
 			});
 		})
 	},
-	requests: () => {
-		new Promise((resolve, reject) => {
+	requests: (exclude_scanners) => {
+		return new Promise((resolve, reject) => {
 			mysqlPool.getConnection((err, connection) => {
+				if (!connection) return resolve();
 				let query = `
 					SELECT 
-						http_request_path, COUNT(*) AS total
+						http_request_path, SUM(count) AS total
 					FROM
 						request
-					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH) AND http_request_path IS NOT NULL
+					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH) AND http_request_path IS NOT NULL${scannerCondition(exclude_scanners)}
 					GROUP BY http_request_path
 					ORDER BY total DESC
 					LIMIT 0, 256
@@ -146,10 +321,103 @@ const monthlyStats = {
 					results.forEach((row) => {
 						rows.push({
 							'request': row['http_request_path'],
-							'total': row['total']
+							'total': Number(row['total'])
+						});
+					});
+					monthlyStats.target(exclude_scanners)['requests'] = rows;
+					resolve();
+				});
+			});
+		})
+	},
+	// First/last seen dates come from the same GROUP BY, so they are within the month as well
+	ips: (exclude_scanners) => {
+		return new Promise((resolve, reject) => {
+			mysqlPool.getConnection((err, connection) => {
+				if (!connection) return resolve();
+				let query = `
+					SELECT 
+						ip, SUM(count) AS total, MIN(date) AS first_seen, MAX(date) AS last_seen
+					FROM
+						request
+					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH)${scannerCondition(exclude_scanners)}
+					GROUP BY ip
+					ORDER BY total DESC
+					LIMIT 0, 256
+				`;
+				connection.query(query, (error, results, fields) => {
+					let rows = [];
+					connection.release();
+					if (error) throw error;
+					results.forEach((row) => {
+						rows.push({
+							'ip': row['ip'],
+							'total': Number(row['total']),
+							'first_seen': row['first_seen'],
+							'last_seen': row['last_seen']
+						});
+					});
+					monthlyStats.target(exclude_scanners)['ips'] = rows;
+					resolve();
+				});
+			});
+		})
+	},
+	botnets: (exclude_scanners) => {
+		return new Promise((resolve, reject) => {
+			mysqlPool.getConnection((err, connection) => {
+				if (!connection) return resolve();
+				let query = `
+					SELECT 
+						botnet_family, SUM(count) AS total, COUNT(DISTINCT ip) AS ips
+					FROM
+						request
+					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH) AND botnet_family IS NOT NULL${scannerCondition(exclude_scanners)}
+					GROUP BY botnet_family
+					ORDER BY total DESC
+				`;
+				connection.query(query, (error, results, fields) => {
+					let rows = [];
+					connection.release();
+					if (error) throw error;
+					results.forEach((row) => {
+						rows.push({
+							'botnet_family': row['botnet_family'],
+							'total': Number(row['total']),
+							'ips': row['ips']
+						});
+					});
+					monthlyStats.target(exclude_scanners)['botnets'] = rows;
+					resolve();
+				});
+			});
+		})
+	},
+	payloads: (exclude_scanners) => {
+		return new Promise((resolve, reject) => {
+			mysqlPool.getConnection((err, connection) => {
+				if (!connection) return resolve();
+				let query = `
+					SELECT 
+						payload_kind, SUM(count) AS total, COUNT(DISTINCT ip) AS ips
+					FROM
+						request
+					WHERE date >=  DATE_SUB(NOW(), INTERVAL 1 MONTH) AND payload_kind IS NOT NULL${scannerCondition(exclude_scanners)}
+					GROUP BY payload_kind
+					ORDER BY total DESC
+				`;
+				connection.query(query, (error, results, fields) => {
+					let rows = [];
+					connection.release();
+					if (error) throw error;
+					results.forEach((row) => {
+						rows.push({
+							'payload_kind': row['payload_kind'],
+							'total': Number(row['total']),
+							'ips': row['ips']
 						});
 					});
-					monthlyStats.data['requests'] = rows;
+					monthlyStats.target(exclude_scanners)['payloads'] = rows;
 					resolve();
 				});
 			});
@@ -157,26 +425,288 @@ const monthlyStats = {
 	}
 };
 
+// Widths of the varchar columns of `request` (in characters), @see ./etc/mysql.db.schema.sql
+const columnWidths = {'ip': 128, 'service': 16, 'request': 4096, 'http_request_path': 1024, 'username': 128, 'password': 128, 'botnet_family': 32, 'user_agent': 512, 'scanner_name': 64, 'payload_kind': 16, 'exploit': 64, 'tags': 255, 'malformed': 64, 'docker_image': 255, 'docker_command': 1024, 'end_reason': 16};
+
+// Columns that are only set by some of the services
+const optionalColumns = ['username', 'password', 'http_request_path', 'duration_ms', 'request_size', 'botnet_family', 'port', 'count', 'user_agent', 'scanner_name', 'payload_kind', 'hassh', 'exploit', 'truncated', 'data_received', 'tags', 'malformed', 'docker_image', 'docker_command', 'end_reason'];
+
+// Requests left out by the `sampling`, counted per service, IP address, port, User-Agent and hour (what the stats
+// and the rollups are broken down by), each with the last one of them
+const skippedRequests = new Map();
+const SKIPPED_FLUSH_INTERVAL = 60 * 1000;
+
+/**
+ * Only 1 in `sampling[service]` requests is saved right away, the skipped ones are saved once a minute as a single
+ * request with their `count`, so that the totals and the rollups stay right. Login attempts are always saved
+ * @param item
+ * @returns {boolean} - Whether the request should be saved
+ */
+const sample = (item) => {
+	let rate = config.sampling[item.service];
+	if (!(rate > 1) || item.username !== undefined) return true;
+	if (Math.random() * rate < 1) return true;
+
+	let key = [item.service, item.ip, item.port, (item.user_agent || '').substr(0, 200), Math.floor((item.timestamp || Date.now()) / 3600000)].join('\n');
+	let skipped = skippedRequests.get(key);
+	skippedRequests.set(key, {'item': item, 'count': (skipped ? skipped.count : 0) + (item.count || 1)});
+	return false;
+};
+
+/* Saves the requests skipped by the sampling so far */
+const flushSkippedRequests = () => {
+	skippedRequests.forEach((skipped) => {
+		saveRequest(Object.assign({}, skipped.item, {'count': skipped.count}));
+	});
+	skippedRequests.clear();
+};
+
 const saveToDatabase = (item) => {
+	if (sample(item)) saveRequest(item);
+};
+
+const saveRequest = (item) => {
 	let request = {
 		'ip': item.ip,
 		'service': item.service,
-		'request': item.request,
-		'request_headers': item.request_headers
+		'request': truncate(item.request, config.limits.max_request_bytes),
+		'request_headers': truncate(item.request_headers, item.priority ? config.limits.high_value_payload_bytes : config.limits.max_payload_bytes)
 	};
-	if ('username' in item) request.username = item['username'];
-	if ('password' in item) request.password = item['password'];
-	if ('http_request_path' in item) request.http_request_path = item['http_request_path'];
-
-	mysqlPool.getConnection((err, connection) => {
-		if (!connection) return;
-		let query = connection.query('INSERT INTO request SET ?', request, (error, results, fields) => {
-			connection.release();
-			if (error) throw error;
+	optionalColumns.forEach((column) => {
+		if (column in item) request[column] = item[column];
+	});
+	// The size of the payload as received, even if the stored one is truncated
+	if (request.request_size === undefined && typeof item.request_headers === 'string') request.request_size = Buffer.byteLength(item.request_headers);
+	if (request.request_headers !== item.request_headers) request.truncated = true;
+	fitColumns(request);
+	compressColumns(request);
+
+	insertRequest(request, item.timestamp || Date.now(), 1);
+};
+
+/**
+ * Cuts the values longer than their column, which the database would reject (ER_DATA_TOO_LONG in the strict mode)
+ * whatever the service, e.g. the attacker's username or User-Agent. The row is then saved with `truncated`
+ * @param row - Modified in place
+ */
+const fitColumns = (row) => {
+	for (let column in columnWidths) {
+		if (typeof row[column] === 'string' && row[column].length > columnWidths[column]) {
+			row[column] = row[column].slice(0, columnWidths[column]);
+			row.truncated = true;
+		}
+	}
+};
+
+/**
+ * @param {string} text
+ * @param {number} maxBytes
+ * @returns {string} - Text cut to `maxBytes` (UTF-8) with a marker telling how much is missing
+ */
+const truncate = (text, maxBytes) => {
+	if (typeof text !== 'string') return text;
+	let length = Buffer.byteLength(text);
+	if (length <= maxBytes) return text;
+
+	// Cutting a multi-byte character in half leaves a replacement character at the end
+	return Buffer.from(text).slice(0, maxBytes).toString().replace(/\ufffd$/, '') + '...[truncated ' + (length - maxBytes) + ' bytes]';
+};
+
+// Prefix of the values stored gzip compressed (base64), the ones without it are stored as they are
+const COMPRESSED_PREFIX = 'gz: ';
+// Shorter values hardly get any smaller
+const COMPRESS_MIN_BYTES = 256;
+
+/**
+ * @param {string} text
+ * @returns {string} - Compressed text, unless it doesn't get any shorter. Text starting with the prefix itself (e.g. sent
+ * by an attacker) is always compressed, so that it is read back as it was rather than taken for a compressed one
+ */
+const compress = (text) => {
+	if (typeof text !== 'string') return text;
+	let forced = text.startsWith(COMPRESSED_PREFIX);
+	if (!forced && Buffer.byteLength(text) < COMPRESS_MIN_BYTES) return text;
+	let compressed = COMPRESSED_PREFIX + zlib.gzipSync(text).toString('base64');
+
+	return forced || compressed.length < Buffer.byteLength(text) ? compressed : text;
+};
+
+/**
+ * Compresses the `request` and `request_headers` of the row to insert as configured (`compression`), and those
+ * starting with the prefix of the compressed values whatever the config
+ * @param row - Modified in place
+ */
+const compressColumns = (row) => {
+	['request', 'request_headers'].forEach((column) => {
+		if (config.compression[column] || (typeof row[column] === 'string' && row[column].startsWith(COMPRESSED_PREFIX))) row[column] = compress(row[column]);
+	});
+};
+
+/**
+ * @param {string} text - Value of the `request` or `request_headers` column, compressed or not
+ * @returns {string}
+ */
+const decompress = (text) => {
+	if (typeof text !== 'string' || !text.startsWith(COMPRESSED_PREFIX)) return text;
+	try {
+		return zlib.gunzipSync(Buffer.from(text.substr(COMPRESSED_PREFIX.length), 'base64')).toString();
+	} catch (err) {
+		return text;
+	}
+};
+
+const INSERT_ATTEMPTS = 3;
+
+/**
+ * Insert errors worth retrying later: the connection lost or refused, the server shutting down or too busy, a lock.
+ * The other `ER_*` errors come from the row itself (e.g. ER_DATA_TOO_LONG) and would fail every time
+ * @param error
+ * @returns {boolean}
+ */
+const isTransientError = (error) => {
+	if (error.fatal || !/^ER_/.test(error.code)) return true;
+
+	return ['ER_LOCK_WAIT_TIMEOUT', 'ER_LOCK_DEADLOCK', 'ER_CON_COUNT_ERROR', 'ER_TOO_MANY_USER_CONNECTIONS', 'ER_SERVER_SHUTDOWN', 'ER_OUT_OF_RESOURCES', 'ER_RECORD_FILE_FULL'].includes(error.code);
+};
+
+/**
+ * Inserts the row, retrying with a backoff on failure
+ * If the database is still failing after the last attempt, the row is appended to the spill file to be replayed on the next start,
+ * a row the database rejects is set aside in the `.rejected` file next to it instead
+ * @param request - Row to insert
+ * @param {number} timestamp - Request time, so the replayed rows keep their original date
+ * @param {number} attempt
+ */
+const insertRequest = (request, timestamp, attempt) => {
+	if (!databaseAvailable) return;
+	if (attempt === 1) pendingInserts++;
+	// The pool is being closed, so the request goes straight to the spill file
+	if (shutdownCallback !== null) return spillRequest(request, timestamp, {'code': 'SHUTDOWN'});
+
+	mysqlPool.query('INSERT INTO request SET ?', request, (error) => {
+		if (!error) return settleInsert();
+		if (!isTransientError(error)) return rejectRequest(request, timestamp, error, settleInsert);
+		if (attempt < INSERT_ATTEMPTS && shutdownCallback === null) {
+			let retry = {'request': request, 'timestamp': timestamp, 'error': error};
+			retry.timer = setTimeout(() => {
+				pendingRetries.delete(retry);
+				insertRequest(request, timestamp, attempt + 1);
+			}, Math.pow(2, attempt) * 1000);
+			pendingRetries.add(retry);
+			return;
+		}
+		spillRequest(request, timestamp, error);
+	});
+};
+
+const spillRequest = (request, timestamp, error) => {
+	let line = JSON.stringify(Object.assign({}, request, {'date': timestamp})) + '\n';
+	fs.mkdir(path.dirname(config.spill_file), {recursive: true}, () => {
+		fs.appendFile(config.spill_file, line, (spillError) => {
+			// Last resort: losing the request rather than crashing the app
+			if (spillError) logger.warning('Cannot save the request to the MySQL server (Error Code: ' + error.code + ') nor to the spill file (Error Code: ' + spillError.code + '). Request lost.');
+			settleInsert();
 		});
 	});
 };
 
+/**
+ * Sets aside a row the database refuses, so that it can be looked into, without blocking the ones after it
+ * @param request
+ * @param {number} timestamp
+ * @param error
+ * @param {function} callback
+ */
+const rejectRequest = (request, timestamp, error, callback) => {
+	let line = JSON.stringify(Object.assign({}, request, {'date': timestamp, 'error': error.code})) + '\n';
+	logger.warning('The MySQL server rejected a request from ' + request.ip + '. Error Code: ' + error.code + '. Set aside in ' + config.spill_file + '.rejected');
+	fs.mkdir(path.dirname(config.spill_file), {recursive: true}, () => {
+		fs.appendFile(config.spill_file + '.rejected', line, () => { callback(); });
+	});
+};
+
+const settleInsert = () => {
+	pendingInserts--;
+	if (shutdownCallback !== null && pendingInserts === 0) closePool();
+};
+
+/* The requests of the connections still open are spilled meanwhile, settling them must not end the pool again */
+const closePool = () => {
+	if (closing) return;
+	closing = true;
+	mysqlPool.end(() => { shutdownCallback(); });
+};
+
+/**
+ * Saves the requests skipped by the sampling, waits for the inserts in flight and closes the pool, the requests waiting for a retry are spilled right away,
+ * as well as the ones of the connections finishing after the shutdown has started
+ * @param {function} callback
+ */
+const flushDatabase = (callback) => {
+	flushSkippedRequests();
+	shutdownCallback = callback;
+	pendingRetries.forEach((retry) => {
+		clearTimeout(retry.timer);
+		spillRequest(retry.request, retry.timestamp, retry.error);
+	});
+	pendingRetries.clear();
+	if (pendingInserts === 0) closePool();
+};
+
+/**
+ * Re-inserts the requests spilled to the disk while the database was failing
+ * The file is moved aside before the replay, so the requests spilled in the meantime are not mixed in
+ * The replay stops at the first transient error, the rows the database rejects are set aside and skipped
+ * @param {function} callback
+ */
+const replaySpillFile = (callback) => {
+	let replay_file = config.spill_file + '.replaying';
+	try {
+		// Appending, since the previous replay might have been interrupted
+		if (fs.existsSync(config.spill_file)) {
+			fs.appendFileSync(replay_file, fs.readFileSync(config.spill_file));
+			fs.unlinkSync(config.spill_file);
+		}
+		if (!fs.existsSync(replay_file)) return callback();
+	} catch (err) {
+		logger.warning('Cannot read the spill file. Error Code: ' + err.code);
+		return callback();
+	}
+
+	let lines = fs.readFileSync(replay_file, 'utf8').split('\n').filter((line) => line.length !== 0);
+	let replayed = 0;
+	let next = () => {
+		if (lines.length === 0) {
+			fs.unlinkSync(replay_file);
+			logger.info(`Replayed ${replayed} spilled requests.`);
+			return callback();
+		}
+		let request;
+		try {
+			request = JSON.parse(lines.shift());
+			request.date = new Date(request.date);
+		} catch (error) {
+			return next();
+		}
+		mysqlPool.query('INSERT INTO request SET ?', request, (error) => {
+			if (error && !isTransientError(error)) {
+				let date = request.date.getTime();
+				delete request.date;
+				return rejectRequest(request, date, error, next);
+			}
+			if (error) {
+				// Keeping the rest of the file for the next start
+				fs.writeFileSync(replay_file, [JSON.stringify(Object.assign(request, {'date': request.date.getTime()}))].concat(lines).join('\n') + '\n');
+				logger.warning('Cannot replay spilled requests. Error Code: ' + error.code);
+				return callback();
+			}
+			replayed++;
+			next();
+		});
+	};
+	next();
+};
+
 const formatHeaders = (headers, indent) => {
 	if (typeof headers !== 'object' || headers.length === 0) return;
 	indent = indent ? indent : '';
@@ -193,10 +723,40 @@ const formatHeaders = (headers, indent) => {
 	return s;
 };
 
+/**
+ * Same address is always saved the same way, e.g. `::ffff:192.0.2.1` as `192.0.2.1` and `2001:DB8:0::1` as `2001:db8::1`
+ * @param {string} address
+ */
 const formatIpAddress = (address) => {
-	if (address.length !== 0 && address.substr(0, 7) === "::ffff:") return address.substr(7);
+	if (!address) return address;
+
+	return cidr.normalize(address);
+};
+
+// RFC1918 private networks, loopback, link-local and CGNAT, as well as IPv6 loopback, link-local and unique local addresses
+const privateNetworks = cidr.ranges(['0.0.0.0/8', '10.0.0.0/8', '100.64.0.0/10', '127.0.0.0/8', '169.254.0.0/16', '172.16.0.0/12', '192.168.0.0/16', '::/128', '::1/128', 'fc00::/7', 'fe80::/10'].map(cidr.parse));
+
+/**
+ * @param {string} address
+ */
+const isPrivateIp = (address) => {
+	return cidr.contains(privateNetworks, formatIpAddress(address));
+};
 
-	return address;
+/**
+ * The captured usernames, paths, etc. are the attacker's, so the text cells a spreadsheet would read as a formula
+ * (starting with `=`, `+`, `-`, `@`, a tab or a carriage return) are prefixed with a quote
+ * @param {Array} values
+ * @returns {string} - CSV line
+ */
+const formatCsvRow = (values) => {
+	return values.map((value) => {
+		if (value === null || value === undefined) return '';
+		let formula = typeof value === 'string' && /^[=+\-@\t\r]/.test(value);
+		value = formula ? "'" + value : String(value);
+		if (formula || /[",\r\n]/.test(value)) value = '"' + value.replace(/"/g, '""') + '"';
+		return value;
+	}).join(',') + '\r\n';
 };
 
 const removeOldData = (data) => {
@@ -213,7 +773,19 @@ const removeOldData = (data) => {
 module.exports = {
 	formatHeaders: formatHeaders,
 	saveToDatabase: saveToDatabase,
+	flushDatabase: flushDatabase,
+	reaggregateHours: reaggregateHours,
+	isTransientError: isTransientError,
+	fitColumns: fitColumns,
+	optionalColumns: optionalColumns,
 	formatIpAddress: formatIpAddress,
+	isPrivateIp: isPrivateIp,
+	formatCsvRow: formatCsvRow,
+	compress: compress,
+	compressColumns: compressColumns,
+	decompress: decompress,
 	removeOldData: removeOldData,
+	monthlyStats: monthlyStats,
+	mysqlPool: mysqlPool,
 	Mysql: Mysql
 };
\ No newline at end of file
@@ -0,0 +1,51 @@
+"use strict";
+
+// Reasons of the HTTP parser errors (`clientError`) worth telling apart, the rest are saved with the error code
+const parserErrors = {
+	'HPE_UNEXPECTED_CONTENT_LENGTH': 'Content-Length with Transfer-Encoding',
+	'HPE_INVALID_CONTENT_LENGTH': 'invalid or duplicate Content-Length',
+	'HPE_INVALID_TRANSFER_ENCODING': 'invalid Transfer-Encoding',
+	'HPE_INVALID_CHUNK_SIZE': 'invalid chunk size',
+	'HPE_INVALID_HEADER_TOKEN': 'invalid header',
+	'HPE_LF_EXPECTED': 'bare line feed',
+	'HPE_INVALID_METHOD': 'invalid method',
+	'HPE_INVALID_URL': 'invalid URL',
+	'HPE_INVALID_VERSION': 'invalid HTTP version',
+	'HPE_HEADER_OVERFLOW': 'headers too large'
+};
+
+/**
+ * @param err - `clientError` of the HTTP server
+ * @returns {string} - Why the request was rejected
+ */
+const parserError = (err) => parserErrors[err.code] || err.code;
+
+/**
+ * Request smuggling (desync) probes the parser lets through: framing headers the front-end and the back-end
+ * of a real site could read differently
+ * @param {Array} rawHeaders - Names and values as received, @see http.IncomingMessage
+ * @returns {null|string} - What is off about the request
+ */
+const smuggling = (rawHeaders) => {
+	let content_length = [];
+	let transfer_encoding = [];
+	for (let i = 0; i < rawHeaders.length; i+= 2) {
+		let name = rawHeaders[i].toLowerCase();
+		if (name === 'content-length') content_length.push(rawHeaders[i + 1]);
+		else if (name === 'transfer-encoding') transfer_encoding.push(rawHeaders[i + 1]);
+		// e.g. `Transfer-Encoding : chunked` or `Transfer_Encoding`, read as the real header by some servers
+		else if (/^transfer.encoding\s*$/.test(name)) return 'obfuscated Transfer-Encoding';
+	}
+	if (content_length.length > 1) return 'duplicate Content-Length';
+	if (transfer_encoding.length > 1) return 'duplicate Transfer-Encoding';
+	if (transfer_encoding.length !== 0 && content_length.length !== 0) return 'Content-Length with Transfer-Encoding';
+	// `xchunked`, `chunked, identity`, a tab before the value, etc.
+	if (transfer_encoding.length !== 0 && !/^((gzip|deflate|compress), )*chunked$/.test(transfer_encoding[0])) return 'obfuscated Transfer-Encoding';
+
+	return null;
+};
+
+module.exports = {
+	parserError: parserError,
+	smuggling: smuggling
+};
@@ -0,0 +1,49 @@
+"use strict";
+
+const REQUEST_LINE = /^([A-Z]{3,16}) (\S+) HTTP\/(\d\.\d)$/;
+
+/**
+ * Minimal HTTP/1.x request parser for the raw TCP sockets
+ * @param {Buffer} buffer - Data received so far
+ * @param {number} maxLength - Once reached, whatever has been received is considered to be the whole request
+ * @returns {null|object} - null while the request is incomplete
+ */
+const parse = (buffer, maxLength) => {
+	let header_end = buffer.indexOf('\r\n\r\n');
+	let first_line_end = buffer.indexOf('\r\n');
+	let first_line = buffer.toString('utf8', 0, first_line_end === -1 ? Math.min(buffer.length, 1024) : first_line_end);
+	let match = first_line.match(REQUEST_LINE);
+
+	// Not HTTP at all: no point waiting for the rest of the headers
+	if (first_line_end === -1) return buffer.length < maxLength && /^[A-Z]{0,16}( \S*)?( \S*)?$/.test(first_line) ? null : {'valid': false};
+	if (!match) return {'valid': false};
+	if (header_end === -1) return buffer.length >= maxLength ? {'valid': false} : null;
+
+	let headers = {};
+	buffer.toString('utf8', first_line_end + 2, header_end).split('\r\n').forEach((line) => {
+		let separator = line.indexOf(':');
+		if (separator <= 0) return;
+		let name = line.substr(0, separator).trim().toLowerCase();
+		let value = line.substr(separator + 1).trim();
+		headers[name] = name in headers ? headers[name] + ', ' + value : value;
+	});
+
+	let body = buffer.slice(header_end + 4);
+	let content_length = parseInt(headers['content-length'], 10) || 0;
+	if (body.length < content_length && buffer.length < maxLength) return null;
+
+	return {
+		'valid': true,
+		'method': match[1],
+		'path': match[2],
+		'version': match[3],
+		'headers': headers,
+		'body': body.slice(0, content_length).toString('utf8'),
+		// Bytes taken by the request, the next one on a kept-alive connection starts there
+		'length': header_end + 4 + Math.min(body.length, content_length)
+	};
+};
+
+module.exports = {
+	parse: parse
+};
@@ -2,37 +2,88 @@
 
 const EventEmitter = require('events');
 const {spawn} = require('child_process');
-const chalk = require('chalk');
+const logger = require('./logger');
 
 class IcmpEchoLogger extends EventEmitter {
-	constructor() {
+	/**
+	 * @param {string} family - Either `ipv4` or `ipv6`
+	 */
+	constructor(family = 'ipv4') {
 		super();
+		this.family = family;
+		this.output = '';
+		this.packet = [];
 		this.start();
 	}
 
 	start() {
 		let cmd = 'tcpdump';
-		let args = ['-nvvv', '-l', '-i', 'eth0', 'icmp', 'and', 'icmp[icmptype]=icmp-echo'];
+		// `-x` prints the packet as hex, so we can extract the echo payload
+		let args = ['-nvvv', '-x', '-l', '-i', 'eth0'];
+		// ip6[40] is the ICMPv6 type right after the fixed IPv6 header, 128 is the Echo Request
+		if (this.family === 'ipv6') args.push('icmp6', 'and', 'ip6[40]=128');
+		else args.push('icmp', 'and', 'icmp[icmptype]=icmp-echo');
 		this.tcpdumpProcess = spawn(cmd, args, {stdio: ['ignore', 'pipe', 'ignore']});
 		this.tcpdumpProcess.on('error', (err) => {
-			console.log(chalk.bgYellow.bold('Warning:') + ' Cannot spawn tcpdump. Error code: ' + err.code);
+			logger.warning('Cannot spawn tcpdump. Error code: ' + err.code);
 		});
 		this.tcpdumpProcess.stdout.on('data', (data) => {
-			let echo_request = data.toString();
-			let lines = echo_request.split("\n");
-			if (lines[1] === undefined) return;
-			let ip_address = lines[1].split(">")[0];
-			if (ip_address === undefined || ip_address.length === 0) return;
-			else ip_address = ip_address.trim();
-
-			this.emit('data', {
-				'ip': ip_address,
-				'service': 'ping',
-				'request': 'ICMP echo request from ' + ip_address,
-				'request_headers': echo_request
+			// A single packet might be split between several chunks, so we are collecting the lines until the next packet starts
+			this.output+= data.toString();
+			let lines = this.output.split("\n");
+			this.output = lines.pop();
+			lines.forEach((line) => {
+				if (line.length !== 0 && !/^\s/.test(line)) this.flush();
+				this.packet.push(line);
 			});
+			clearTimeout(this.flushTimer);
+			this.flushTimer = setTimeout(() => { this.flush(); }, 100);
 		});
 	}
+
+	flush() {
+		let lines = this.packet;
+		this.packet = [];
+		// IPv6 packets are printed with the addresses on the same line as the header
+		let addresses = this.family === 'ipv6' ? lines[0] && lines[0].split(") ").pop() : lines[1];
+		if (addresses === undefined) return;
+		let ip_address = addresses.split(">")[0];
+		if (ip_address === undefined || ip_address.length === 0) return;
+		else ip_address = ip_address.trim();
+
+		let hex = '';
+		let description = [];
+		lines.forEach((line) => {
+			let match = line.match(/^\s*0x[0-9a-f]+:\s+([0-9a-f ]+)/i);
+			if (match) hex+= match[1].replace(/\s/g, '');
+			else if (line.trim().length !== 0) description.push(line);
+		});
+
+		let item = {
+			'ip': ip_address,
+			'service': this.family === 'ipv6' ? 'icmpv6' : 'ping',
+			'request': (this.family === 'ipv6' ? 'ICMPv6' : 'ICMP') + ' echo request from ' + ip_address,
+			'request_headers': description.join("\n") + "\n"
+		};
+		if (this.family === 'ipv6') {
+			let length = lines[0].match(/payload length: (\d+)\)/);
+			if (length) item.request_size = 40 + parseInt(length[1], 10);
+		}
+		else {
+			let length = lines[0].match(/length (\d+)\)/);
+			if (length) item.request_size = parseInt(length[1], 10);
+		}
+		if (item.request_size) item.request+= ' (' + item.request_size + ' bytes)';
+		if (hex.length !== 0) {
+			// Skipping IP header and 8 bytes of the ICMP header
+			// IPv4 header length is in the lower nibble of the first byte (in 32-bit words), IPv6 header is always 40 bytes
+			let header_length = this.family === 'ipv6' ? 40 : parseInt(hex[1], 16) * 4;
+			let payload = hex.substr((header_length + 8) * 2);
+			if (payload.length !== 0) item.request_headers+= 'payload: ' + payload + "\n";
+		}
+
+		this.emit('data', item);
+	}
 }
 
-module.exports = IcmpEchoLogger;
\ No newline at end of file
+module.exports = IcmpEchoLogger;
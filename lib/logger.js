@@ -0,0 +1,61 @@
+"use strict";
+
+const chalk = require('chalk');
+
+// `LOG_FORMAT=json` prints every line as a JSON object for the log shippers (Loki, Elastic, Vector, etc.)
+const json = process.env.LOG_FORMAT === 'json';
+
+/**
+ * @param {string} level
+ * @param {string} message
+ * @param fields - Extra fields of the JSON record
+ */
+const write = (level, message, fields) => {
+	let record = Object.assign({'time': new Date().toISOString(), 'level': level, 'message': message}, fields);
+	(level === 'error' ? console.error : console.log)(JSON.stringify(record));
+};
+
+/**
+ * @param {string} title - Highlighted part of the message
+ * @param {string} details
+ */
+const info = (title, details) => {
+	if (json) return write('info', title + (details || ''));
+	console.log(chalk.green.bold(title) + (details || ''));
+};
+
+const warning = (message) => {
+	if (json) return write('warning', message);
+	console.log(chalk.bgYellow.bold('Warning:') + ' ' + message);
+};
+
+const error = (message) => {
+	if (json) return write('error', message);
+	console.error(chalk.bgRed.bold('Error:') + ' ' + message);
+};
+
+/**
+ * One record per captured request, with the details as separate fields so that nothing has to be parsed out of the message
+ * Only in the JSON format, the human readable output stays as quiet as before
+ * @param item
+ */
+const request = (item) => {
+	if (!json) return;
+	write('info', 'request', {
+		'ip': item.ip,
+		'service': item.service,
+		'port': item.port,
+		'request': item.request,
+		'http_request_path': item.http_request_path,
+		'username': item.username,
+		'duration_ms': item.duration_ms,
+		'timestamp': item.timestamp
+	});
+};
+
+module.exports = {
+	info: info,
+	warning: warning,
+	error: error,
+	request: request
+};
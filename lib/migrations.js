@@ -0,0 +1,269 @@
+"use strict";
+
+const logger = require('./logger');
+
+/**
+ * Schema changes for the databases created from an older `./etc/mysql.db.schema.sql`
+ * Every migration is applied only if its column/index/table is missing, so they are safe to run on every start
+ */
+const migrations = [
+	{
+		'table': 'request', 'column': 'duration_ms',
+		'sql': "ALTER TABLE request ADD COLUMN duration_ms int(10) unsigned NOT NULL DEFAULT '0'"
+	},
+	{
+		'table': 'request', 'column': 'request_size',
+		'sql': 'ALTER TABLE request ADD COLUMN request_size int(10) unsigned DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'index': 'idx_date',
+		'sql': 'ALTER TABLE request ADD KEY idx_date (date)'
+	},
+	{
+		'table': 'stats_hourly',
+		'sql': `
+			CREATE TABLE stats_hourly (
+				hour_bucket datetime NOT NULL,
+				service varchar(16) NOT NULL DEFAULT '',
+				total int(11) NOT NULL DEFAULT '0',
+				PRIMARY KEY (hour_bucket, service)
+			) ENGINE=InnoDB DEFAULT CHARSET=utf8
+		`
+	},
+	{
+		// Builds the index over all the existing rows, might take a while on a big table
+		'table': 'request', 'index': 'ft_request',
+		'sql': 'ALTER TABLE request ADD FULLTEXT KEY ft_request (request, request_headers, http_request_path)'
+	},
+	{
+		'table': 'request', 'column': 'botnet_family',
+		'sql': 'ALTER TABLE request ADD COLUMN botnet_family varchar(32) DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'column': 'port',
+		'sql': 'ALTER TABLE request ADD COLUMN port smallint(5) unsigned DEFAULT NULL'
+	},
+	{
+		'table': 'stats_hourly_port',
+		'sql': `
+			CREATE TABLE stats_hourly_port (
+				hour_bucket datetime NOT NULL,
+				port smallint(5) unsigned NOT NULL,
+				total int(11) NOT NULL DEFAULT '0',
+				PRIMARY KEY (hour_bucket, port)
+			) ENGINE=InnoDB DEFAULT CHARSET=utf8
+		`
+	},
+	{
+		'table': 'request', 'column': 'count',
+		'sql': "ALTER TABLE request ADD COLUMN count int(10) unsigned NOT NULL DEFAULT '1'"
+	},
+	{
+		'table': 'request', 'column': 'user_agent',
+		'sql': 'ALTER TABLE request ADD COLUMN user_agent varchar(512) DEFAULT NULL'
+	},
+	{
+		'table': 'stats_hourly_user_agent',
+		'sql': `
+			CREATE TABLE stats_hourly_user_agent (
+				hour_bucket datetime NOT NULL,
+				user_agent varchar(200) NOT NULL,
+				total int(11) NOT NULL DEFAULT '0',
+				PRIMARY KEY (hour_bucket, user_agent)
+			) ENGINE=InnoDB DEFAULT CHARSET=utf8
+		`
+	},
+	{
+		'table': 'request', 'column': 'scanner_name',
+		'sql': 'ALTER TABLE request ADD COLUMN scanner_name varchar(64) DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'column': 'payload_kind',
+		'sql': 'ALTER TABLE request ADD COLUMN payload_kind varchar(16) DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'column': 'hassh',
+		'sql': 'ALTER TABLE request ADD COLUMN hassh char(32) DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'index': 'idx_service',
+		'sql': 'ALTER TABLE request ADD KEY idx_service (service)'
+	},
+	{
+		'table': 'request', 'column': 'exploit',
+		'sql': 'ALTER TABLE request ADD COLUMN exploit varchar(64) DEFAULT NULL'
+	},
+	{
+		'table': 'stats_daily',
+		'sql': `
+			CREATE TABLE stats_daily (
+				day_bucket date NOT NULL,
+				total int(11) NOT NULL DEFAULT '0',
+				unique_ips int(11) NOT NULL DEFAULT '0',
+				PRIMARY KEY (day_bucket)
+			) ENGINE=InnoDB DEFAULT CHARSET=utf8
+		`
+	},
+	{
+		'table': 'request', 'column': 'truncated',
+		'sql': 'ALTER TABLE request ADD COLUMN truncated tinyint(1) DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'column': 'data_received',
+		'sql': 'ALTER TABLE request ADD COLUMN data_received tinyint(1) DEFAULT NULL'
+	},
+	{
+		'table': 'sample',
+		'sql': `
+			CREATE TABLE sample (
+				sha256 char(64) NOT NULL,
+				size int(11) NOT NULL,
+				file_name varchar(255) NOT NULL,
+				service varchar(16) NOT NULL,
+				first_ip varchar(128) NOT NULL,
+				first_seen datetime NOT NULL,
+				last_seen datetime NOT NULL,
+				uploads int(11) NOT NULL DEFAULT '1',
+				detection varchar(255) DEFAULT NULL,
+				PRIMARY KEY (sha256),
+				KEY last_seen (last_seen)
+			) ENGINE=InnoDB DEFAULT CHARSET=utf8
+		`
+	},
+	{
+		'table': 'request', 'column': 'tags',
+		'sql': 'ALTER TABLE request ADD COLUMN tags varchar(255) DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'column': 'malformed',
+		'sql': 'ALTER TABLE request ADD COLUMN malformed varchar(64) DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'column': 'docker_image',
+		'sql': 'ALTER TABLE request ADD COLUMN docker_image varchar(255) DEFAULT NULL'
+	},
+	{
+		'table': 'request', 'column': 'docker_command',
+		'sql': 'ALTER TABLE request ADD COLUMN docker_command varchar(1024) DEFAULT NULL'
+	},
+	{
+		'table': 'delivery',
+		'sql': `
+			CREATE TABLE delivery (
+				id int(11) NOT NULL AUTO_INCREMENT,
+				integration varchar(16) NOT NULL,
+				payload mediumtext NOT NULL,
+				attempts int(11) NOT NULL DEFAULT '1',
+				next_attempt datetime NOT NULL,
+				last_error varchar(255) DEFAULT NULL,
+				PRIMARY KEY (id),
+				KEY next_attempt (next_attempt)
+			) ENGINE=InnoDB DEFAULT CHARSET=utf8
+		`
+	},
+	{
+		// The hours rolled up before have no count of the scanners, so they can't leave them out
+		'table': 'stats_hourly', 'column': 'scanners',
+		'sql': "ALTER TABLE stats_hourly ADD COLUMN scanners int(11) NOT NULL DEFAULT '0'"
+	},
+	{
+		'table': 'stats_hourly_port', 'column': 'scanners',
+		'sql': "ALTER TABLE stats_hourly_port ADD COLUMN scanners int(11) NOT NULL DEFAULT '0'"
+	},
+	{
+		'table': 'stats_hourly_user_agent', 'column': 'scanners',
+		'sql': "ALTER TABLE stats_hourly_user_agent ADD COLUMN scanners int(11) NOT NULL DEFAULT '0'"
+	},
+	{
+		'table': 'request', 'column': 'end_reason',
+		'sql': 'ALTER TABLE request ADD COLUMN end_reason varchar(16) DEFAULT NULL'
+	},
+	{
+		'table': 'sample', 'column': 'checked_at',
+		'sql': 'ALTER TABLE sample ADD COLUMN checked_at datetime DEFAULT NULL'
+	},
+	{
+		'table': 'request_archive',
+		'sql': `
+			CREATE TABLE request_archive (
+				table_name varchar(16) NOT NULL,
+				total bigint(20) NOT NULL DEFAULT '0',
+				PRIMARY KEY (table_name)
+			) ENGINE=InnoDB DEFAULT CHARSET=utf8
+		`
+	}
+];
+
+/**
+ * @param migration
+ * @returns {Array} - Query checking whether the migration has been applied already
+ */
+const checkQuery = (migration) => {
+	if (migration.column) return ['SELECT COUNT(*) AS cnt FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND COLUMN_NAME = ?', [migration.table, migration.column]];
+	if (migration.index) return ['SELECT COUNT(*) AS cnt FROM information_schema.STATISTICS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND INDEX_NAME = ?', [migration.table, migration.index]];
+
+	return ['SELECT COUNT(*) AS cnt FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?', [migration.table]];
+};
+
+/**
+ * Adds the columns added to `request` by the migrations to the tables bin/archive.js moved the past months into
+ * (`request_201809`, etc.), which are made like `request` once, so that archiving into them again and reading them
+ * along with `request` (`include_archived=true`) keep working
+ * @param mysqlPool
+ * @param {function} callback
+ */
+const upgradeArchives = (mysqlPool, callback) => {
+	let sql = "SELECT TABLE_NAME AS table_name, COLUMN_NAME AS column_name FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME REGEXP '^request_[0-9]{6}$'";
+	mysqlPool.query(sql, (error, rows) => {
+		if (error) throw error;
+		let tables = {};
+		rows.forEach((row) => {
+			tables[row['table_name']] = (tables[row['table_name']] || []).concat(row['column_name']);
+		});
+		let pending = [];
+		Object.keys(tables).sort().forEach((table) => {
+			migrations.filter((migration) => migration.table === 'request' && migration.column && !tables[table].includes(migration.column)).forEach((migration) => {
+				pending.push(migration.sql.replace('ALTER TABLE request ', 'ALTER TABLE ' + table + ' '));
+			});
+		});
+		let next = () => {
+			let alter = pending.shift();
+			if (!alter) return callback();
+
+			logger.info('Migrating the database: ', alter);
+			mysqlPool.query(alter, (error) => {
+				if (error) throw error;
+				next();
+			});
+		};
+		next();
+	});
+};
+
+/**
+ * Applies the missing migrations one by one, to the archived months too
+ * @param mysqlPool
+ * @param {function} callback
+ */
+const runMigrations = (mysqlPool, callback) => {
+	let pending = migrations.slice();
+	let next = () => {
+		let migration = pending.shift();
+		if (!migration) return upgradeArchives(mysqlPool, callback);
+
+		let [query, params] = checkQuery(migration);
+		mysqlPool.query(query, params, (error, results) => {
+			if (error) throw error;
+			if (results[0].cnt !== 0) return next();
+
+			logger.info('Migrating the database: ', migration.sql.trim().split('\n')[0]);
+			mysqlPool.query(migration.sql, (error) => {
+				if (error) throw error;
+				next();
+			});
+		});
+	};
+	next();
+};
+
+module.exports = runMigrations;
@@ -0,0 +1,184 @@
+"use strict";
+
+// MySQL Client/Server Protocol, just enough of it to capture the login attempts
+// https://dev.mysql.com/doc/internals/en/connection-phase-packets.html
+
+const crypto = require('crypto');
+
+const CLIENT_CONNECT_WITH_DB = 0x00000008;
+const CLIENT_PROTOCOL_41 = 0x00000200;
+const CLIENT_SSL = 0x00000800;
+const CLIENT_SECURE_CONNECTION = 0x00008000;
+const CLIENT_PLUGIN_AUTH = 0x00080000;
+const CLIENT_CONNECT_ATTRS = 0x00100000;
+const CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA = 0x00200000;
+
+// Everything a 5.7 server advertises, except for the SSL and compression which we cannot speak
+const SERVER_CAPABILITIES = 0x01fff7df;
+const SERVER_STATUS_AUTOCOMMIT = 0x0002;
+const CHARSET_UTF8_GENERAL_CI = 0x21;
+
+/**
+ * Wraps the payload into a packet: 3 bytes payload length followed by the sequence id
+ * @param {Buffer} payload
+ * @param {number} sequenceId
+ */
+const packet = (payload, sequenceId) => {
+	let header = Buffer.alloc(4);
+	header.writeUIntLE(payload.length, 0, 3);
+	header.writeUInt8(sequenceId, 3);
+
+	return Buffer.concat([header, payload]);
+};
+
+/* 20 bytes of printable characters, the real server never uses `\0` and `$` in the scramble */
+const scramble = () => {
+	return Buffer.from(Array.from(crypto.randomBytes(20)).map((byte) => {
+		let char = 33 + byte % 94;
+		return char === 36 ? 37 : char;
+	}));
+};
+
+/**
+ * Protocol::HandshakeV10
+ * @param {number} connectionId
+ * @param {string} serverVersion
+ * @param {Buffer} authPluginData - 20 bytes scramble
+ */
+const handshake = (connectionId, serverVersion, authPluginData) => {
+	let fixed = Buffer.alloc(4 + 8 + 1 + 2 + 1 + 2 + 2 + 1 + 10);
+	let offset = fixed.writeUInt32LE(connectionId, 0);
+	offset+= authPluginData.copy(fixed, offset, 0, 8);
+	offset = fixed.writeUInt8(0, offset);
+	offset = fixed.writeUInt16LE(SERVER_CAPABILITIES & 0xffff, offset);
+	offset = fixed.writeUInt8(CHARSET_UTF8_GENERAL_CI, offset);
+	offset = fixed.writeUInt16LE(SERVER_STATUS_AUTOCOMMIT, offset);
+	offset = fixed.writeUInt16LE(SERVER_CAPABILITIES >>> 16, offset);
+	fixed.writeUInt8(authPluginData.length + 1, offset);
+
+	return packet(Buffer.concat([
+		Buffer.from([0x0a]),
+		Buffer.from(serverVersion + '\0'),
+		fixed,
+		authPluginData.slice(8),
+		Buffer.from('\0mysql_native_password\0')
+	]), 0);
+};
+
+/**
+ * ERR_Packet
+ * @param {number} code
+ * @param {string} sqlState
+ * @param {string} message
+ * @param {number} sequenceId
+ */
+const error = (code, sqlState, message, sequenceId) => {
+	let header = Buffer.alloc(3);
+	header.writeUInt8(0xff, 0);
+	header.writeUInt16LE(code, 1);
+
+	return packet(Buffer.concat([header, Buffer.from('#' + sqlState + message)]), sequenceId);
+};
+
+/**
+ * Small cursor over the packet payload
+ * @param {Buffer} buffer
+ */
+const reader = (buffer) => {
+	let offset = 0;
+	let self = {
+		remaining: () => buffer.length - offset,
+		skip: (length) => { offset+= length; },
+		uint: (length) => {
+			let value = buffer.readUIntLE(offset, length);
+			offset+= length;
+			return value;
+		},
+		lengthEncodedInt: () => {
+			let first = self.uint(1);
+			if (first < 0xfb) return first;
+			if (first === 0xfc) return self.uint(2);
+			if (first === 0xfd) return self.uint(3);
+			if (first === 0xfe) return self.uint(6) + self.uint(2) * Math.pow(2, 48);
+			return 0;
+		},
+		bytes: (length) => {
+			let value = buffer.slice(offset, offset + length);
+			offset+= length;
+			return value;
+		},
+		nullTerminated: () => {
+			let end = buffer.indexOf(0, offset);
+			if (end === -1) end = buffer.length;
+			let value = buffer.toString('utf8', offset, end);
+			offset = end + 1;
+			return value;
+		},
+		lengthEncodedString: () => self.bytes(self.lengthEncodedInt()).toString('utf8')
+	};
+
+	return self;
+};
+
+/**
+ * Returns the full packet (header included) once it is available in the buffer
+ * @param {Buffer} buffer
+ */
+const readPacket = (buffer) => {
+	if (buffer.length < 4) return null;
+	let length = buffer.readUIntLE(0, 3);
+	if (buffer.length < 4 + length) return null;
+
+	return {'sequence_id': buffer.readUInt8(3), 'payload': buffer.slice(4, 4 + length)};
+};
+
+/**
+ * Protocol::HandshakeResponse41 (or the pre-4.1 Protocol::HandshakeResponse320)
+ * @param {Buffer} payload
+ */
+const parseHandshakeResponse = (payload) => {
+	let response = {'username': '', 'auth_response': '', 'database': null, 'auth_plugin': null, 'attributes': {}, 'ssl': false};
+	let r = reader(payload);
+	try {
+		let capabilities = r.uint(2);
+		if (!(capabilities & CLIENT_PROTOCOL_41)) {
+			r.skip(3);
+			response.username = r.nullTerminated();
+			response.auth_response = r.bytes(r.remaining()).toString('hex');
+			return response;
+		}
+		capabilities+= r.uint(2) * 0x10000;
+		r.skip(4 + 1 + 23);
+		// SSLRequest is a truncated handshake response, the client would start the TLS handshake right after it
+		if (r.remaining() === 0) {
+			response.ssl = !!(capabilities & CLIENT_SSL);
+			return response;
+		}
+		response.username = r.nullTerminated();
+		if (capabilities & CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA) response.auth_response = r.bytes(r.lengthEncodedInt()).toString('hex');
+		else if (capabilities & CLIENT_SECURE_CONNECTION) response.auth_response = r.bytes(r.uint(1)).toString('hex');
+		else response.auth_response = Buffer.from(r.nullTerminated()).toString('hex');
+		if ((capabilities & CLIENT_CONNECT_WITH_DB) && r.remaining() > 0) response.database = r.nullTerminated();
+		if ((capabilities & CLIENT_PLUGIN_AUTH) && r.remaining() > 0) response.auth_plugin = r.nullTerminated();
+		if ((capabilities & CLIENT_CONNECT_ATTRS) && r.remaining() > 0) {
+			let end = r.lengthEncodedInt();
+			end = payload.length - r.remaining() + end;
+			while (payload.length - r.remaining() < end) {
+				let key = r.lengthEncodedString();
+				response.attributes[key] = r.lengthEncodedString();
+			}
+		}
+	} catch (err) {
+		// Truncated or garbage packet, keeping whatever was parsed so far
+	}
+
+	return response;
+};
+
+module.exports = {
+	scramble: scramble,
+	handshake: handshake,
+	error: error,
+	readPacket: readPacket,
+	parseHandshakeResponse: parseHandshakeResponse
+};
@@ -0,0 +1,33 @@
+"use strict";
+
+// Rough triage of the raw payloads captured on the generic TCP ports
+
+const NOP_SLED = Buffer.alloc(16, 0x90);
+// xor eax,eax; push eax; push "//sh" and the same for x86_64, the usual start of the execve("/bin/sh") shellcode
+const SHELLCODE_PATTERNS = [Buffer.from('31c05068', 'hex'), Buffer.from('4831f656', 'hex'), Buffer.from('/bin//sh'), Buffer.from('/bin/sh\x00')];
+
+/**
+ * @param {Buffer} buffer - Data received from the client
+ * @returns {null|string} - `shellcode`, `executable`, `gzip`, `zlib`, `base64`, `hex`, `text` or `binary`
+ */
+const classify = (buffer) => {
+	if (buffer.length === 0) return null;
+
+	if (buffer.length >= 2 && buffer[0] === 0x1f && buffer[1] === 0x8b) return 'gzip';
+	// CMF/FLG pair of the zlib stream header is a multiple of 31
+	if (buffer.length >= 2 && buffer[0] === 0x78 && ((buffer[0] << 8) + buffer[1]) % 31 === 0) return 'zlib';
+	if (buffer.length >= 4 && buffer.toString('latin1', 0, 4) === '\x7fELF') return 'executable';
+	if (buffer.includes(NOP_SLED) || SHELLCODE_PATTERNS.some((pattern) => buffer.includes(pattern))) return 'shellcode';
+
+	let text = buffer.toString('utf8');
+	if (/[\x00-\x08\x0b\x0c\x0e-\x1f\x7f�]/.test(text)) return 'binary';
+	let blob = text.trim();
+	if (blob.length >= 32 && /^([0-9a-f]{2})+$/i.test(blob)) return 'hex';
+	if (blob.length >= 32 && blob.length % 4 === 0 && /^[A-Za-z0-9+\/]+={0,2}$/.test(blob)) return 'base64';
+
+	return 'text';
+};
+
+module.exports = {
+	classify: classify
+};
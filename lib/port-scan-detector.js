@@ -0,0 +1,46 @@
+"use strict";
+
+const EventEmitter = require('events');
+
+/**
+ * Distinct ports connected to per IP address, shared by all the services
+ * Every service logs its own connections, a scan only shows up when they are put together
+ * Emits `scan` with the IP address and the ports once the `window` of an address with `threshold` or more ports is over
+ */
+class PortScanDetector extends EventEmitter {
+	/**
+	 * @param {number} threshold - Distinct ports within the window taken for a scan, 0 disables the detector
+	 * @param {number} window - Seconds since the first connection of the address
+	 */
+	constructor(threshold, window) {
+		super();
+		this.threshold = threshold;
+		this.window = window * 1000;
+		this.addresses = new Map();
+
+		if (this.threshold) setInterval(() => { this.flush(); }, Math.min(this.window, 10 * 1000));
+	}
+
+	/**
+	 * @param {string} ip
+	 * @param {number} port - Local port connected to
+	 */
+	record(ip, port) {
+		if (!this.threshold || !port) return;
+
+		let address = this.addresses.get(ip);
+		if (!address) this.addresses.set(ip, address = {'ports': new Set(), 'started_at': Date.now()});
+		address.ports.add(port);
+	}
+
+	flush() {
+		let now = Date.now();
+		this.addresses.forEach((address, ip) => {
+			if (now - address.started_at < this.window) return;
+			if (address.ports.size >= this.threshold) this.emit('scan', ip, Array.from(address.ports).sort((a, b) => a - b), now - address.started_at);
+			this.addresses.delete(ip);
+		});
+	}
+}
+
+module.exports = PortScanDetector;
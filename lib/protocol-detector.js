@@ -0,0 +1,125 @@
+"use strict";
+
+// Guesses the protocol spoken by the client from the first bytes, regardless of the port it connected to
+
+const MONGODB_OP_QUERY = 2004;
+const MONGODB_OP_MSG = 2013;
+
+const TLS_VERSIONS = {0x0300: 'SSL 3.0', 0x0301: 'TLS 1.0', 0x0302: 'TLS 1.1', 0x0303: 'TLS 1.2', 0x0304: 'TLS 1.3'};
+const TLS_EXTENSION_SERVER_NAME = 0;
+const TLS_EXTENSION_SUPPORTED_VERSIONS = 43;
+
+/**
+ * Reads the server name (SNI), the highest offered version and the number of cipher suites out of the ClientHello
+ * Only the first record is looked at, whatever doesn't fit in it is left out
+ * @param {Buffer} buffer - Starting with the TLS record header
+ * @returns {object}
+ */
+const parseClientHello = (buffer) => {
+	let details = {};
+	try {
+		// Record header (5 bytes), handshake type and length (4 bytes)
+		if (buffer[5] !== 0x01) return details;
+		let end = Math.min(buffer.length, 5 + buffer.readUInt16BE(3));
+		let offset = 9;
+		let version = buffer.readUInt16BE(offset);
+		offset+= 2 + 32;
+		offset+= 1 + buffer[offset];
+		let cipher_suites = buffer.readUInt16BE(offset);
+		details.cipher_suites = cipher_suites / 2;
+		offset+= 2 + cipher_suites;
+		offset+= 1 + buffer[offset];
+		let extensions_end = Math.min(end, offset + 2 + buffer.readUInt16BE(offset));
+		offset+= 2;
+		while (offset + 4 <= extensions_end) {
+			let type = buffer.readUInt16BE(offset);
+			let length = buffer.readUInt16BE(offset + 2);
+			let data = buffer.slice(offset + 4, Math.min(offset + 4 + length, extensions_end));
+			if (type === TLS_EXTENSION_SERVER_NAME && data.length >= 5 && data[2] === 0x00) {
+				details.sni = data.toString('latin1', 5, 5 + data.readUInt16BE(3));
+			}
+			if (type === TLS_EXTENSION_SUPPORTED_VERSIONS && data.length >= 3) {
+				for (let i = 1; i + 1 < data.length && i <= data[0]; i+= 2) {
+					let offered = data.readUInt16BE(i);
+					// Skipping the GREASE values, which are not in the list
+					if (TLS_VERSIONS[offered] && offered > version) version = offered;
+				}
+			}
+			offset+= 4 + length;
+		}
+		if (TLS_VERSIONS[version]) details.version = TLS_VERSIONS[version];
+	} catch (err) {
+		// Truncated ClientHello, keeping what was read so far
+	}
+
+	return details;
+};
+
+/**
+ * @param {Buffer} buffer - Data received from the client
+ * @returns {null|object} - `protocol` name and optional `details` extracted from the handshake
+ */
+const detect = (buffer) => {
+	if (buffer.length < 4) return null;
+	let text = buffer.toString('latin1', 0, Math.min(buffer.length, 512));
+
+	// TPKT header followed by the X.224 Connection Request, the cookie carries the username
+	if (buffer[0] === 0x03 && buffer[1] === 0x00 && buffer.length > 5 && buffer[5] === 0xe0) {
+		let cookie = text.match(/Cookie: mstshash=([^\r\n]*)/);
+		return {'protocol': 'RDP', 'details': cookie ? {'username': cookie[1]} : {}};
+	}
+	// NetBIOS session header followed by the SMB header
+	if (buffer.length >= 8 && buffer[0] === 0x00 && buffer.toString('latin1', 5, 8) === 'SMB') {
+		if (buffer[4] === 0xff) return {'protocol': 'SMB1', 'details': {}};
+		if (buffer[4] === 0xfe) return {'protocol': 'SMB2', 'details': {}};
+	}
+	if (buffer.length >= 16 && buffer.readInt32LE(0) === buffer.length) {
+		let op_code = buffer.readInt32LE(12);
+		if (op_code === MONGODB_OP_QUERY || op_code === MONGODB_OP_MSG) return {'protocol': 'MongoDB', 'details': {}};
+	}
+	// Handshake record of TLS 1.0-1.3 (SSL 3.0 included)
+	if (buffer[0] === 0x16 && buffer[1] === 0x03 && buffer[2] <= 0x04) return {'protocol': 'TLS', 'details': parseClientHello(buffer)};
+	if (buffer[0] === 0x05 && buffer[1] > 0 && buffer.length === 2 + buffer[1]) return {'protocol': 'SOCKS5', 'details': {}};
+	if (buffer[0] === 0x04 && (buffer[1] === 0x01 || buffer[1] === 0x02)) return {'protocol': 'SOCKS4', 'details': {}};
+	if (buffer[0] === 0xff && buffer[1] >= 0xfb && buffer[1] <= 0xfe) return {'protocol': 'telnet', 'details': {}};
+	if (text.startsWith('SSH-')) return {'protocol': 'SSH', 'details': {'version': text.split(/\r?\n/)[0]}};
+	if (text.startsWith('JDWP-Handshake')) return {'protocol': 'JDWP', 'details': {}};
+	if (/^[A-Z]+ sips?:\S+ SIP\/2\.0\r?\n/.test(text)) return {'protocol': 'SIP', 'details': {}};
+	if (/^[A-Z]{3,16} \S+ HTTP\/\d\.\d\r?\n/.test(text)) return {'protocol': 'HTTP', 'details': {}};
+	if (/^\*\d+\r\n\$\d+\r\n/.test(text)) return {'protocol': 'Redis', 'details': {}};
+
+	return null;
+};
+
+/**
+ * @param {Buffer} buffer
+ * @returns {boolean} - Whether the payload can be stored as text
+ */
+const isPrintable = (buffer) => {
+	let text = buffer.toString('utf8');
+
+	return !/[\x00-\x08\x0b\x0c\x0e-\x1f\x7f\ufffd]/.test(text);
+};
+
+/**
+ * Drops the incomplete UTF-8 character left at the end when the payload was cut at the capture limit,
+ * which would otherwise make a text payload look binary
+ * @param {Buffer} buffer
+ * @returns {Buffer}
+ */
+const trimPartialCharacter = (buffer) => {
+	// The lead byte of the last character is at most 3 bytes back
+	for (let i = buffer.length - 1; i >= 0 && i >= buffer.length - 4; i--) {
+		if ((buffer[i] & 0xc0) === 0x80) continue;
+		let length = buffer[i] >= 0xf0 ? 4 : (buffer[i] >= 0xe0 ? 3 : (buffer[i] >= 0xc0 ? 2 : 1));
+		return buffer.length - i < length ? buffer.slice(0, i) : buffer;
+	}
+
+	return buffer;
+};
+
+module.exports = {
+	detect: detect,
+	isPrintable: isPrintable,
+	trimPartialCharacter: trimPartialCharacter
+};
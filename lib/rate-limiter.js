@@ -0,0 +1,63 @@
+"use strict";
+
+const EventEmitter = require('events');
+
+/**
+ * Token bucket per IP address
+ * Emits `summary` with the number of suppressed requests per IP once every `summaryInterval` seconds
+ */
+class RateLimiter extends EventEmitter {
+	/**
+	 * @param {number} rate - Tokens added per second, 0 disables the limiter
+	 * @param {number} burst - Bucket size
+	 * @param {number} summaryInterval - Seconds between the summaries
+	 */
+	constructor(rate, burst, summaryInterval) {
+		super();
+		this.rate = rate;
+		this.burst = Math.max(burst, 1);
+		this.buckets = new Map();
+
+		setInterval(() => { this.flush(); }, summaryInterval * 1000);
+	}
+
+	/**
+	 * @param {string} ip
+	 * @returns {boolean} - Whether the request should be logged
+	 */
+	take(ip) {
+		if (!this.rate) return true;
+
+		let now = Date.now();
+		let bucket = this.buckets.get(ip);
+		// New IP addresses start with a full bucket, so the first request is always logged
+		if (!bucket) {
+			bucket = {'tokens': this.burst, 'updated': now, 'suppressed': 0};
+			this.buckets.set(ip, bucket);
+		}
+		bucket.tokens = Math.min(this.burst, bucket.tokens + (now - bucket.updated) / 1000 * this.rate);
+		bucket.updated = now;
+
+		if (bucket.tokens >= 1) {
+			bucket.tokens--;
+			return true;
+		}
+		bucket.suppressed++;
+
+		return false;
+	}
+
+	/* Reports suppressed requests and evicts idle buckets, so the memory stays bounded */
+	flush() {
+		let now = Date.now();
+		this.buckets.forEach((bucket, ip) => {
+			if (bucket.suppressed !== 0) {
+				this.emit('summary', ip, bucket.suppressed);
+				bucket.suppressed = 0;
+			}
+			if (bucket.tokens + (now - bucket.updated) / 1000 * this.rate >= this.burst) this.buckets.delete(ip);
+		});
+	}
+}
+
+module.exports = RateLimiter;
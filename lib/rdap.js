@@ -0,0 +1,123 @@
+"use strict";
+
+const https = require('https');
+const url = require('url');
+
+// Redirects followed, rdap.org sends the client on to the registry of the address (ARIN, RIPE, etc.)
+const MAX_REDIRECTS = 3;
+// Lookups kept in the cache, the oldest one is forgotten first
+const MAX_CACHE_SIZE = 10000;
+
+/**
+ * Registration details of the IP addresses (network name, abuse contact) from RDAP
+ * Only looked up on demand, cached for `cache_ttl` seconds and limited to `requests_per_minute`,
+ * so that the registries' servers don't get hammered
+ */
+class RdapClient {
+	/**
+	 * @param options - `rdap` section of the config
+	 */
+	constructor(options) {
+		this.options = options;
+		this.cache = new Map();
+		this.minute = 0;
+		this.requests = 0;
+	}
+
+	/**
+	 * @param {string} ip
+	 * @returns {Promise} - Resolves with the details, rejects when the lookup was not possible
+	 */
+	lookup(ip) {
+		let cached = this.cache.get(ip);
+		if (cached && cached.expires_at > Date.now()) return Promise.resolve(cached.details);
+
+		let minute = Math.floor(Date.now() / 60000);
+		if (minute !== this.minute) {
+			this.minute = minute;
+			this.requests = 0;
+		}
+		if (this.requests >= this.options.requests_per_minute) return Promise.reject(new Error('Too many RDAP lookups, try again in a minute'));
+		this.requests++;
+
+		return this.fetch(this.options.url + encodeURIComponent(ip), MAX_REDIRECTS).then((body) => {
+			let details = RdapClient.parse(body);
+			this.cache.delete(ip);
+			this.cache.set(ip, {'details': details, 'expires_at': Date.now() + this.options.cache_ttl * 1000});
+			if (this.cache.size > MAX_CACHE_SIZE) this.cache.delete(this.cache.keys().next().value);
+			return details;
+		});
+	}
+
+	/**
+	 * @param {string} address
+	 * @param {number} redirects - Redirects left to follow
+	 * @returns {Promise} - Resolves with the parsed JSON
+	 */
+	fetch(address, redirects) {
+		return new Promise((resolve, reject) => {
+			let req = https.get(Object.assign(url.parse(address), {'headers': {'Accept': 'application/rdap+json'}, 'timeout': this.options.timeout * 1000}), (res) => {
+				if ([301, 302, 303, 307, 308].includes(res.statusCode) && res.headers['location'] && redirects > 0) {
+					res.resume();
+					return resolve(this.fetch(url.resolve(address, res.headers['location']), redirects - 1));
+				}
+				if (res.statusCode !== 200) {
+					res.resume();
+					return reject(new Error('RDAP lookup failed. HTTP Status: ' + res.statusCode));
+				}
+				let body = '';
+				res.setEncoding('utf8');
+				res.on('data', (chunk) => { body+= chunk; });
+				res.on('end', () => {
+					try {
+						resolve(JSON.parse(body));
+					} catch (err) {
+						reject(new Error('Invalid RDAP response'));
+					}
+				});
+			});
+			req.on('timeout', () => {
+				req.abort();
+			});
+			req.on('error', (err) => {
+				reject(new Error('Cannot reach the RDAP server. Error Code: ' + (err.code || err.message)));
+			});
+		});
+	}
+
+	/**
+	 * @param body - RDAP `ip network` object
+	 * @returns {object}
+	 */
+	static parse(body) {
+		let cidrs = (body['cidr0_cidrs'] || []).map((cidr) => (cidr['v4prefix'] || cidr['v6prefix']) + '/' + cidr['length']);
+
+		return {
+			'name': body['name'] || null,
+			'handle': body['handle'] || null,
+			'country': body['country'] || null,
+			'range': cidrs.length !== 0 ? cidrs.join(', ') : (body['startAddress'] ? body['startAddress'] + ' - ' + body['endAddress'] : null),
+			'abuse_email': RdapClient.abuseEmail(body['entities'] || [])
+		};
+	}
+
+	/**
+	 * The abuse contact is an entity with the `abuse` role, usually nested in the one of the organization
+	 * @param {Array} entities
+	 * @returns {null|string}
+	 */
+	static abuseEmail(entities) {
+		for (let entity of entities) {
+			if ((entity['roles'] || []).includes('abuse')) {
+				let email = ((entity['vcardArray'] || [])[1] || []).find((property) => property[0] === 'email');
+				if (email) return email[3];
+			}
+			let nested = RdapClient.abuseEmail(entity['entities'] || []);
+			if (nested !== null) return nested;
+		}
+
+		return null;
+	}
+}
+
+module.exports = RdapClient;
@@ -0,0 +1,92 @@
+"use strict";
+
+// Connection sequence of RDP, just the X.224 Connection Request/Confirm exchange
+// https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr/18a27ef9-6f9a-4501-b000-94b1fe3c2c10
+
+const X224_CONNECTION_REQUEST = 0xe0;
+const X224_CONNECTION_CONFIRM = 0xd0;
+const TYPE_RDP_NEG_REQ = 0x01;
+const TYPE_RDP_NEG_RSP = 0x02;
+
+const PROTOCOL_RDP = 0x00;
+const PROTOCOL_SSL = 0x01;
+const PROTOCOL_HYBRID = 0x02;
+const PROTOCOL_RDSTLS = 0x04;
+const PROTOCOL_HYBRID_EX = 0x08;
+
+/**
+ * @param {Buffer} buffer - Data received from the client so far
+ * @returns {null|false|object} - `null` until the whole TPKT is received, `false` if it is not a Connection Request,
+ * the `cookie` (mstshash username) and `requested_protocols` otherwise
+ */
+const parseConnectionRequest = (buffer) => {
+	if (buffer.length < 4) return null;
+	if (buffer[0] !== 0x03 || buffer[1] !== 0x00) return false;
+	let length = buffer.readUInt16BE(2);
+	if (buffer.length < length) return null;
+	if (length < 11 || (buffer[5] & 0xf0) !== X224_CONNECTION_REQUEST) return false;
+
+	// Variable part after the fixed TPKT (4 bytes) and X.224 (7 bytes) headers
+	let data = buffer.slice(11, length);
+	let request = {'cookie': null, 'requested_protocols': null};
+	let end = data.indexOf('\r\n');
+	if (end !== -1) {
+		let token = data.toString('latin1', 0, end);
+		if (token.startsWith('Cookie: mstshash=')) request.cookie = token.substr('Cookie: mstshash='.length);
+		data = data.slice(end + 2);
+	}
+	if (data.length >= 8 && data[0] === TYPE_RDP_NEG_REQ) request.requested_protocols = data.readUInt32LE(4);
+
+	return request;
+};
+
+/**
+ * X.224 Connection Confirm, with the RDP Negotiation Response when the client has sent the request
+ * @param {null|number} selectedProtocol
+ */
+const connectionConfirm = (selectedProtocol) => {
+	let negotiation = Buffer.alloc(0);
+	if (selectedProtocol !== null) {
+		negotiation = Buffer.alloc(8);
+		negotiation.writeUInt8(TYPE_RDP_NEG_RSP, 0);
+		negotiation.writeUInt16LE(8, 2);
+		negotiation.writeUInt32LE(selectedProtocol, 4);
+	}
+	let x224 = Buffer.from([6 + negotiation.length, X224_CONNECTION_CONFIRM, 0x00, 0x00, 0x12, 0x34, 0x00]);
+	let tpkt = Buffer.from([0x03, 0x00, 0x00, 0x00]);
+	tpkt.writeUInt16BE(tpkt.length + x224.length + negotiation.length, 2);
+
+	return Buffer.concat([tpkt, x224, negotiation]);
+};
+
+/**
+ * Picks TLS like a default Windows server without NLA would, the standard RDP security otherwise
+ * @param {null|number} requestedProtocols
+ * @returns {null|number}
+ */
+const selectProtocol = (requestedProtocols) => {
+	if (requestedProtocols === null) return null;
+	if (requestedProtocols & PROTOCOL_SSL) return PROTOCOL_SSL;
+	if (requestedProtocols & PROTOCOL_HYBRID) return PROTOCOL_HYBRID;
+
+	return PROTOCOL_RDP;
+};
+
+/**
+ * @param {number} protocols - Flags of the requested protocols
+ * @returns {Array} - Their names
+ */
+const protocolNames = (protocols) => {
+	let names = [[PROTOCOL_SSL, 'SSL'], [PROTOCOL_HYBRID, 'HYBRID'], [PROTOCOL_RDSTLS, 'RDSTLS'], [PROTOCOL_HYBRID_EX, 'HYBRID_EX']]
+		.filter(([flag]) => protocols & flag)
+		.map(([flag, name]) => name);
+
+	return names.length !== 0 ? names : ['RDP'];
+};
+
+module.exports = {
+	parseConnectionRequest: parseConnectionRequest,
+	connectionConfirm: connectionConfirm,
+	selectProtocol: selectProtocol,
+	protocolNames: protocolNames
+};
@@ -0,0 +1,37 @@
+"use strict";
+
+const config = require('./config');
+
+// Fields shown in place of the redacted ones, e.g. the payload decoded by `/api/event` or the `user:password` pairs
+const derivedFields = {
+	'password': ['credentials'],
+	'request_headers': ['payload_text', 'decoded']
+};
+
+const fields = new Set();
+config.redact_fields.forEach((field) => {
+	fields.add(field);
+	(derivedFields[field] || []).forEach((derived) => fields.add(derived));
+});
+
+/**
+ * Copy of the data going out of the API, the live feed and the stats page with the `redact_fields` replaced,
+ * so that a public dashboard doesn't give away the captured secrets. The database is left intact
+ * @param value - Item, row or any JSON-like structure of them
+ * @returns {*}
+ */
+const redact = (value) => {
+	if (fields.size === 0 || value === null || typeof value !== 'object' || value instanceof Date) return value;
+	if (Array.isArray(value)) return value.map(redact);
+
+	let copy = {};
+	Object.keys(value).forEach((key) => {
+		copy[key] = fields.has(key) && value[key] !== null && value[key] !== undefined ? '[redacted]' : redact(value[key]);
+	});
+
+	return copy;
+};
+
+redact.fields = fields;
+
+module.exports = redact;
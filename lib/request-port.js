@@ -0,0 +1,20 @@
+"use strict";
+
+const config = require('./config');
+
+/**
+ * Port the client connected to. Behind the reverse proxy the local port is the alternative one, so it is taken
+ * from the X-Forwarded-Port header of a trusted proxy (`trusted_proxies`), going by the protocol when the header is missing
+ * @param req
+ * @returns {number}
+ */
+const requestPort = (req) => {
+	if (!config.nginx_reverse_proxy) return req.socket.localPort;
+
+	let forwarded = parseInt(req.headers['x-forwarded-port'], 10);
+	if (forwarded > 0 && forwarded < 65536 && req.app.get('trust proxy fn')(req.socket.remoteAddress, 0)) return forwarded;
+
+	return req.protocol === 'https' ? 443 : 80;
+};
+
+module.exports = requestPort;
@@ -0,0 +1,99 @@
+"use strict";
+
+// Redis Serialization Protocol (RESP)
+// https://redis.io/topics/protocol
+
+const CRLF = '\r\n';
+
+/**
+ * Reads a CRLF terminated line starting at the offset
+ * @param {Buffer} buffer
+ * @param {number} offset
+ */
+const readLine = (buffer, offset) => {
+	let end = buffer.indexOf(CRLF, offset);
+	if (end === -1) return null;
+
+	return {'line': buffer.toString('utf8', offset, end), 'offset': end + 2};
+};
+
+/**
+ * Multi bulk command, e.g. *2\r\n$3\r\nGET\r\n$3\r\nkey\r\n
+ * @param {Buffer} buffer
+ * @param {number} offset
+ */
+const parseArray = (buffer, offset) => {
+	let header = readLine(buffer, offset);
+	if (header === null) return null;
+	let count = parseInt(header.line.substr(1), 10);
+	if (isNaN(count) || count > 1024 * 1024) throw new Error("invalid multibulk length");
+
+	let args = [];
+	offset = header.offset;
+	for (let i = 0; i < count; i++) {
+		let length_line = readLine(buffer, offset);
+		if (length_line === null) return null;
+		if (length_line.line[0] !== '$') throw new Error("expected '$', got '" + length_line.line[0] + "'");
+		let length = parseInt(length_line.line.substr(1), 10);
+		if (isNaN(length) || length < 0 || length > 512 * 1024 * 1024) throw new Error("invalid bulk length");
+		if (buffer.length < length_line.offset + length + 2) return null;
+		args.push(buffer.toString('utf8', length_line.offset, length_line.offset + length));
+		offset = length_line.offset + length + 2;
+	}
+
+	return {'args': args, 'offset': offset};
+};
+
+/**
+ * Inline command, e.g. PING\r\n, as sent by telnet or netcat
+ * @param {Buffer} buffer
+ * @param {number} offset
+ */
+const parseInline = (buffer, offset) => {
+	let end = buffer.indexOf('\n', offset);
+	if (end === -1) return null;
+	let line = buffer.toString('utf8', offset, end).trim();
+
+	return {'args': line.length === 0 ? [] : line.split(/\s+/), 'offset': end + 1};
+};
+
+/**
+ * Parses as many complete commands as available in the buffer
+ * Throws on the protocol errors, just like the real server does
+ * @param {Buffer} buffer
+ * @returns {{commands: Array, rest: Buffer}}
+ */
+const parse = (buffer) => {
+	let commands = [];
+	let offset = 0;
+	while (offset < buffer.length) {
+		let command = buffer[offset] === 0x2a ? parseArray(buffer, offset) : parseInline(buffer, offset);
+		if (command === null) break;
+		if (command.args.length !== 0) commands.push(command.args);
+		offset = command.offset;
+	}
+
+	return {'commands': commands, 'rest': buffer.slice(offset)};
+};
+
+const simpleString = (text) => '+' + text + CRLF;
+
+const error = (text) => '-' + text + CRLF;
+
+const integer = (number) => ':' + number + CRLF;
+
+const bulkString = (text) => {
+	if (text === null || text === undefined) return '$-1' + CRLF;
+	return '$' + Buffer.byteLength(text) + CRLF + text + CRLF;
+};
+
+const array = (items) => '*' + items.length + CRLF + items.map(bulkString).join('');
+
+module.exports = {
+	parse: parse,
+	simpleString: simpleString,
+	error: error,
+	integer: integer,
+	bulkString: bulkString,
+	array: array
+};
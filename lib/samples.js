@@ -0,0 +1,147 @@
+"use strict";
+
+const fs = require('fs');
+const https = require('https');
+const logger = require('./logger');
+const helper = require('./helper');
+
+// VirusTotal's answer for the files it has never seen, which are looked up again after a day as they may have been submitted since
+const NOT_FOUND = 'virustotal: not found';
+const NOT_FOUND_RECHECK = 24 * 3600 * 1000;
+
+/**
+ * Files uploaded over FTP and SFTP, de-duplicated by their SHA-256 in the `sample` table
+ * The new ones are checked against the `blocklist_file` and, with an API key, against VirusTotal (at most
+ * `requests_per_minute`). The result is kept with the sample, so the same file is looked up only once (unless not found)
+ */
+class SampleTracker {
+	/**
+	 * @param options - `samples` section of the config
+	 */
+	constructor(options) {
+		this.options = options;
+		this.blocklist = SampleTracker.loadBlocklist(options.blocklist_file);
+		this.minute = 0;
+		this.requests = 0;
+	}
+
+	/**
+	 * Blocklist file, one SHA-256 per line optionally followed by the name of the malware
+	 * @param {string} file
+	 * @returns {Map} - Hash to the name
+	 */
+	static loadBlocklist(file) {
+		let blocklist = new Map();
+		if (!file) return blocklist;
+		try {
+			fs.readFileSync(file, 'utf8').split(/\r?\n/).forEach((line) => {
+				let match = line.trim().match(/^([0-9a-f]{64})\b\s*(.*)$/i);
+				if (match) blocklist.set(match[1].toLowerCase(), match[2] || 'blocklisted');
+			});
+		} catch (err) {
+			logger.warning('Cannot read the `samples.blocklist_file` ' + file + '. Error Code: ' + err.code);
+		}
+
+		return blocklist;
+	}
+
+	/**
+	 * @param item - Request with the `uploads` of the session
+	 */
+	record(item) {
+		item.uploads.forEach((upload) => {
+			let sample = [upload.sha256, upload.size, upload.path.substr(0, 255), item.service, item.ip, new Date(item.timestamp), new Date(item.timestamp), 1];
+			helper.mysqlPool.query('INSERT INTO sample (sha256, size, file_name, service, first_ip, first_seen, last_seen, uploads) VALUES (?) ON DUPLICATE KEY UPDATE uploads = uploads + 1, last_seen = VALUES(last_seen)', [sample], (error) => {
+				if (error) return;
+				helper.mysqlPool.query('SELECT detection, checked_at FROM sample WHERE sha256 = ?', [upload.sha256], (error, rows) => {
+					// Looked up already, or left for the next upload of the same file when the lookup wasn't possible
+					if (error || rows.length === 0 || !SampleTracker.isDue(rows[0]['detection'], rows[0]['checked_at'])) return;
+					this.check(upload.sha256).then((detection) => {
+						if (detection === null) return;
+						helper.mysqlPool.query('UPDATE sample SET detection = ?, checked_at = NOW() WHERE sha256 = ?', [detection, upload.sha256]);
+						if (SampleTracker.isMalicious(detection)) logger.warning('Known malware uploaded by ' + item.ip + ' over ' + item.service + ': ' + upload.path + ' (' + detection + ')');
+					});
+				});
+			});
+		});
+	}
+
+	/**
+	 * @param {null|string} detection - Result of the previous lookup
+	 * @param {null|Date} checked_at
+	 * @returns {boolean} - Whether the sample is to be looked up (again)
+	 */
+	static isDue(detection, checked_at) {
+		if (detection === null) return true;
+
+		return detection === NOT_FOUND && (checked_at === null || Date.now() - new Date(checked_at).getTime() > NOT_FOUND_RECHECK);
+	}
+
+	/**
+	 * @param {string} detection - e.g. `blocklist: Mirai` or `virustotal: 12/70`
+	 * @returns {boolean} - Whether it is on the blocklist or flagged by at least one of VirusTotal's engines
+	 */
+	static isMalicious(detection) {
+		if (detection.startsWith('blocklist: ')) return true;
+		let match = /^virustotal: (\d+)\/\d+$/.exec(detection);
+
+		return match !== null && Number(match[1]) > 0;
+	}
+
+	/**
+	 * @param {string} sha256
+	 * @returns {Promise} - Resolves with the detection, null when it couldn't be looked up
+	 */
+	check(sha256) {
+		if (this.blocklist.has(sha256)) return Promise.resolve('blocklist: ' + this.blocklist.get(sha256));
+		if (!this.options.virustotal_api_key) return Promise.resolve(null);
+
+		let minute = Math.floor(Date.now() / 60000);
+		if (minute !== this.minute) {
+			this.minute = minute;
+			this.requests = 0;
+		}
+		if (this.requests >= this.options.requests_per_minute) return Promise.resolve(null);
+		this.requests++;
+
+		return new Promise((resolve) => {
+			let req = https.get({
+				'hostname': 'www.virustotal.com',
+				'path': '/api/v3/files/' + sha256,
+				'headers': {'x-apikey': this.options.virustotal_api_key, 'Accept': 'application/json'},
+				'timeout': this.options.timeout * 1000
+			}, (res) => {
+				if (res.statusCode === 404) {
+					res.resume();
+					return resolve(NOT_FOUND);
+				}
+				if (res.statusCode !== 200) {
+					res.resume();
+					logger.warning('VirusTotal lookup of ' + sha256 + ' failed. HTTP Status: ' + res.statusCode);
+					return resolve(null);
+				}
+				let body = '';
+				res.setEncoding('utf8');
+				res.on('data', (chunk) => { body+= chunk; });
+				res.on('end', () => {
+					try {
+						let stats = JSON.parse(body)['data']['attributes']['last_analysis_stats'];
+						let engines = Object.keys(stats).reduce((total, key) => total + stats[key], 0);
+						resolve('virustotal: ' + stats['malicious'] + '/' + engines);
+					} catch (err) {
+						resolve(null);
+					}
+				});
+			});
+			req.on('timeout', () => {
+				req.abort();
+			});
+			req.on('error', (err) => {
+				logger.warning('Cannot reach VirusTotal. Error Code: ' + (err.code || err.message));
+				resolve(null);
+			});
+		});
+	}
+}
+
+module.exports = SampleTracker;
@@ -0,0 +1,24 @@
+"use strict";
+
+const config = require('./config');
+const cidr = require('./cidr');
+
+// Research scanners (Shodan, Censys, Shadowserver, etc.) are recorded as usual, but tagged, so they can be filtered out
+const scanners = config.known_scanners.map((scanner) => ({
+	'name': scanner.name,
+	'networks': cidr.ranges(scanner.networks.map(cidr.parse))
+}));
+
+/**
+ * @param {string} ip
+ * @returns {string|null} - Name of the known scanner the IP address belongs to
+ */
+const match = (ip) => {
+	let scanner = scanners.find((scanner) => cidr.contains(scanner.networks, ip));
+
+	return scanner ? scanner.name : null;
+};
+
+module.exports = {
+	match: match
+};
@@ -0,0 +1,119 @@
+"use strict";
+
+// Just enough of SOCKS4/4a and SOCKS5 to learn where the client wanted to be proxied to
+// https://www.openssh.com/txt/socks4.protocol, https://tools.ietf.org/html/rfc1928, https://tools.ietf.org/html/rfc1929
+
+const METHOD_NO_AUTH = 0x00;
+const METHOD_USERNAME_PASSWORD = 0x02;
+const METHOD_NONE_ACCEPTABLE = 0xff;
+
+const ATYP_IPV4 = 0x01;
+const ATYP_DOMAIN = 0x03;
+const ATYP_IPV6 = 0x04;
+
+const commands = {0x01: 'CONNECT', 0x02: 'BIND', 0x03: 'UDP ASSOCIATE'};
+
+/**
+ * SOCKS4 request, the destination is sent right away. SOCKS4a sends the hostname after the user id instead of the address
+ * @param {Buffer} buffer
+ * @returns {null|false|object} - `null` until the whole request is received, `false` if it is not one
+ */
+const parseSocks4 = (buffer) => {
+	if (buffer.length < 9) return null;
+	if (buffer[0] !== 0x04 || !commands[buffer[1]] || buffer[1] === 0x03) return false;
+	let end = buffer.indexOf(0, 8);
+	if (end === -1) return null;
+	let request = {'command': commands[buffer[1]], 'port': buffer.readUInt16BE(2), 'host': Array.from(buffer.slice(4, 8)).join('.'), 'userid': buffer.toString('utf8', 8, end)};
+	if (buffer[4] === 0 && buffer[5] === 0 && buffer[6] === 0 && buffer[7] !== 0) {
+		let host_end = buffer.indexOf(0, end + 1);
+		if (host_end === -1) return null;
+		request.host = buffer.toString('utf8', end + 1, host_end);
+	}
+
+	return request;
+};
+
+/**
+ * SOCKS5 greeting with the authentication methods offered by the client
+ * @param {Buffer} buffer
+ * @returns {null|false|object} - The `methods` and the `length` of the greeting
+ */
+const parseGreeting = (buffer) => {
+	if (buffer.length < 2) return null;
+	if (buffer[0] !== 0x05 || buffer[1] === 0) return false;
+	if (buffer.length < 2 + buffer[1]) return null;
+
+	return {'methods': Array.from(buffer.slice(2, 2 + buffer[1])), 'length': 2 + buffer[1]};
+};
+
+/**
+ * Username/password sub-negotiation
+ * @param {Buffer} buffer
+ * @returns {null|false|object}
+ */
+const parseAuth = (buffer) => {
+	if (buffer.length < 2) return null;
+	if (buffer[0] !== 0x01) return false;
+	let password_at = 2 + buffer[1];
+	if (buffer.length < password_at + 1 || buffer.length < password_at + 1 + buffer[password_at]) return null;
+	let end = password_at + 1 + buffer[password_at];
+
+	return {'username': buffer.toString('utf8', 2, password_at), 'password': buffer.toString('utf8', password_at + 1, end), 'length': end};
+};
+
+/**
+ * SOCKS5 request with the destination
+ * @param {Buffer} buffer
+ * @returns {null|false|object}
+ */
+const parseRequest = (buffer) => {
+	if (buffer.length < 5) return null;
+	if (buffer[0] !== 0x05 || !commands[buffer[1]]) return false;
+	let host;
+	let port_at;
+	if (buffer[3] === ATYP_IPV4) {
+		if (buffer.length < 10) return null;
+		host = Array.from(buffer.slice(4, 8)).join('.');
+		port_at = 8;
+	}
+	else if (buffer[3] === ATYP_DOMAIN) {
+		port_at = 5 + buffer[4];
+		if (buffer.length < port_at + 2) return null;
+		host = buffer.toString('utf8', 5, port_at);
+	}
+	else if (buffer[3] === ATYP_IPV6) {
+		if (buffer.length < 22) return null;
+		host = '[' + buffer.slice(4, 20).toString('hex').match(/.{4}/g).join(':') + ']';
+		port_at = 20;
+	}
+	else return false;
+
+	return {'command': commands[buffer[1]], 'host': host, 'port': buffer.readUInt16BE(port_at)};
+};
+
+/**
+ * Picks no authentication when offered, so that the client gets to the request sooner, the username/password otherwise
+ * @param {Array} methods
+ * @returns {number}
+ */
+const selectMethod = (methods) => {
+	if (methods.includes(METHOD_NO_AUTH)) return METHOD_NO_AUTH;
+	if (methods.includes(METHOD_USERNAME_PASSWORD)) return METHOD_USERNAME_PASSWORD;
+
+	return METHOD_NONE_ACCEPTABLE;
+};
+
+module.exports = {
+	METHOD_USERNAME_PASSWORD: METHOD_USERNAME_PASSWORD,
+	METHOD_NONE_ACCEPTABLE: METHOD_NONE_ACCEPTABLE,
+	parseSocks4: parseSocks4,
+	parseGreeting: parseGreeting,
+	parseAuth: parseAuth,
+	parseRequest: parseRequest,
+	selectMethod: selectMethod,
+	// Request rejected or failed
+	socks4Refused: Buffer.from([0x00, 0x5b, 0, 0, 0, 0, 0, 0]),
+	// Connection refused
+	socks5Refused: Buffer.from([0x05, 0x05, 0x00, ATYP_IPV4, 0, 0, 0, 0, 0, 0]),
+	authSucceeded: Buffer.from([0x01, 0x00])
+};
@@ -0,0 +1,117 @@
+"use strict";
+
+const crypto = require('crypto');
+const fs = require('fs');
+const logger = require('./logger');
+
+// OpenSSH names of the EC curves, as JWK names them
+const curves = {'P-256': 'nistp256', 'P-384': 'nistp384', 'P-521': 'nistp521'};
+
+/**
+ * @param {Buffer} buffer
+ * @returns {Buffer} - SSH `string`, length prefixed
+ */
+const sshString = (buffer) => {
+	let length = Buffer.alloc(4);
+	length.writeUInt32BE(buffer.length);
+	return Buffer.concat([length, buffer]);
+};
+
+/**
+ * @param {string} value - Base64url of an unsigned big-endian integer
+ * @returns {Buffer} - SSH `mpint`, with a leading zero byte when the high bit is set
+ */
+const sshMpint = (value) => {
+	let buffer = Buffer.from(value, 'base64');
+	return sshString(buffer[0] & 0x80 ? Buffer.concat([Buffer.alloc(1), buffer]) : buffer);
+};
+
+/**
+ * @param {string|Buffer} key - PEM private key
+ * @returns {string} - Key type as OpenSSH shows it, e.g. `ssh-rsa`
+ */
+const keyType = (key) => {
+	let jwk = crypto.createPublicKey(key).export({'format': 'jwk'});
+	return jwk.kty === 'RSA' ? 'ssh-rsa' : 'ecdsa-sha2-' + curves[jwk.crv];
+};
+
+/**
+ * Same fingerprint as `ssh-keygen -l` and the clients' "The authenticity of host ... can't be established" show
+ * @param {string|Buffer} key - PEM private key
+ * @returns {string} - e.g. `SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8`
+ */
+const fingerprint = (key) => {
+	let jwk = crypto.createPublicKey(key).export({'format': 'jwk'});
+	let type = keyType(key);
+	let blob = jwk.kty === 'RSA'
+		? Buffer.concat([sshString(Buffer.from(type)), sshMpint(jwk.e), sshMpint(jwk.n)])
+		: Buffer.concat([sshString(Buffer.from(type)), sshString(Buffer.from(curves[jwk.crv])), sshString(Buffer.concat([Buffer.from([4]), Buffer.from(jwk.x, 'base64'), Buffer.from(jwk.y, 'base64')]))]);
+
+	return 'SHA256:' + crypto.createHash('sha256').update(blob).digest('base64').replace(/=+$/, '');
+};
+
+/**
+ * ssh2 reads the PKCS#1 (RSA) and SEC1 (ECDSA) PEM keys, but not the newer OpenSSH format of Ed25519
+ * @param {string} type - `rsa` or `ecdsa`
+ * @returns {string} - PEM private key
+ */
+const generate = (type) => {
+	if (type === 'ecdsa') {
+		return crypto.generateKeyPairSync('ec', {'namedCurve': 'prime256v1', 'privateKeyEncoding': {'type': 'sec1', 'format': 'pem'}, 'publicKeyEncoding': {'type': 'spki', 'format': 'pem'}}).privateKey;
+	}
+
+	return crypto.generateKeyPairSync('rsa', {'modulusLength': 2048, 'privateKeyEncoding': {'type': 'pkcs1', 'format': 'pem'}, 'publicKeyEncoding': {'type': 'spki', 'format': 'pem'}}).privateKey;
+};
+
+let hostKeys = null;
+
+/**
+ * @param {string|Buffer} key - PEM private key
+ * @returns {string} - Key type and fingerprint for the log, e.g. `ssh-rsa SHA256:...`
+ */
+const describe = (key) => {
+	try {
+		return keyType(key) + ' ' + fingerprint(key);
+	} catch (err) {
+		return 'fingerprint unknown: ' + err.message;
+	}
+};
+
+/**
+ * Keys of `ssh_host_keys`, read once and shared by all the SSH ports
+ * A key which is missing or not readable is replaced by a temporary one, the fingerprint then changes on every restart
+ * A key which is read but cannot be fingerprinted (e.g. a format `crypto` doesn't know) is still served as it is
+ * @param {Array} paths
+ * @returns {Array} - PEM private keys
+ */
+const load = (paths) => {
+	if (hostKeys !== null) return hostKeys;
+	hostKeys = paths.map((path) => {
+		let key;
+		try {
+			key = fs.readFileSync(path);
+		} catch (err) {
+			if (!['ENOENT', 'EACCES'].includes(err.code)) throw err;
+			key = generate('rsa');
+			logger.warning('Cannot read the SSH host key ' + path + ' (' + err.message + '), using a temporary one (' + describe(key) + '). '
+				+ 'The fingerprint will change on the next start, which gives the honeypot away to the returning clients. Create the key with `node bin/ssh-host-keys.js --regenerate`.');
+			return key;
+		}
+		try {
+			logger.info('SSH host key ', path + ' (' + keyType(key) + ' ' + fingerprint(key) + ')');
+		} catch (err) {
+			logger.warning('Cannot fingerprint the SSH host key ' + path + ' (' + err.message + '), it is served as it is.');
+		}
+
+		return key;
+	});
+
+	return hostKeys;
+};
+
+module.exports = {
+	fingerprint: fingerprint,
+	keyType: keyType,
+	generate: generate,
+	load: load
+};
@@ -0,0 +1,119 @@
+"use strict";
+
+const dgram = require('dgram');
+const net = require('net');
+const os = require('os');
+const logger = require('./logger');
+
+const MONTHS = ['Jan', 'Feb', 'Mar', 'Apr', 'May', 'Jun', 'Jul', 'Aug', 'Sep', 'Oct', 'Nov', 'Dec'];
+const RECONNECT_INTERVAL = 5000;
+const SEVERITY_INFO = 6;
+
+/**
+ * Sends the requests to a syslog collector (SIEM) in ArcSight Common Event Format, over UDP or TCP
+ * e.g. <134>Oct 16 10:39:44 honeypot CEF:0|Honeypot|honeypot|1.0|ssh|Login attempt|5|rt=1539686384000 src=1.2.3.4 app=ssh suser=root ...
+ * The messages that cannot be sent, e.g. while the TCP connection to the collector is down, go to the delivery queue
+ */
+class SyslogSender {
+	/**
+	 * @param options - `syslog` section of the config
+	 * @param deliveryQueue - @see DeliveryQueue
+	 */
+	constructor(options, deliveryQueue) {
+		this.options = options;
+		this.hostname = os.hostname();
+		this.deliveryQueue = deliveryQueue;
+		deliveryQueue.register('syslog', (message, callback) => { this.deliver(message, callback); });
+		if (options.protocol === 'tcp') this.connect();
+		else this.udpSocket = dgram.createSocket(net.isIPv6(options.host) ? 'udp6' : 'udp4').on('error', (err) => {
+			logger.warning('Cannot send to the syslog collector. Error Code: ' + err.code);
+		});
+	}
+
+	connect() {
+		this.tcpSocket = net.connect(this.options.port, this.options.host, () => {
+			this.deliveryQueue.retryNow('syslog');
+		});
+		this.tcpSocket.on('error', (err) => {
+			logger.warning('Cannot connect to the syslog collector. Error Code: ' + err.code);
+		});
+		this.tcpSocket.on('close', () => {
+			this.tcpSocket = null;
+			setTimeout(() => { this.connect(); }, RECONNECT_INTERVAL);
+		});
+	}
+
+	/**
+	 * @param item - Request as emitted by the services
+	 */
+	send(item) {
+		let message = this.header(item.timestamp) + this.format(item);
+		this.deliver(message, (err) => {
+			if (err) this.deliveryQueue.add('syslog', message, err);
+		});
+	}
+
+	/**
+	 * @param {string} message - Formatted with the header
+	 * @param {function} callback - Called with the error, if the message could not be sent
+	 */
+	deliver(message, callback) {
+		if (this.options.protocol !== 'tcp') {
+			let buffer = Buffer.from(message);
+			return this.udpSocket.send(buffer, 0, buffer.length, this.options.port, this.options.host, (err) => { callback(err); });
+		}
+		if (!this.tcpSocket || this.tcpSocket.connecting) return callback(new Error('Not connected'));
+		this.tcpSocket.write(message + '\n', (err) => { callback(err); });
+	}
+
+	/**
+	 * RFC 3164 header
+	 * @param {number} timestamp
+	 */
+	header(timestamp) {
+		let date = new Date(timestamp || Date.now());
+		let day = date.getDate() < 10 ? ' ' + date.getDate() : date.getDate();
+		let time = [date.getHours(), date.getMinutes(), date.getSeconds()].map((value) => value < 10 ? '0' + value : value).join(':');
+
+		return '<' + (this.options.facility * 8 + SEVERITY_INFO) + '>' + MONTHS[date.getMonth()] + ' ' + day + ' ' + time + ' ' + this.hostname + ' ';
+	}
+
+	/**
+	 * @param item
+	 * @returns {string} - CEF record
+	 */
+	format(item) {
+		let login_attempt = item.username !== undefined;
+		let extensions = {
+			'rt': item.timestamp || Date.now(),
+			// CEF has a separate field for the IPv6 source address
+			'src': net.isIPv6(item.ip) ? undefined : item.ip,
+			'c6a2': net.isIPv6(item.ip) ? item.ip : undefined,
+			'c6a2Label': net.isIPv6(item.ip) ? 'sourceIPv6Address' : undefined,
+			'dpt': item.port,
+			'app': item.service,
+			'msg': item.request,
+			'request': item.http_request_path,
+			'suser': item.username,
+			'cs1Label': item.password !== undefined ? 'password' : undefined,
+			'cs1': item.password,
+			'cn1Label': item.duration_ms !== undefined ? 'durationMs' : undefined,
+			'cn1': item.duration_ms
+		};
+		let extension = Object.keys(extensions).filter((key) => extensions[key] !== undefined && extensions[key] !== null).map((key) => {
+			return key + '=' + String(extensions[key]).replace(/\\/g, '\\\\').replace(/=/g, '\\=').replace(/\r?\n/g, '\\n');
+		}).join(' ');
+
+		return [
+			'CEF:0',
+			'Honeypot',
+			'honeypot',
+			'1.0',
+			item.service,
+			login_attempt ? 'Login attempt' : (item.http_request_path !== undefined ? 'HTTP request' : 'Connection'),
+			login_attempt ? 5 : 3
+		].map((field) => String(field).replace(/\\/g, '\\\\').replace(/\|/g, '\\|')).join('|') + '|' + extension;
+	}
+}
+
+module.exports = SyslogSender;
@@ -136,5 +136,8 @@ const list = {
 
 list[27017] = 'MongoDB';
 list[11211] = 'memcached';
+list[6379] = 'redis';
+list[1080] = 'socks';
+list[2375] = 'docker';
 
 module.exports = list;
\ No newline at end of file
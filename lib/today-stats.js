@@ -0,0 +1,86 @@
+"use strict";
+
+const helper = require('./helper');
+
+// Seconds between the refreshes, the counts are at most that much behind
+const REFRESH_INTERVAL = 60;
+
+/**
+ * Requests since the midnight, kept up to date incrementally: every refresh only reads the rows added since the previous one,
+ * so that the cost doesn't grow as the day's rows pile up
+ * The past hour is counted from the midnight at the latest, just after it the number is lower than it should be
+ */
+class TodayStats {
+	constructor() {
+		this.reset();
+		this.refreshing = null;
+		setInterval(() => { this.refresh().catch(() => {}); }, REFRESH_INTERVAL * 1000);
+	}
+
+	reset() {
+		let today = new Date();
+		today.setHours(0, 0, 0, 0);
+		this.day = today;
+		this.last_id = 0;
+		this.total = 0;
+		this.ips = new Set();
+		// Requests per minute of the past hour
+		this.minutes = new Map();
+		this.updated_at = null;
+	}
+
+	/**
+	 * @returns {Promise}
+	 */
+	refresh() {
+		if (this.refreshing !== null) return this.refreshing;
+		let today = new Date();
+		today.setHours(0, 0, 0, 0);
+		if (today.getTime() !== this.day.getTime()) this.reset();
+
+		let sql = "SELECT ip, DATE_FORMAT(date, '%Y-%m-%d %H:%i:00') AS minute, SUM(count) AS total, MAX(id) AS last_id FROM request WHERE date >= ? AND id > ? GROUP BY ip, minute";
+		this.refreshing = new Promise((resolve, reject) => {
+			helper.mysqlPool.query(sql, [this.day, this.last_id], (error, rows) => {
+				this.refreshing = null;
+				if (error) return reject(error);
+				let hour_ago = Date.now() - 3600 * 1000;
+				rows.forEach((row) => {
+					let total = Number(row['total']);
+					this.total+= total;
+					this.ips.add(row['ip']);
+					this.last_id = Math.max(this.last_id, row['last_id']);
+					let minute = new Date(row['minute']).getTime();
+					if (minute >= hour_ago - 60 * 1000) this.minutes.set(minute, (this.minutes.get(minute) || 0) + total);
+				});
+				this.minutes.forEach((total, minute) => {
+					if (minute < hour_ago - 60 * 1000) this.minutes.delete(minute);
+				});
+				this.updated_at = Date.now();
+				resolve();
+			});
+		});
+
+		return this.refreshing;
+	}
+
+	/**
+	 * @returns {Promise} - Resolves with the latest snapshot, waits for the first refresh if there was none yet
+	 */
+	snapshot() {
+		return (this.updated_at === null ? this.refresh() : Promise.resolve()).then(() => {
+			let hour_ago = Date.now() - 3600 * 1000;
+			let last_hour = 0;
+			this.minutes.forEach((total, minute) => {
+				if (minute >= hour_ago) last_hour+= total;
+			});
+			return {
+				'today': this.total,
+				'last_hour': last_hour,
+				'unique_ips_today': this.ips.size,
+				'updated_at': this.updated_at
+			};
+		});
+	}
+}
+
+module.exports = new TodayStats();
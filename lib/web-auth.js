@@ -0,0 +1,49 @@
+"use strict";
+
+const crypto = require('crypto');
+const config = require('./config');
+
+/**
+ * @param {string} a
+ * @param {string} b
+ * @returns {boolean} - Constant time comparison, so the token cannot be guessed byte by byte
+ */
+const safeEqual = (a, b) => {
+	let hash_a = crypto.createHash('sha256').update(String(a)).digest();
+	let hash_b = crypto.createHash('sha256').update(String(b)).digest();
+
+	return crypto.timingSafeEqual(hash_a, hash_b);
+};
+
+/**
+ * The token is accepted either as a Bearer token or as the Basic auth password (with any username),
+ * so that the dashboard can be opened in a browser
+ * @param {string} authorization - Value of the Authorization header
+ * @returns {boolean}
+ */
+const isAuthorized = (authorization) => {
+	if (!config.dashboard_auth_token) return true;
+	if (typeof authorization !== 'string') return false;
+
+	let [scheme, credentials] = authorization.split(' ');
+	if (/^bearer$/i.test(scheme)) return safeEqual(credentials, config.dashboard_auth_token);
+	if (/^basic$/i.test(scheme)) {
+		let decoded = Buffer.from(credentials || '', 'base64').toString();
+		return safeEqual(decoded.substr(decoded.indexOf(':') + 1), config.dashboard_auth_token);
+	}
+
+	return false;
+};
+
+/* Express middleware protecting the dashboard, the stats and the API */
+const middleware = (req, res, next) => {
+	if (isAuthorized(req.headers['authorization'])) return next();
+
+	res.set('WWW-Authenticate', 'Basic realm="Honeypot"');
+	res.sendStatus(401);
+};
+
+module.exports = {
+	isAuthorized: isAuthorized,
+	middleware: middleware
+};
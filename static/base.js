@@ -5,6 +5,7 @@ const app = {
 	requests_total: 0,
 	requests_since_launch: 0,
 	last_ip_address: null,
+	last_event_id: 0,
 
 	init: function() {
 		app.modal = new tingle.modal();
@@ -33,7 +34,9 @@ const app = {
 	},
 
 	renderRow: function (data) {
-		if (!data || data.request.length === 0) return;
+		if (!data) return;
+		if (data.event_id > app.last_event_id) app.last_event_id = data.event_id;
+		if (data.request.length === 0) return;
 
 		let current_date = new Date();
 		current_date.setTime(data.timestamp);
@@ -85,13 +88,13 @@ const app = {
 
 		let html = '';
 		for (let i in data) {
-			html+= '<div>' + app.escapeHtml(data[i]['username']) + ':' + app.escapeHtml(data[i]['password']) + '</div>';
+			html+= '<div>' + app.escapeHtml(data[i]['username']) + ':' + app.escapeHtml(data[i]['password'] || '') + '</div>';
 		}
 		$('#recent_credential').html(html);
 	},
 
 	updateCredentials: function(data) {
-		let html = '<div>' + app.escapeHtml(data['username']) + ':' + app.escapeHtml(data['password']) + '</div>';
+		let html = '<div>' + app.escapeHtml(data['username']) + ':' + app.escapeHtml(data['password'] || '') + '</div>';
 		$('#recent_credential').prepend(html);
 		$('#recent_credential div:last').remove();
 	},
@@ -139,10 +142,21 @@ const favicon = {
 };
 
 let socket = io();
+// Resuming after the last item seen rather than getting the recent ones again
+socket.io.on('reconnect_attempt', function() {
+	socket.io.opts.query = {'last_event_id': app.last_event_id};
+});
 
 socket.on('init', function(init_data) {
 	app.requests_total = init_data['total_requests_number'];
-	app.renderData(init_data['data']);
+	let data = init_data['data'] || [];
+	// Some of the items missed while disconnected are gone, so the recent ones are sent instead, less those shown already
+	if (!init_data['resumed']) {
+		data = data.filter(function(item) {
+			return item.event_id > app.last_event_id;
+		});
+	}
+	app.renderData(data);
 	app.renderCredentials(init_data['recent_credentials']);
 	app.pauseLoadingSpinner();
 });
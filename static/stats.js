@@ -10,9 +10,46 @@ const Stats = {
 		google.charts.setOnLoadCallback(Stats.drawServicesTable);
 		google.charts.setOnLoadCallback(Stats.drawCredentialsTable);
 		google.charts.setOnLoadCallback(Stats.drawRequestsTable);
+		google.charts.setOnLoadCallback(Stats.drawIpsTable);
+		google.charts.setOnLoadCallback(Stats.drawBotnetsTable);
+		google.charts.setOnLoadCallback(Stats.drawPayloadsTable);
 		window.setTimeout(() =>{
 			Stats.drawServices();
 		}, 0);
+		$.getJSON('/api/timeseries', {'hours': 48, 'bucket': 'hour'}, Stats.drawTimeseries);
+	},
+
+	drawTimeseries: function(timeseries) {
+		let series_data = [];
+		for (let i = 0; i < timeseries.data.length; i++) {
+			series_data.push([timeseries.data[i]['timestamp'], timeseries.data[i]['total']]);
+		}
+		Highcharts.chart('timeseries', {
+			credits: false,
+			chart: {
+				type: 'line'
+			},
+			title: false,
+			time: {
+				useUTC: false
+			},
+			xAxis: {
+				type: 'datetime'
+			},
+			yAxis: {
+				title: {
+					text: 'Requests per hour'
+				},
+				min: 0
+			},
+			legend: {
+				enabled: false
+			},
+			series: [{
+				name: 'Requests',
+				data: series_data
+			}]
+		});
 	},
 
 	drawServices: function() {
@@ -67,9 +104,10 @@ const Stats = {
 		let data = new google.visualization.DataTable();
 		data.addColumn('string', 'Service');
 		data.addColumn('number', 'Requests #');
+		data.addColumn('number', 'Avg. Session, s');
 		for (let i = 0; i < Stats.data.services.length; i++) {
 			let row = Stats.data.services[i];
-			data.addRow([row['service'], row['total']]);
+			data.addRow([row['service'], row['total'], {v: row['avg_duration_ms'], f: (row['avg_duration_ms'] / 1000).toFixed(1)}]);
 		}
 
 		let table = new google.visualization.Table(document.getElementById('services-table'));
@@ -102,6 +140,73 @@ const Stats = {
 		table.draw(data, options);
 	},
 
+	drawIpsTable: function() {
+		if (!Stats.data.ips) return;
+		let data = new google.visualization.DataTable();
+		data.addColumn('string', 'IP Address');
+		data.addColumn('number', '#');
+		data.addColumn('datetime', 'First Seen');
+		data.addColumn('datetime', 'Last Seen');
+		for (let i = 0; i < Stats.data.ips.length; i++) {
+			let row = Stats.data.ips[i];
+			let first_seen = new Date(row['first_seen']);
+			// Flagging the addresses which showed up only during the past day
+			let ip = Date.now() - first_seen.getTime() < 24 * 3600 * 1000 ? row['ip'] + ' (new)' : row['ip'];
+			data.addRow([ip, row['total'], first_seen, new Date(row['last_seen'])]);
+		}
+
+		let table = new google.visualization.Table(document.getElementById('ips-table'));
+
+		let options = {
+			showRowNumber: true,
+			width: '100%',
+			height: '100%'
+		};
+		table.draw(data, options);
+	},
+
+	drawBotnetsTable: function() {
+		if (!Stats.data.botnets) return;
+		let data = new google.visualization.DataTable();
+		data.addColumn('string', 'Botnet');
+		data.addColumn('number', 'Sessions #');
+		data.addColumn('number', 'IP Addresses #');
+		for (let i = 0; i < Stats.data.botnets.length; i++) {
+			let row = Stats.data.botnets[i];
+			data.addRow([row['botnet_family'], row['total'], row['ips']]);
+		}
+
+		let table = new google.visualization.Table(document.getElementById('botnets-table'));
+
+		let options = {
+			showRowNumber: true,
+			width: '100%',
+			height: '100%'
+		};
+		table.draw(data, options);
+	},
+
+	drawPayloadsTable: function() {
+		if (!Stats.data.payloads) return;
+		let data = new google.visualization.DataTable();
+		data.addColumn('string', 'Payload');
+		data.addColumn('number', 'Requests #');
+		data.addColumn('number', 'IP Addresses #');
+		for (let i = 0; i < Stats.data.payloads.length; i++) {
+			let row = Stats.data.payloads[i];
+			data.addRow([row['payload_kind'], row['total'], row['ips']]);
+		}
+
+		let table = new google.visualization.Table(document.getElementById('payloads-table'));
+
+		let options = {
+			showRowNumber: true,
+			width: '100%',
+			height: '100%'
+		};
+		table.draw(data, options);
+	},
+
 	drawCredentialsTable: function() {
 		let data = new google.visualization.DataTable();
 		data.addColumn('string', 'Username/Password');
@@ -0,0 +1,62 @@
+"use strict";
+
+const assert = require('assert');
+const cidr = require('./../lib/cidr');
+const helper = require('./../lib/helper');
+
+module.exports = {
+	'IPv4-mapped IPv6 addresses are normalized to IPv4': () => {
+		assert.strictEqual(cidr.normalize('::ffff:1.2.3.4'), '1.2.3.4');
+		assert.strictEqual(cidr.normalize('::FFFF:C000:0201'), '192.0.2.1');
+	},
+	'IPv6 addresses are compressed and lowercase': () => {
+		assert.strictEqual(cidr.normalize('2001:0DB8:0000:0000:0000:0000:0000:0001'), '2001:db8::1');
+		assert.strictEqual(cidr.normalize('2001:db8:0:0:1:0:0:1'), '2001:db8::1:0:0:1');
+		assert.strictEqual(cidr.normalize('2001:db8::'), '2001:db8::');
+		assert.strictEqual(cidr.normalize('::1'), '::1');
+		assert.strictEqual(cidr.normalize('::'), '::');
+	},
+	'the zone of a link-local address is dropped': () => {
+		assert.strictEqual(cidr.normalize('fe80::1%eth0'), 'fe80::1');
+	},
+	'IPv4 addresses and anything else are left as they are': () => {
+		assert.strictEqual(cidr.normalize('192.0.2.1'), '192.0.2.1');
+		assert.strictEqual(cidr.normalize('not an ip'), 'not an ip');
+	},
+	'private IPv4 addresses, mapped to IPv6 too': () => {
+		['10.1.2.3', '172.16.0.1', '192.168.1.1', '100.64.0.1', '127.0.0.1', '::ffff:10.0.0.1'].forEach((ip) => {
+			assert.strictEqual(helper.isPrivateIp(ip), true, ip);
+		});
+		['8.8.8.8', '::ffff:8.8.8.8', '172.32.0.1'].forEach((ip) => {
+			assert.strictEqual(helper.isPrivateIp(ip), false, ip);
+		});
+	},
+	'link-local IPv6 addresses (fe80::/10)': () => {
+		['fe80::1', 'fe80::1%eth0', 'febf::1'].forEach((ip) => {
+			assert.strictEqual(helper.isPrivateIp(ip), true, ip);
+		});
+		assert.strictEqual(helper.isPrivateIp('fec0::1'), false);
+	},
+	'unique local IPv6 addresses (fc00::/7)': () => {
+		['fc00::1', 'fd12:3456::1'].forEach((ip) => {
+			assert.strictEqual(helper.isPrivateIp(ip), true, ip);
+		});
+	},
+	'addresses are looked up in the sorted and merged ranges': () => {
+		let list = cidr.ranges(['192.0.2.0/24', '10.0.0.0/8', '10.1.0.0/16', '198.51.100.7', '192.0.3.0/24', '2001:db8::/32'].map(cidr.parse));
+		assert.strictEqual(list[4].length, 3);
+		['10.0.0.0', '10.255.255.255', '10.1.2.3', '192.0.2.1', '192.0.3.255', '198.51.100.7', '2001:db8::1'].forEach((ip) => {
+			assert.strictEqual(cidr.contains(list, ip), true, ip);
+		});
+		['9.255.255.255', '11.0.0.0', '192.0.4.0', '198.51.100.6', '198.51.100.8', '0.0.0.0', '2001:db9::', '::ffff:10.0.0.1', 'not an ip'].forEach((ip) => {
+			assert.strictEqual(cidr.contains(list, ip), false, ip);
+		});
+		assert.strictEqual(cidr.contains(cidr.ranges([]), '10.0.0.1'), false);
+	},
+	'public IPv6 addresses': () => {
+		['2001:4860:4860::8888', '2a03:2880:f12f:83:face:b00c:0:25de'].forEach((ip) => {
+			assert.strictEqual(helper.isPrivateIp(ip), false, ip);
+		});
+		assert.strictEqual(helper.isPrivateIp('::1'), true);
+	}
+};
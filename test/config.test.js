@@ -0,0 +1,76 @@
+"use strict";
+
+const assert = require('assert');
+const config = require('./../lib/config');
+const validate = require('./../lib/config-validator');
+
+/**
+ * @param {string} key - Option, e.g. `persona`
+ * @param changes - Replaced options of the section
+ * @returns {object} - Changes of a nested option, keeping the rest of its section
+ */
+const section = (key, changes) => ({[key]: Object.assign({}, config[key], changes)});
+
+// Description, the options changed in the valid config and a part of the expected error message
+const mistakes = [
+	['server_ip which is not an IP address', {'server_ip': 'localhost'}, '`server_ip`'],
+	['express_js_alternative_port out of range', {'express_js_alternative_port': 70000}, '`express_js_alternative_port`'],
+	['an option which should be a list', {'enabled_ports': 22}, '`enabled_ports` should be a list'],
+	['one of the enabled_ports is not a port', {'enabled_ports': [22, 0]}, '`0` in `enabled_ports`'],
+	['ftp_pasv_range the wrong way round', {'ftp_pasv_range': '50100-50000'}, '`ftp_pasv_range`'],
+	['one of the listen_addresses is not an IP address', {'listen_addresses': ['eth0']}, '`eth0` in `listen_addresses`'],
+	['cors_allowed_origins with a path', {'cors_allowed_origins': ['https://soc.example.com/']}, '`https://soc.example.com/` in `cors_allowed_origins`'],
+	['one of the known_scanners without the networks', {'known_scanners': [{'name': 'Shodan'}]}, '`known_scanners` should have a `name`'],
+	['invalid network of the known_scanners', {'known_scanners': [{'name': 'Shodan', 'networks': ['198.20.69.72/33']}]}, '`known_scanners` Shodan:'],
+	['missing enrichment list', section('enrichment', {'tor_exit_list': '/nonexistent/tor.txt'}), '`enrichment` list /nonexistent/tor.txt'],
+	['no ssh_host_keys', {'ssh_host_keys': []}, '`ssh_host_keys` should list at least one key file'],
+	['negative max_connections_per_ip', {'max_connections_per_ip': -1}, '`max_connections_per_ip`'],
+	['fractional max_ssh_sessions', {'max_ssh_sessions': 1.5}, '`max_ssh_sessions`'],
+	['port_scan.threshold which is not a number', section('port_scan', {'threshold': '10'}), '`port_scan.threshold`'],
+	['port_scan.window of 0', section('port_scan', {'window': 0}), '`port_scan.window`'],
+	['no mysql_connect_retry.attempts', section('mysql_connect_retry', {'attempts': 0}), '`mysql_connect_retry.attempts`'],
+	['negative mysql_connect_retry.max_delay', section('mysql_connect_retry', {'max_delay': -1}), '`mysql_connect_retry.max_delay`'],
+	['negative database_size.warning_bytes', section('database_size', {'warning_bytes': -1}), '`database_size.warning_bytes`'],
+	['database_size.interval of 0', section('database_size', {'interval': 0}), '`database_size.interval`'],
+	['negative http_dedup_window', {'http_dedup_window': -5}, '`http_dedup_window`'],
+	['service timeout of 0', section('timeouts', {'telnet_session': 0}), '`timeouts.telnet_session`'],
+	['limit which is not a number', section('limits', {'capture_bytes': '16k'}), '`limits.capture_bytes`'],
+	['sampling of 0', section('sampling', {'http': 0}), '`sampling.http`'],
+	['negative tarpit delay', section('tarpit', {'telnet': -1}), '`tarpit.telnet`'],
+	['missing samples.blocklist_file', section('samples', {'blocklist_file': '/nonexistent/blocklist.txt'}), '`samples.blocklist_file`'],
+	['samples.requests_per_minute of 0', section('samples', {'requests_per_minute': 0}), '`samples.requests_per_minute`'],
+	['rdap.url over plain HTTP', section('rdap', {'enabled': true, 'url': 'http://rdap.org/ip/'}), '`rdap.url`'],
+	['rdap.timeout of 0', section('rdap', {'enabled': true, 'timeout': 0}), '`rdap.timeout`'],
+	['syslog.port out of range', section('syslog', {'host': '127.0.0.1', 'port': 0}), '`syslog.port`'],
+	['unknown syslog.protocol', section('syslog', {'protocol': 'tls'}), '`syslog.protocol`'],
+	['delivery_queue.max_entries of 0', section('delivery_queue', {'max_entries': 0}), '`delivery_queue.max_entries`'],
+	['delivery_queue.retry_interval of 0', section('delivery_queue', {'retry_interval': 0}), '`delivery_queue.retry_interval`'],
+	['emulation.ssh_accept_probability over 1', section('emulation', {'ssh_accept_probability': 2}), '`emulation.ssh_accept_probability`'],
+	['negative emulation.ssh_accept_after', section('emulation', {'ssh_accept_after': -1}), '`emulation.ssh_accept_after`'],
+	['emulation.telnet_max_commands of 0', section('emulation', {'telnet_max_commands': 0}), '`emulation.telnet_max_commands`'],
+	['empty persona.hostname', section('persona', {'hostname': ''}), '`persona.hostname`'],
+	['no persona.users', section('persona', {'users': []}), '`persona.users`'],
+	['admin panel without the leading slash', section('emulation', {'admin_panels': {'phpmyadmin/': 'phpmyadmin'}}), '`emulation.admin_panels` phpmyadmin/ should start with a slash'],
+	['unknown admin panel page', section('emulation', {'admin_panels': {'/admin/': '/nonexistent/panel.html'}}), '/nonexistent/panel.html is neither a built-in page'],
+	['unknown emulation.catch_all', section('emulation', {'catch_all': 'drop'}), '`emulation.catch_all`'],
+	['exploit_signatures without the patterns', {'exploit_signatures': [{'name': 'Log4Shell'}]}, '`exploit_signatures` should have a `name`'],
+	['invalid regular expression of the exploit_signatures', {'exploit_signatures': [{'name': 'Log4Shell', 'patterns': ['\\$\\{jndi:(']}]}, '`exploit_signatures` Log4Shell: \\$\\{jndi:( is not a valid regular expression'],
+	['invalid regular expression of the botnet_signatures', {'botnet_signatures': [{'family': 'Mirai', 'commands': ['[MIRAI']}]}, '`botnet_signatures` Mirai: [MIRAI is not a valid regular expression']
+];
+
+module.exports = {
+	'the template is valid': () => {
+		assert.deepStrictEqual(validate(config), []);
+	},
+	'all the mistakes are reported at once': () => {
+		let errors = validate(Object.assign({}, config, {'max_connections_per_ip': -1}, section('syslog', {'protocol': 'tls'}), section('persona', {'hostname': ''})));
+		assert.strictEqual(errors.length, 3, errors.join('\n'));
+	}
+};
+mistakes.forEach(([description, changes, expected]) => {
+	module.exports[description] = () => {
+		let errors = validate(Object.assign({}, config, changes));
+		assert.strictEqual(errors.length, 1, errors.join('\n'));
+		assert.ok(errors[0].includes(expected), errors[0]);
+	};
+});
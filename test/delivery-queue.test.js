@@ -0,0 +1,72 @@
+"use strict";
+
+const assert = require('assert');
+const helper = require('./../lib/helper');
+const logger = require('./../lib/logger');
+const DeliveryQueue = require('./../lib/delivery-queue');
+
+/**
+ * Runs the case against a database which is down until `database.up` is set, and collects the warnings
+ * @param {function} test - Called with the queue, the fake database and the warnings
+ * @returns {Promise}
+ */
+const withDatabase = (test) => {
+	let database = {'up': false, 'inserted': []};
+	let warnings = [];
+	let query = helper.mysqlPool.query;
+	let warning = logger.warning;
+	let info = logger.info;
+	helper.mysqlPool.query = (sql, params, callback) => {
+		if (!database.up) return setImmediate(() => callback(Object.assign(new Error('connect ECONNREFUSED'), {'code': 'ECONNREFUSED'})));
+		if (sql.startsWith('INSERT')) database.inserted = database.inserted.concat(sql.endsWith('VALUES (?)') ? params : params[0]);
+		setImmediate(() => callback(null, {'affectedRows': 0}));
+	};
+	logger.warning = (message) => warnings.push(message);
+	logger.info = () => {};
+
+	return Promise.resolve().then(() => test(new DeliveryQueue({'max_entries': 2, 'retry_interval': 30, 'max_retry_interval': 3600}), database, warnings)).then(() => {
+		helper.mysqlPool.query = query;
+		logger.warning = warning;
+		logger.info = info;
+	}, (err) => {
+		helper.mysqlPool.query = query;
+		logger.warning = warning;
+		logger.info = info;
+		throw err;
+	});
+};
+
+const tick = () => new Promise((resolve) => setImmediate(resolve));
+
+module.exports = {
+	'the deliveries are kept in memory while the database is down': () => withDatabase((queue, database, warnings) => {
+		['a', 'b', 'c'].forEach((message) => queue.add('syslog', message, new Error('EPIPE')));
+		return tick().then(() => {
+			assert.deepStrictEqual(queue.unsaved.map((delivery) => delivery[1]), ['"b"', '"c"']);
+			assert.strictEqual(queue.lost, 1);
+			assert.strictEqual(warnings.length, 1, warnings.join('\n'));
+		});
+	}),
+	'the dropped deliveries are summarized in one warning': () => withDatabase((queue, database, warnings) => {
+		['a', 'b', 'c', 'd'].forEach((message) => queue.add('syslog', message, new Error('EPIPE')));
+		return tick().then(() => new Promise((resolve) => {
+			queue.flush(() => assert.fail('queued while the database is down'));
+			setTimeout(resolve, 10);
+		})).then(() => {
+			assert.strictEqual(warnings.length, 2, warnings.join('\n'));
+			assert.ok(warnings[1].includes('dropped the 2 oldest'), warnings[1]);
+			assert.strictEqual(queue.unsaved.length, 2);
+		});
+	}),
+	'the deliveries kept in memory are queued once the database is back': () => withDatabase((queue, database) => {
+		['a', 'b'].forEach((message) => queue.add('abuseipdb', message, new Error('ETIMEDOUT')));
+		return tick().then(() => new Promise((resolve) => {
+			database.up = true;
+			queue.flush(resolve);
+		})).then(() => {
+			assert.deepStrictEqual(database.inserted.map((delivery) => delivery[1]), ['"a"', '"b"']);
+			assert.strictEqual(queue.unsaved.length, 0);
+			assert.strictEqual(queue.unavailable, false);
+		});
+	})
+};
@@ -0,0 +1,26 @@
+"use strict";
+
+const assert = require('assert');
+const socketSession = require('./socket-session');
+
+const dockerSession = socketSession('docker');
+
+/**
+ * Sends the request and disconnects once it is answered
+ * @param {string} request
+ * @returns {Promise} - Resolved with the request logged for the connection
+ */
+const session = (request) => dockerSession((client) => {
+	client.write(request);
+	client.on('data', () => {
+		client.end();
+	});
+});
+
+module.exports = {
+	'User-Agent and path of the request': () => session('GET /v1.41/version HTTP/1.1\r\nHost: 127.0.0.1\r\nUser-Agent: Docker-Client/20.10.7 (linux)\r\n\r\n').then((info) => {
+		assert.strictEqual(info.http_request_path, '/v1.41/version');
+		assert.strictEqual(info.user_agent, 'Docker-Client/20.10.7 (linux)');
+		assert.strictEqual(info.request, 'GET /v1.41/version');
+	})
+};
@@ -0,0 +1,74 @@
+"use strict";
+
+const assert = require('assert');
+const helper = require('./../lib/helper');
+
+module.exports = {
+	'values longer than their column are cut': () => {
+		let row = {'username': 'a'.repeat(200), 'password': 'p', 'user_agent': 'Mozilla/5.0 ' + 'x'.repeat(1000), 'http_request_path': '/' + 'a'.repeat(2000)};
+		helper.fitColumns(row);
+		assert.strictEqual(row.username.length, 128);
+		assert.strictEqual(row.password, 'p');
+		assert.strictEqual(row.user_agent.length, 512);
+		assert.strictEqual(row.http_request_path.length, 1024);
+		assert.strictEqual(row.truncated, true);
+	},
+	'a row which fits is left as it is': () => {
+		let row = {'username': 'admin', 'password': 'admin', 'port': 8080, 'request_headers': 'x'.repeat(10000)};
+		helper.fitColumns(row);
+		assert.deepStrictEqual(row, {'username': 'admin', 'password': 'admin', 'port': 8080, 'request_headers': 'x'.repeat(10000)});
+	},
+	'the recount after an import counts the known scanners too': () => new Promise((resolve, reject) => {
+		let queries = [];
+		let query = helper.mysqlPool.query;
+		helper.mysqlPool.query = (sql, params, callback) => {
+			queries.push(sql);
+			setImmediate(() => callback(null));
+		};
+		helper.reaggregateHours(new Date(2018, 9, 1), new Date(2018, 9, 2), (error) => {
+			helper.mysqlPool.query = query;
+			if (error) return reject(error);
+			let hourly = queries.filter((sql) => sql.includes('INSERT INTO stats_hourly'));
+			try {
+				assert.strictEqual(hourly.length, 3);
+				hourly.forEach((sql) => {
+					assert.ok(sql.includes('SUM(IF(scanner_name IS NULL, 0, count))'), sql);
+					assert.ok(sql.includes('scanners = VALUES(scanners)'), sql);
+				});
+				resolve();
+			} catch (err) {
+				reject(err);
+			}
+		});
+	}),
+	'the monthly stats are emitted once all their tables are there': () => {
+		let getConnection = helper.mysqlPool.getConnection;
+		let stats = (connection) => new Promise((resolve) => {
+			helper.mysqlPool.getConnection = (callback) => setImmediate(() => callback(connection ? null : new Error('no connection'), connection));
+			helper.Mysql.prototype.getMonthlyStats.call({'emit': (event, data, data_without_scanners) => resolve([data, data_without_scanners])});
+		});
+		let tables = ['services', 'credentials', 'requests', 'ips', 'botnets', 'payloads'];
+		helper.monthlyStats.data = {};
+		helper.monthlyStats.data_without_scanners = {};
+
+		return stats(null).then(() => stats({'query': (sql, callback) => setTimeout(() => callback(null, []), sql.includes('http_request_path') ? 50 : 0), 'release': () => {}})).then(([data, data_without_scanners]) => {
+			helper.mysqlPool.getConnection = getConnection;
+			assert.deepStrictEqual(Object.keys(data).sort(), tables.slice().sort());
+			assert.deepStrictEqual(Object.keys(data_without_scanners).sort(), tables.slice().sort());
+		}, (err) => {
+			helper.mysqlPool.getConnection = getConnection;
+			throw err;
+		});
+	},
+	'text sent with the prefix of the compressed values is read back as it was': () => {
+		// Valid compressed text, as an attacker could send it
+		let forged = helper.compress('x'.repeat(1000));
+		assert.ok(forged.startsWith('gz: '));
+		let row = {'request': 'GET /', 'request_headers': forged};
+		helper.compressColumns(row);
+		assert.strictEqual(row.request, 'GET /');
+		assert.notStrictEqual(row.request_headers, forged);
+		assert.strictEqual(helper.decompress(row.request_headers), forged);
+		assert.strictEqual(helper.decompress(helper.compress('gz: short')), 'gz: short');
+	}
+};
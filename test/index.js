@@ -0,0 +1,32 @@
+"use strict";
+
+/**
+ * Runs the cases of every `*.test.js` file of this directory, one after the other: `npm test`
+ * A test file exports its cases, the description to a function throwing (e.g. `assert`) or rejecting on failure
+ */
+
+const fs = require('fs');
+const path = require('path');
+
+process.env.HONEYPOT_CONFIG = path.join(__dirname, '..', 'config.js.template');
+
+let passed = 0;
+let failed = 0;
+let queue = Promise.resolve();
+fs.readdirSync(__dirname).filter((file) => file.endsWith('.test.js')).sort().forEach((file) => {
+	let cases = require(path.join(__dirname, file));
+	Object.keys(cases).forEach((name) => {
+		queue = queue.then(() => cases[name]()).then(() => {
+			passed++;
+			console.log('ok - ' + file + ': ' + name);
+		}, (err) => {
+			failed++;
+			console.log('not ok - ' + file + ': ' + name + '\n' + err.stack);
+		});
+	});
+});
+queue.then(() => {
+	console.log(passed + ' passed, ' + failed + ' failed');
+	// The modules under test start timers, e.g. the connection limiter
+	process.exit(failed === 0 ? 0 : 1);
+});
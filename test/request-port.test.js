@@ -0,0 +1,48 @@
+"use strict";
+
+const assert = require('assert');
+const express = require('express');
+const config = require('./../lib/config');
+const requestPort = require('./../lib/request-port');
+
+/**
+ * Port of a request as the app sees it, without going through a server
+ * @param {boolean} nginx_reverse_proxy
+ * @param socket - `localPort` and `remoteAddress` of the connection
+ * @param headers
+ * @returns {number}
+ */
+const port = (nginx_reverse_proxy, socket, headers) => {
+	let app = express();
+	app.set('trust proxy', ['loopback']);
+	let req = Object.create(app.request);
+	req.headers = headers;
+	req.connection = req.socket = socket;
+
+	let saved = config.nginx_reverse_proxy;
+	config.nginx_reverse_proxy = nginx_reverse_proxy;
+	try {
+		return requestPort(req);
+	} finally {
+		config.nginx_reverse_proxy = saved;
+	}
+};
+
+module.exports = {
+	'the local port of a direct connection': () => {
+		assert.strictEqual(port(false, {'localPort': 8080, 'remoteAddress': '203.0.113.5'}, {'x-forwarded-port': '443'}), 8080);
+	},
+	'X-Forwarded-Port of the trusted proxy': () => {
+		assert.strictEqual(port(true, {'localPort': 30101, 'remoteAddress': '127.0.0.1'}, {'x-forwarded-port': '8443'}), 8443);
+	},
+	'X-Forwarded-Port sent straight by the client is ignored': () => {
+		assert.strictEqual(port(true, {'localPort': 30101, 'remoteAddress': '203.0.113.5'}, {'x-forwarded-port': '8443'}), 80);
+	},
+	'the port of the protocol without X-Forwarded-Port': () => {
+		assert.strictEqual(port(true, {'localPort': 30101, 'remoteAddress': '127.0.0.1'}, {'x-forwarded-proto': 'https'}), 443);
+		assert.strictEqual(port(true, {'localPort': 30101, 'remoteAddress': '127.0.0.1'}, {}), 80);
+	},
+	'an invalid X-Forwarded-Port is ignored': () => {
+		assert.strictEqual(port(true, {'localPort': 30101, 'remoteAddress': '127.0.0.1'}, {'x-forwarded-port': '70000'}), 80);
+	}
+};
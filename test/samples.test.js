@@ -0,0 +1,21 @@
+"use strict";
+
+const assert = require('assert');
+const SampleTracker = require('./../lib/samples');
+
+module.exports = {
+	'only the blocklist hits and the files flagged by VirusTotal are malicious': () => {
+		assert.strictEqual(SampleTracker.isMalicious('blocklist: Mirai'), true);
+		assert.strictEqual(SampleTracker.isMalicious('virustotal: 12/70'), true);
+		assert.strictEqual(SampleTracker.isMalicious('virustotal: 0/70'), false);
+		assert.strictEqual(SampleTracker.isMalicious('virustotal: not found'), false);
+	},
+	'the files VirusTotal doesn\'t know are looked up again after a day': () => {
+		assert.strictEqual(SampleTracker.isDue(null, null), true);
+		assert.strictEqual(SampleTracker.isDue('virustotal: not found', null), true);
+		assert.strictEqual(SampleTracker.isDue('virustotal: not found', new Date(Date.now() - 25 * 3600 * 1000)), true);
+		assert.strictEqual(SampleTracker.isDue('virustotal: not found', new Date()), false);
+		assert.strictEqual(SampleTracker.isDue('virustotal: 0/70', new Date(0)), false);
+		assert.strictEqual(SampleTracker.isDue('blocklist: Mirai', new Date(0)), false);
+	}
+};
@@ -0,0 +1,22 @@
+"use strict";
+
+const net = require('net');
+const CustomSocketServer = require('./../lib/custom-socket-server');
+
+/**
+ * Server of the service for the tests, listening on a free port as the one of the config doesn't matter here
+ * @param {string} service - e.g. `telnet`
+ * @returns {function} - Connects, has the given writer send over the socket, and resolves with the request logged for the connection
+ */
+module.exports = (service) => {
+	const server = CustomSocketServer(0, service);
+
+	return (write) => new Promise((resolve) => {
+		server.once('data', resolve);
+		let client = net.connect(server.servers[0].address().port, '127.0.0.1', () => {
+			write(client);
+		});
+		client.on('data', () => {});
+		client.on('error', () => {});
+	});
+};
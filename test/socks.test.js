@@ -0,0 +1,33 @@
+"use strict";
+
+const assert = require('assert');
+const socketSession = require('./socket-session');
+
+const socksSession = socketSession('socks');
+
+/**
+ * Sends the data and waits for the server to refuse the request
+ * @param {Buffer} data
+ * @returns {Promise} - Resolved with the request logged for the connection
+ */
+const session = (data) => socksSession((client) => {
+	client.write(data);
+});
+
+module.exports = {
+	'SOCKS4 destination and user id': () => session(Buffer.from([0x04, 0x01, 0x00, 0x50, 192, 0, 2, 1, 0x61, 0x62, 0])).then((info) => {
+		assert.strictEqual(info.username, 'ab');
+		assert.strictEqual(info.password, undefined);
+		assert.strictEqual(info.http_request_path, '192.0.2.1:80');
+		assert.ok(info.request.startsWith('SOCKS4 CONNECT to 192.0.2.1:80 from '), info.request);
+	}),
+	'SOCKS5 credentials and destination': () => session(Buffer.concat([
+		Buffer.from([0x05, 0x01, 0x02]),
+		Buffer.from([0x01, 4]), Buffer.from('user'), Buffer.from([4]), Buffer.from('pass'),
+		Buffer.from([0x05, 0x01, 0x00, 0x03, 11]), Buffer.from('example.com'), Buffer.from([0x00, 0x19])
+	])).then((info) => {
+		assert.strictEqual(info.username, 'user');
+		assert.strictEqual(info.password, 'pass');
+		assert.strictEqual(info.http_request_path, 'example.com:25');
+	})
+};
@@ -0,0 +1,81 @@
+"use strict";
+
+const assert = require('assert');
+const config = require('./../lib/config');
+const CustomSocketServer = require('./../lib/custom-socket-server');
+const socketSession = require('./socket-session');
+
+const telnetSession = socketSession('telnet');
+
+/**
+ * Sends the chunks over one connection, one after the other, and disconnects
+ * @param {Array} chunks - Strings, or the milliseconds to wait before the next one
+ * @returns {Promise} - Resolved with the request logged for the connection
+ */
+const session = (chunks) => telnetSession((client) => {
+	chunks.reduce((sent, chunk) => sent.then(() => new Promise((next) => {
+		if (typeof chunk === 'number') return setTimeout(next, chunk);
+		client.write(chunk, next);
+	})), Promise.resolve()).then(() => {
+		client.end();
+	});
+});
+
+module.exports = {
+	'credentials sent back-to-back': () => session(['root\r\nxc3511\r\nenable\r\n']).then((info) => {
+		assert.strictEqual(info.username, 'root');
+		assert.strictEqual(info.password, 'xc3511');
+		assert.strictEqual(info.request_headers, 'enable\r\n');
+	}),
+	'a single word after the username is the password': () => session(['admin\r\n', 'enable\r\n']).then((info) => {
+		assert.strictEqual(info.username, 'admin');
+		assert.strictEqual(info.password, 'enable');
+		assert.strictEqual(info.request_headers, undefined);
+	}),
+	'disconnected after the username': () => session(['admin\r\n']).then((info) => {
+		assert.strictEqual(info.username, 'admin');
+		assert.strictEqual(info.password, undefined);
+		assert.ok(info.request.endsWith(' (no password)'), info.request);
+	}),
+	'credentials without the line ending before the disconnect': () => session(['admin\r\n', 'pass']).then((info) => {
+		assert.strictEqual(info.username, 'admin');
+		assert.strictEqual(info.password, 'pass');
+	}),
+	'a command instead of the password': () => session(['root\r\n/bin/busybox MIRAI\r\n']).then((info) => {
+		assert.strictEqual(info.username, 'root');
+		assert.strictEqual(info.password, undefined);
+		assert.strictEqual(info.request_headers, '/bin/busybox MIRAI\r\n');
+	}),
+	'the shell is started when the password never comes': () => session(['root\r\n', 3500, 'enable\r\n']).then((info) => {
+		assert.strictEqual(info.username, 'root');
+		assert.strictEqual(info.password, undefined);
+		assert.strictEqual(info.request_headers, 'enable\r\n');
+	}),
+	'the last command of a session disconnected at the command limit gets no output': () => {
+		let steps = CustomSocketServer.telnetTranscript('root', 'xc3511', ['', 'id', 'uname -m'], true);
+		assert.strictEqual(steps.length, 6);
+		assert.ok(steps[4].startsWith('id\r\nuid=0(root)'), steps[4]);
+		assert.strictEqual(steps[5], 'uname -m\r\n');
+		assert.ok(CustomSocketServer.telnetTranscript('root', 'xc3511', ['', 'id', 'uname -m'], false)[5].startsWith('uname -m\r\nmips'));
+	},
+	'the session disconnected at the command limit is saved with the end_reason': () => {
+		let max_commands = config.emulation.telnet_max_commands;
+		config.emulation.telnet_max_commands = 2;
+		return session(['root\r\nxc3511\r\nid\r\n\r\n']).then((info) => {
+			assert.strictEqual(info.end_reason, 'command_limit');
+			assert.strictEqual(info.truncated, undefined);
+			assert.strictEqual(info.request_headers, 'id\r\n\r\n');
+		}).finally(() => {
+			config.emulation.telnet_max_commands = max_commands;
+		});
+	},
+	'the session cut off at the session timeout is saved with the end_reason': () => {
+		config.timeouts.telnet_session = 1;
+		return session(['root\r\nxc3511\r\n', 'id\r\n', 1500]).then((info) => {
+			assert.strictEqual(info.end_reason, 'session_timeout');
+			assert.strictEqual(info.truncated, undefined);
+		}).finally(() => {
+			delete config.timeouts.telnet_session;
+		});
+	}
+};
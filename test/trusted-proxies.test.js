@@ -0,0 +1,53 @@
+"use strict";
+
+const assert = require('assert');
+const express = require('express');
+
+/**
+ * App configured the way app.js does it behind the reverse proxy
+ * @param {Array} trusted_proxies
+ */
+const appTrusting = (trusted_proxies) => {
+	let app = express();
+	app.set('trust proxy', trusted_proxies);
+
+	return app;
+};
+
+/**
+ * Request as the app sees it, without going through a server
+ * @param app
+ * @param {string} remoteAddress - Peer of the connection
+ * @param headers
+ */
+const request = (app, remoteAddress, headers) => {
+	let req = Object.create(app.request);
+	req.headers = headers;
+	req.connection = req.socket = {'remoteAddress': remoteAddress};
+
+	return req;
+};
+
+module.exports = {
+	'X-Forwarded-For sent straight by the client is ignored': () => {
+		let app = appTrusting(['loopback']);
+		assert.strictEqual(request(app, '203.0.113.5', {'x-forwarded-for': '198.51.100.1'}).ip, '203.0.113.5');
+	},
+	'X-Forwarded-For of the trusted proxy gives the client': () => {
+		let app = appTrusting(['loopback']);
+		assert.strictEqual(request(app, '127.0.0.1', {'x-forwarded-for': '198.51.100.1'}).ip, '198.51.100.1');
+	},
+	'an address the client prepends to X-Forwarded-For is not taken': () => {
+		let app = appTrusting(['loopback']);
+		assert.strictEqual(request(app, '127.0.0.1', {'x-forwarded-for': '10.0.0.1, 198.51.100.1'}).ip, '198.51.100.1');
+	},
+	'only the configured proxies are trusted': () => {
+		let app = appTrusting(['10.0.0.0/8']);
+		assert.strictEqual(request(app, '10.1.2.3', {'x-forwarded-for': '198.51.100.1'}).ip, '198.51.100.1');
+		assert.strictEqual(request(app, '127.0.0.1', {'x-forwarded-for': '198.51.100.1'}).ip, '127.0.0.1');
+	},
+	'the connection peer is taken without X-Forwarded-For': () => {
+		let app = appTrusting(['loopback']);
+		assert.strictEqual(request(app, '127.0.0.1', {}).ip, '127.0.0.1');
+	}
+};